@@ -0,0 +1,552 @@
+use std::{
+    collections::VecDeque,
+    fmt::{self, Display},
+    time::Instant,
+};
+
+use serde::de::DeserializeOwned;
+
+use crate::{
+    store::Store,
+    telemetry::{ChannelId, Gap, Telemetry, TelemetryHeader},
+};
+
+/// How urgently an alarm needs attention
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Yellow,
+    Red,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Yellow => write!(f, "yellow"),
+            Severity::Red => write!(f, "red"),
+        }
+    }
+}
+
+/// What is checked by an alarm
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Kind {
+    /// The value against its [`Rule::limits`]
+    Limit,
+    /// The change of the value per second against its [`Rule::rate`]
+    Rate,
+    /// Whether the channel of the value stopped updating
+    Stale,
+}
+
+impl Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Kind::Limit => write!(f, "limit"),
+            Kind::Rate => write!(f, "rate"),
+            Kind::Stale => write!(f, "stale"),
+        }
+    }
+}
+
+/// Which side of its limits a value went out on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Low,
+    High,
+}
+
+/// Yellow and red thresholds on both sides of a value
+///
+/// A value at or beyond a threshold raises an alarm of its colour. Once
+/// raised, the value has to come back within the threshold by `hysteresis`
+/// before the alarm clears or is downgraded, so a value hovering around a
+/// threshold does not raise a flood of alarms.
+///
+/// ```
+/// use micromanager_tele::alarm::Limits;
+///
+/// // Battery volts, with a tenth of a volt of hysteresis
+/// let limits = Limits::new().low(10.8, 10.2).high(12.8, 13.2).hysteresis(0.1);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Limits {
+    pub red_low: Option<f64>,
+    pub yellow_low: Option<f64>,
+    pub yellow_high: Option<f64>,
+    pub red_high: Option<f64>,
+    pub hysteresis: f64,
+}
+
+impl Limits {
+    /// No thresholds at all
+    pub const fn new() -> Self {
+        Self {
+            red_low: None,
+            yellow_low: None,
+            yellow_high: None,
+            red_high: None,
+            hysteresis: 0.0,
+        }
+    }
+
+    /// Thresholds below which the value is too low
+    pub fn low(mut self, yellow: f64, red: f64) -> Self {
+        self.yellow_low = Some(yellow);
+        self.red_low = Some(red);
+        self
+    }
+
+    /// Thresholds above which the value is too high
+    pub fn high(mut self, yellow: f64, red: f64) -> Self {
+        self.yellow_high = Some(yellow);
+        self.red_high = Some(red);
+        self
+    }
+
+    pub fn hysteresis(mut self, hysteresis: f64) -> Self {
+        self.hysteresis = hysteresis;
+        self
+    }
+
+    /// The alarm `value` raises, given the severity of the alarm already raised
+    pub fn check(&self, value: f64, raised: Option<Severity>) -> Option<(Severity, Direction)> {
+        // Thresholds of raised alarms are moved back by the hysteresis
+        let margin = |severity| {
+            if raised >= Some(severity) {
+                self.hysteresis
+            } else {
+                0.0
+            }
+        };
+        let high = |threshold: Option<f64>, severity| {
+            threshold.is_some_and(|threshold| value >= threshold - margin(severity))
+        };
+        let low = |threshold: Option<f64>, severity| {
+            threshold.is_some_and(|threshold| value <= threshold + margin(severity))
+        };
+
+        if high(self.red_high, Severity::Red) {
+            Some((Severity::Red, Direction::High))
+        } else if low(self.red_low, Severity::Red) {
+            Some((Severity::Red, Direction::Low))
+        } else if high(self.yellow_high, Severity::Yellow) {
+            Some((Severity::Yellow, Direction::High))
+        } else if low(self.yellow_low, Severity::Yellow) {
+            Some((Severity::Yellow, Direction::Low))
+        } else {
+            None
+        }
+    }
+}
+
+/// Everything checked about a watched value
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Rule {
+    /// Limits of the value itself
+    pub limits: Limits,
+    /// Limits of the change of the value per second of device time
+    pub rate: Limits,
+    /// Severity of the alarm raised when the channel goes stale, if any
+    pub stale: Option<Severity>,
+}
+
+impl Rule {
+    pub const fn new() -> Self {
+        Self {
+            limits: Limits::new(),
+            rate: Limits::new(),
+            stale: None,
+        }
+    }
+
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn rate(mut self, rate: Limits) -> Self {
+        self.rate = rate;
+        self
+    }
+
+    pub fn stale(mut self, severity: Severity) -> Self {
+        self.stale = Some(severity);
+        self
+    }
+}
+
+/// An alarm shown to the operator
+///
+/// Alarms stay shown until they are both cleared and acknowledged, so one
+/// that came and went unnoticed is not missed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Alarm {
+    /// Name of the watched value
+    pub name: &'static str,
+    pub kind: Kind,
+    pub severity: Severity,
+    /// Side of the limits the value is out on, `None` for staleness
+    pub direction: Option<Direction>,
+    /// The latest value or rate checked, `None` for staleness
+    pub value: Option<f64>,
+    /// Ground time the alarm was raised at
+    pub raised: Instant,
+    /// The condition still holds
+    pub active: bool,
+    pub acknowledged: bool,
+}
+
+/// A change of an alarm, as kept in the history
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transition {
+    Raised(Severity),
+    Escalated(Severity),
+    Downgraded(Severity),
+    Cleared,
+    Acknowledged,
+}
+
+impl Display for Transition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Transition::Raised(severity) => write!(f, "raised {}", severity),
+            Transition::Escalated(severity) => write!(f, "escalated to {}", severity),
+            Transition::Downgraded(severity) => write!(f, "downgraded to {}", severity),
+            Transition::Cleared => write!(f, "cleared"),
+            Transition::Acknowledged => write!(f, "acknowledged"),
+        }
+    }
+}
+
+/// An entry of the alarm history
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Entry {
+    /// Ground time of the transition
+    pub at: Instant,
+    pub name: &'static str,
+    pub kind: Kind,
+    pub transition: Transition,
+    /// The value or rate that caused the transition
+    pub value: Option<f64>,
+}
+
+impl Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} alarm {}", self.name, self.kind, self.transition)?;
+
+        if let Some(value) = self.value {
+            write!(f, " at {}", value)?;
+        }
+
+        Ok(())
+    }
+}
+
+type Extract = Box<dyn Fn(&[u8]) -> Option<f64> + Send>;
+
+struct Watch {
+    name: &'static str,
+    channel: ChannelId,
+    extract: Extract,
+    rule: Rule,
+    /// Header and value of the latest packet
+    previous: Option<(TelemetryHeader, f64)>,
+    limit: Option<Alarm>,
+    rate: Option<Alarm>,
+    stale: Option<Alarm>,
+}
+
+impl Watch {
+    fn slot(&mut self, kind: Kind) -> &mut Option<Alarm> {
+        match kind {
+            Kind::Limit => &mut self.limit,
+            Kind::Rate => &mut self.rate,
+            Kind::Stale => &mut self.stale,
+        }
+    }
+}
+
+impl fmt::Debug for Watch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Watch")
+            .field("name", &self.name)
+            .field("channel", &self.channel)
+            .field("rule", &self.rule)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Ground side checking of telemetry against limits
+///
+/// Every value [`watch`](Self::watch)ed is extracted from the telemetry handed
+/// to [`update`](Self::update) and checked against its [`Rule`]. Staleness is
+/// judged by a [`Store`] in [`poll`](Self::poll), which has to be called
+/// regularly since a channel goes stale precisely when nothing arrives.
+#[derive(Debug)]
+pub struct Alarms {
+    watches: Vec<Watch>,
+    history: VecDeque<Entry>,
+    capacity: usize,
+}
+
+impl Alarms {
+    pub fn new() -> Self {
+        Self {
+            watches: Vec::new(),
+            history: VecDeque::new(),
+            capacity: 1000,
+        }
+    }
+
+    /// Check the value `extract`ed from every packet of `T` against `rule`
+    ///
+    /// # Panics
+    ///
+    /// If another value is watched under the same name.
+    pub fn watch<T: Telemetry + DeserializeOwned>(
+        mut self,
+        name: &'static str,
+        extract: impl Fn(&T) -> f64 + Send + 'static,
+        rule: Rule,
+    ) -> Self {
+        assert!(
+            self.watches.iter().all(|watch| watch.name != name),
+            "duplicate watch {}",
+            name
+        );
+
+        self.watches.push(Watch {
+            name,
+            channel: T::CHANNEL,
+            extract: Box::new(move |payload| {
+                postcard::from_bytes::<T>(payload)
+                    .ok()
+                    .map(|value| extract(&value))
+            }),
+            rule,
+            previous: None,
+            limit: None,
+            rate: None,
+            stale: None,
+        });
+
+        self
+    }
+
+    /// Number of transitions kept in the history, a thousand by default, none
+    /// if zero
+    pub fn history_len(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Check the values of a packet received at `now`
+    pub fn update(&mut self, header: &TelemetryHeader, payload: &[u8], now: Instant) {
+        for index in 0..self.watches.len() {
+            let watch = &mut self.watches[index];
+            if watch.channel != header.channel {
+                continue;
+            }
+            let Some(value) = (watch.extract)(payload) else {
+                continue;
+            };
+
+            let gap = watch
+                .previous
+                .map(|(previous, _)| Gap::of(header, &previous));
+            let rate = match (gap, watch.previous) {
+                (Some(Gap::Ahead(_)), Some((previous, previous_value)))
+                    if previous.timestamp < header.timestamp =>
+                {
+                    let elapsed = header
+                        .timestamp
+                        .saturating_duration_since(previous.timestamp);
+                    Some((value - previous_value) / elapsed.as_secs_f64())
+                }
+                // Values arriving out of order say nothing about the rate, and
+                // after a restart of the device it is measured again
+                _ => None,
+            };
+            if gap != Some(Gap::Late) {
+                watch.previous = Some((*header, value));
+            }
+
+            let raised = watch
+                .limit
+                .filter(|alarm| alarm.active)
+                .map(|alarm| alarm.severity);
+            let check = watch.rule.limits.check(value, raised);
+            let check = check.map(|(severity, direction)| (severity, Some(direction)));
+            self.transition(index, Kind::Limit, check, Some(value), now);
+
+            if let Some(rate) = rate {
+                let watch = &self.watches[index];
+                let raised = watch
+                    .rate
+                    .filter(|alarm| alarm.active)
+                    .map(|alarm| alarm.severity);
+                let check = watch.rule.rate.check(rate, raised);
+                let check = check.map(|(severity, direction)| (severity, Some(direction)));
+                self.transition(index, Kind::Rate, check, Some(rate), now);
+            }
+        }
+    }
+
+    /// Raise or clear staleness alarms from the freshness of the channels in `store`
+    pub fn poll(&mut self, store: &Store, now: Instant) {
+        for index in 0..self.watches.len() {
+            let watch = &self.watches[index];
+            let Some(severity) = watch.rule.stale else {
+                continue;
+            };
+
+            let stale = store
+                .freshness(watch.channel, now)
+                .is_some_and(|freshness| freshness.stale);
+            let check = stale.then_some((severity, None));
+
+            self.transition(index, Kind::Stale, check, None, now);
+        }
+    }
+
+    /// Acknowledge an alarm, returning whether there was one to acknowledge
+    pub fn acknowledge(&mut self, name: &str, kind: Kind, now: Instant) -> bool {
+        let Some(watch) = self.watches.iter_mut().find(|watch| watch.name == name) else {
+            return false;
+        };
+        let slot = watch.slot(kind);
+
+        let Some(alarm) = slot.as_mut().filter(|alarm| !alarm.acknowledged) else {
+            return false;
+        };
+        alarm.acknowledged = true;
+        let entry = Entry {
+            at: now,
+            name: alarm.name,
+            kind,
+            transition: Transition::Acknowledged,
+            value: alarm.value,
+        };
+
+        if !alarm.active {
+            *slot = None;
+        }
+        self.record(entry);
+
+        true
+    }
+
+    /// Acknowledge every alarm shown
+    pub fn acknowledge_all(&mut self, now: Instant) {
+        let shown: Vec<_> = self
+            .alarms()
+            .map(|alarm| (alarm.name, alarm.kind))
+            .collect();
+
+        for (name, kind) in shown {
+            self.acknowledge(name, kind, now);
+        }
+    }
+
+    /// The alarms shown, active or not yet acknowledged
+    pub fn alarms(&self) -> impl Iterator<Item = &Alarm> + '_ {
+        self.watches
+            .iter()
+            .flat_map(|watch| [&watch.limit, &watch.rate, &watch.stale])
+            .flatten()
+    }
+
+    /// The most severe active alarm, if any
+    pub fn worst(&self) -> Option<Severity> {
+        self.alarms()
+            .filter(|alarm| alarm.active)
+            .map(|alarm| alarm.severity)
+            .max()
+    }
+
+    /// Transitions of all alarms, oldest first
+    pub fn history(&self) -> impl Iterator<Item = &Entry> + '_ {
+        self.history.iter()
+    }
+
+    fn transition(
+        &mut self,
+        index: usize,
+        kind: Kind,
+        check: Option<(Severity, Option<Direction>)>,
+        value: Option<f64>,
+        now: Instant,
+    ) {
+        let watch = &mut self.watches[index];
+        let name = watch.name;
+        let slot = watch.slot(kind);
+
+        let transition = match (check, slot.as_mut()) {
+            (Some((severity, direction)), Some(alarm)) if alarm.active => {
+                alarm.value = value;
+                alarm.direction = direction;
+
+                if severity > alarm.severity {
+                    alarm.severity = severity;
+                    alarm.acknowledged = false;
+                    Some(Transition::Escalated(severity))
+                } else if severity < alarm.severity {
+                    alarm.severity = severity;
+                    Some(Transition::Downgraded(severity))
+                } else {
+                    None
+                }
+            }
+            (Some((severity, direction)), _) => {
+                *slot = Some(Alarm {
+                    name,
+                    kind,
+                    severity,
+                    direction,
+                    value,
+                    raised: now,
+                    active: true,
+                    acknowledged: false,
+                });
+                Some(Transition::Raised(severity))
+            }
+            (None, Some(alarm)) if alarm.active => {
+                alarm.active = false;
+                alarm.value = value;
+
+                if alarm.acknowledged {
+                    *slot = None;
+                }
+                Some(Transition::Cleared)
+            }
+            (None, _) => None,
+        };
+
+        if let Some(transition) = transition {
+            self.record(Entry {
+                at: now,
+                name,
+                kind,
+                transition,
+                value,
+            });
+        }
+    }
+
+    fn record(&mut self, entry: Entry) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.history.len() == self.capacity {
+            self.history.pop_front();
+        }
+
+        self.history.push_back(entry);
+    }
+}
+
+impl Default for Alarms {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,190 @@
+use core::fmt;
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::telecommand::{CommandHeader, NackReason};
+
+/// Length of the truncated HMAC-SHA256 tag of an authenticated command
+pub const TAG_LEN: usize = 16;
+
+/// Bytes appended to the payload of an authenticated command: the counter
+/// followed by the tag
+pub const TRAILER_LEN: usize = 4 + TAG_LEN;
+
+/// Number of counters below the highest one accepted that are still accepted
+/// once, so commands lost and retransmitted after newer ones get through
+pub const REPLAY_WINDOW: u32 = 64;
+
+/// A secret shared by the ground and a device
+#[derive(Clone)]
+pub struct Key(Hmac<Sha256>);
+
+impl Key {
+    pub fn new(secret: &[u8]) -> Self {
+        Self(Hmac::new_from_slice(secret).expect("HMAC accepts keys of any length"))
+    }
+
+    fn mac(&self, header: &CommandHeader, counter: u32, payload: &[u8]) -> Hmac<Sha256> {
+        let mut mac = self.0.clone();
+        mac.update(&header.sequence.get().to_le_bytes());
+        mac.update(&header.command.get().to_le_bytes());
+        mac.update(&counter.to_le_bytes());
+        mac.update(payload);
+
+        mac
+    }
+}
+
+impl fmt::Debug for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Key(..)")
+    }
+}
+
+/// Ground side authentication of commands
+///
+/// Every command gets a new counter, which the device accepts only once. The
+/// counter has to keep increasing for as long as the key is used, so it must
+/// be saved with [`counter`](Self::counter) and restored when the ground
+/// restarts.
+#[derive(Debug, Clone)]
+pub struct Signer {
+    key: Key,
+    next: u32,
+}
+
+impl Signer {
+    /// Sign commands with `key`, starting with the counter `next`
+    pub fn new(key: Key, next: u32) -> Self {
+        Self { key, next }
+    }
+
+    /// The counter the next command will be signed with
+    pub fn counter(&self) -> u32 {
+        self.next
+    }
+
+    /// The trailer to append to `payload` for the device to accept it
+    pub fn seal(&mut self, header: &CommandHeader, payload: &[u8]) -> [u8; TRAILER_LEN] {
+        let counter = self.next;
+        self.next = self.next.wrapping_add(1);
+
+        let tag = self
+            .key
+            .mac(header, counter, payload)
+            .finalize()
+            .into_bytes();
+
+        let mut trailer = [0; TRAILER_LEN];
+        trailer[..4].copy_from_slice(&counter.to_le_bytes());
+        trailer[4..].copy_from_slice(&tag[..TAG_LEN]);
+        trailer
+    }
+}
+
+/// A command whose tag matched, see [`Verifier::verify`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Verified<'a> {
+    /// The payload without its trailer
+    pub payload: &'a [u8],
+    pub counter: u32,
+}
+
+/// Device side authentication of commands
+///
+/// Rejects commands without a valid tag as
+/// [`Unauthenticated`](NackReason::Unauthenticated), and commands whose
+/// counter was already accepted as [`Replayed`](NackReason::Replayed). The
+/// highest counter accepted should be saved with [`last`](Self::last) and
+/// restored with [`with_last`](Self::with_last), or commands recorded before
+/// a restart of the device can be replayed.
+#[derive(Debug, Clone)]
+pub struct Verifier {
+    key: Key,
+    last: Option<u32>,
+    /// Bit `n` is set once `last - n` was accepted
+    seen: u64,
+}
+
+impl Verifier {
+    pub fn new(key: Key) -> Self {
+        Self {
+            key,
+            last: None,
+            seen: 0,
+        }
+    }
+
+    /// Accept only counters above `last`
+    pub fn with_last(mut self, last: u32) -> Self {
+        self.last = Some(last);
+        // Which counters below it were used is not known, refuse them all
+        self.seen = u64::MAX;
+        self
+    }
+
+    /// The highest counter accepted
+    pub fn last(&self) -> Option<u32> {
+        self.last
+    }
+
+    /// Check the tag of a command, without accepting its counter
+    pub fn verify<'a>(
+        &self,
+        header: &CommandHeader,
+        payload: &'a [u8],
+    ) -> Result<Verified<'a>, NackReason> {
+        let split = payload
+            .len()
+            .checked_sub(TRAILER_LEN)
+            .ok_or(NackReason::Unauthenticated)?;
+        let (payload, trailer) = payload.split_at(split);
+        let counter = u32::from_le_bytes(trailer[..4].try_into().expect("trailer holds a counter"));
+
+        self.key
+            .mac(header, counter, payload)
+            .verify_truncated_left(&trailer[4..])
+            .map_err(|_| NackReason::Unauthenticated)?;
+
+        Ok(Verified { payload, counter })
+    }
+
+    /// Whether `counter` was not accepted yet
+    pub fn is_fresh(&self, counter: u32) -> bool {
+        let Some(last) = self.last else {
+            return true;
+        };
+        if counter > last {
+            return true;
+        }
+
+        let age = last - counter;
+        age < REPLAY_WINDOW && self.seen & (1 << age) == 0
+    }
+
+    /// Accept a counter, so it is refused from now on
+    pub fn accept(&mut self, counter: u32) {
+        match self.last {
+            Some(last) if counter <= last => {
+                let age = last - counter;
+                if age < REPLAY_WINDOW {
+                    self.seen |= 1 << age;
+                }
+            }
+            Some(last) => {
+                let shift = counter - last;
+                self.seen = if shift < REPLAY_WINDOW {
+                    self.seen << shift | 1
+                } else {
+                    1
+                };
+                self.last = Some(counter);
+            }
+            None => {
+                self.seen = 1;
+                self.last = Some(counter);
+            }
+        }
+    }
+}
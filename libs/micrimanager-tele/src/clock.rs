@@ -0,0 +1,199 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::{link::Pulse, time::Timestamp};
+
+/// Heartbeats kept to estimate the clock of the device from
+const WINDOW: usize = 16;
+
+/// Fraction a device clock may run fast or slow by, fits beyond it are taken
+/// to come from bad samples
+const MAX_DRIFT: f64 = 0.01;
+
+/// The relation between the clock of a device and that of the ground
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Estimate {
+    /// Seconds the device clock was ahead of the ground clock at the epoch
+    pub offset: f64,
+    /// Parts per million the device clock runs fast by, negative if slow
+    pub drift: f64,
+    /// Half the shortest round trip the estimate is based on
+    pub uncertainty: Duration,
+    /// Heartbeats the estimate is based on
+    pub samples: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    /// Seconds since the epoch at the middle of the round trip
+    ground: f64,
+    /// Seconds of device time when the heartbeat was answered
+    device: f64,
+    rtt: Duration,
+}
+
+/// Ground side estimate of the clock of a device, from the answers to heartbeats
+///
+/// Works like NTP: the device is assumed to have answered each heartbeat half
+/// way through its round trip. The offset and drift of the device clock are
+/// fitted to the heartbeats with the shortest round trips, which are the ones
+/// least disturbed by queueing on the link.
+///
+/// The epoch must be the one the [`Monitor`](crate::link::Monitor) sending the
+/// heartbeats was created with. A device clock going backwards is taken as a
+/// restart of the device, and the estimate starts over. A fit that makes the
+/// device clock drift implausibly is discarded, keeping the previous estimate.
+#[derive(Debug)]
+pub struct ClockSync {
+    epoch: Instant,
+    wall: SystemTime,
+    samples: VecDeque<Sample>,
+    /// Device seconds at the epoch, and device seconds per ground second
+    fit: Option<(f64, f64)>,
+}
+
+impl ClockSync {
+    /// Estimate a device clock, with `wall` the wall clock time at `epoch`
+    pub fn new(epoch: Instant, wall: SystemTime) -> Self {
+        Self {
+            epoch,
+            wall,
+            samples: VecDeque::with_capacity(WINDOW),
+            fit: None,
+        }
+    }
+
+    /// Refine the estimate with the answer to a heartbeat received at `now`
+    pub fn update(&mut self, pulse: &Pulse, now: Instant) {
+        let sent = Duration::from_micros(pulse.sent.as_micros());
+        let received = now.saturating_duration_since(self.epoch);
+        let rtt = received.saturating_sub(sent);
+        let device = micros_to_secs(pulse.device);
+
+        if self
+            .samples
+            .back()
+            .is_some_and(|sample| device < sample.device)
+        {
+            self.samples.clear();
+            self.fit = None;
+        }
+
+        if self.samples.len() == WINDOW {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(Sample {
+            ground: (sent + rtt / 2).as_secs_f64(),
+            device,
+            rtt,
+        });
+
+        if let Some(fit) = self.fit() {
+            self.fit = Some(fit);
+        }
+    }
+
+    pub fn estimate(&self) -> Option<Estimate> {
+        let (offset, rate) = self.fit?;
+        let best = self.best().count();
+
+        Some(Estimate {
+            offset,
+            drift: (rate - 1.0) * 1e6,
+            uncertainty: self.min_rtt() / 2,
+            samples: best,
+        })
+    }
+
+    /// The ground time a device timestamp corresponds to
+    pub fn to_ground(&self, timestamp: Timestamp) -> Option<Instant> {
+        let (offset, rate) = self.fit?;
+        let seconds = (micros_to_secs(timestamp) - offset) / rate;
+
+        if seconds >= 0.0 {
+            self.epoch
+                .checked_add(Duration::try_from_secs_f64(seconds).ok()?)
+        } else {
+            self.epoch
+                .checked_sub(Duration::try_from_secs_f64(-seconds).ok()?)
+        }
+    }
+
+    /// The wall clock time a device timestamp corresponds to
+    pub fn to_wall(&self, timestamp: Timestamp) -> Option<SystemTime> {
+        let ground = self.to_ground(timestamp)?;
+
+        match ground.checked_duration_since(self.epoch) {
+            Some(since) => self.wall.checked_add(since),
+            None => self.wall.checked_sub(self.epoch.duration_since(ground)),
+        }
+    }
+
+    /// The device time a ground time corresponds to
+    ///
+    /// `None` if there is no estimate yet, or the time is before the device booted.
+    pub fn to_device(&self, instant: Instant) -> Option<Timestamp> {
+        let (offset, rate) = self.fit?;
+        let seconds = match instant.checked_duration_since(self.epoch) {
+            Some(since) => since.as_secs_f64(),
+            None => -self.epoch.duration_since(instant).as_secs_f64(),
+        };
+        let device = offset + seconds * rate;
+
+        let device = Duration::try_from_secs_f64(device).ok()?;
+
+        Some(Timestamp::from(device))
+    }
+
+    fn min_rtt(&self) -> Duration {
+        self.samples
+            .iter()
+            .map(|sample| sample.rtt)
+            .min()
+            .unwrap_or_default()
+    }
+
+    /// Samples with a round trip at most twice the shortest one
+    fn best(&self) -> impl Iterator<Item = &Sample> + '_ {
+        let limit = self.min_rtt() * 2;
+
+        self.samples
+            .iter()
+            .filter(move |sample| sample.rtt <= limit)
+    }
+
+    /// Least squares fit of device time over ground time, unless it is implausible
+    fn fit(&self) -> Option<(f64, f64)> {
+        let count = self.best().count() as f64;
+        let (ground, device) = self.best().fold((0.0, 0.0), |(ground, device), sample| {
+            (ground + sample.ground, device + sample.device)
+        });
+        let (ground, device) = (ground / count, device / count);
+
+        let (covariance, variance) =
+            self.best()
+                .fold((0.0, 0.0), |(covariance, variance), sample| {
+                    let dg = sample.ground - ground;
+                    let dd = sample.device - device;
+
+                    (covariance + dg * dd, variance + dg * dg)
+                });
+
+        // A rate needs samples spread out in time
+        let rate = if variance > 1e-6 {
+            covariance / variance
+        } else {
+            1.0
+        };
+
+        let offset = device - rate * ground;
+
+        ((rate - 1.0).abs() <= MAX_DRIFT && offset.is_finite()).then_some((offset, rate))
+    }
+}
+
+fn micros_to_secs(timestamp: Timestamp) -> f64 {
+    timestamp.as_micros() as f64 / 1e6
+}
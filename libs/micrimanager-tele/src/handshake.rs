@@ -0,0 +1,135 @@
+use heapless::Vec;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    telecommand::{builtin, CommandId, Telecommand},
+    telemetry::{ChannelId, Telemetry},
+};
+
+#[cfg(feature = "std")]
+pub use negotiate::{negotiate, Agreement, Difference, Report};
+
+#[cfg(feature = "std")]
+mod negotiate;
+
+/// Version of the wire format and of the built in telecommands
+///
+/// Ends with differing versions can not talk to each other at all.
+pub const PROTOCOL_VERSION: u16 = 1;
+
+/// Most telemetry packets, and separately telecommands, a [`Schema`] can describe
+pub const MAX_SCHEMA_ENTRIES: usize = 16;
+
+/// Hash of the description of a packet definition
+///
+/// Computed by the derive macros from the names and types of the fields of the
+/// packet, as written. Packets whose hashes differ can not be decoded by the
+/// other end.
+///
+/// The fields of the types a packet is built from are not part of its hash, a
+/// derive macro only sees their names. Changing a struct or enum used as a
+/// field, or what a type alias stands for, leaves the hash as it was, and the
+/// two ends still [`negotiate`] the packet as identical. Such changes need a
+/// new channel or command id.
+pub const fn schema_hash(description: &str) -> u32 {
+    // 32 bit FNV-1a
+    let bytes = description.as_bytes();
+    let mut hash = 0x811c_9dc5_u32;
+
+    let mut index = 0;
+    while index < bytes.len() {
+        hash ^= bytes[index] as u32;
+        hash = hash.wrapping_mul(0x0100_0193);
+        index += 1;
+    }
+
+    hash
+}
+
+/// The definition of a single packet as known to one end
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SchemaEntry<I> {
+    pub id: I,
+    pub hash: u32,
+    /// Name of the packet, only known locally
+    #[serde(skip)]
+    pub name: &'static str,
+}
+
+/// Every packet definition known to one end of a link
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Schema {
+    pub version: u16,
+    pub telemetry: Vec<SchemaEntry<ChannelId>, MAX_SCHEMA_ENTRIES>,
+    pub commands: Vec<SchemaEntry<CommandId>, MAX_SCHEMA_ENTRIES>,
+}
+
+impl Schema {
+    pub const fn new() -> Self {
+        Self {
+            version: PROTOCOL_VERSION,
+            telemetry: Vec::new(),
+            commands: Vec::new(),
+        }
+    }
+
+    /// Add a telemetry packet to the schema
+    ///
+    /// # Panics
+    ///
+    /// If the schema already holds [`MAX_SCHEMA_ENTRIES`] telemetry packets.
+    pub fn telemetry<T: Telemetry>(mut self) -> Self {
+        let entry = SchemaEntry {
+            id: T::CHANNEL,
+            hash: T::SCHEMA,
+            name: T::NAME,
+        };
+
+        if self.telemetry.push(entry).is_err() {
+            panic!("schema is full, can not add {}", T::NAME);
+        }
+
+        self
+    }
+
+    /// Add a telecommand to the schema
+    ///
+    /// # Panics
+    ///
+    /// If the schema already holds [`MAX_SCHEMA_ENTRIES`] telecommands.
+    pub fn command<C: Telecommand>(mut self) -> Self {
+        let entry = SchemaEntry {
+            id: C::ID,
+            hash: C::SCHEMA,
+            name: C::NAME,
+        };
+
+        if self.commands.push(entry).is_err() {
+            panic!("schema is full, can not add {}", C::NAME);
+        }
+
+        self
+    }
+}
+
+impl Default for Schema {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Exchange schemas with the device, sent by the ground on connect
+///
+/// Answered by the [`Dispatcher`](crate::telecommand::Dispatcher) with the
+/// schema of the device, after which the ground can
+/// [`negotiate`] what both ends understand. Starts a new session on the device,
+/// so it forgets about previously executed commands.
+///
+/// Packets are compared by their [`schema_hash`], which does not cover the
+/// layout of the types their fields are made of.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Handshake(pub Schema);
+
+builtin! {
+    Handshake => HANDSHAKE, Schema;
+}
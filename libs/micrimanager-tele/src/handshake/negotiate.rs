@@ -0,0 +1,162 @@
+use std::{
+    collections::HashSet,
+    fmt::{self, Display},
+    hash::Hash,
+};
+
+use crate::{telecommand::CommandId, telemetry::ChannelId};
+
+use super::{Schema, SchemaEntry};
+
+/// A packet definition the two ends do not agree on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Difference<I> {
+    /// Both ends know the packet but define it differently
+    Changed { id: I, name: &'static str },
+    /// Only this end knows the packet
+    LocalOnly { id: I, name: &'static str },
+    /// Only the other end knows the packet
+    RemoteOnly { id: I },
+}
+
+impl<I: Display> Display for Difference<I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Difference::Changed { id, name } => write!(f, "{} ({}) differs", name, id),
+            Difference::LocalOnly { id, name } => {
+                write!(f, "{} ({}) unknown to remote", name, id)
+            }
+            Difference::RemoteOnly { id } => write!(f, "{} unknown to local", id),
+        }
+    }
+}
+
+/// Everything that differs between the schemas of two ends
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    pub local_version: u16,
+    pub remote_version: u16,
+    pub telemetry: Vec<Difference<ChannelId>>,
+    pub commands: Vec<Difference<CommandId>>,
+}
+
+impl Report {
+    /// Whether both ends agree on everything
+    pub fn is_identical(&self) -> bool {
+        self.local_version == self.remote_version
+            && self.telemetry.is_empty()
+            && self.commands.is_empty()
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.local_version != self.remote_version {
+            write!(
+                f,
+                "protocol version {} does not match remote version {}",
+                self.local_version, self.remote_version
+            )?;
+        } else {
+            write!(f, "protocol version {}", self.local_version)?;
+        }
+
+        for difference in &self.telemetry {
+            write!(f, ", telemetry {}", difference)?;
+        }
+
+        for difference in &self.commands {
+            write!(f, ", telecommand {}", difference)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// What two ends with compatible protocol versions can exchange
+#[derive(Debug, Clone)]
+pub struct Agreement {
+    report: Report,
+    telemetry: HashSet<ChannelId>,
+    commands: HashSet<CommandId>,
+}
+
+impl Agreement {
+    /// The differences between the two ends, if any
+    pub fn report(&self) -> &Report {
+        &self.report
+    }
+
+    /// Whether telemetry on the channel is defined identically on both ends
+    ///
+    /// Telemetry on other channels must not be decoded.
+    pub fn accepts_telemetry(&self, channel: ChannelId) -> bool {
+        self.telemetry.contains(&channel)
+    }
+
+    /// Whether the command is defined identically on both ends
+    ///
+    /// Other commands must not be sent.
+    pub fn accepts_command(&self, command: CommandId) -> bool {
+        self.commands.contains(&command)
+    }
+}
+
+/// Compare the local schema with the one received from the other end
+///
+/// Fails if the protocol versions differ. Otherwise only the packets defined
+/// identically on both ends are accepted, and the rest is listed in the
+/// [`Report`].
+pub fn negotiate(local: &Schema, remote: &Schema) -> Result<Agreement, Report> {
+    let (telemetry, telemetry_differences) = compare(&local.telemetry, &remote.telemetry);
+    let (commands, command_differences) = compare(&local.commands, &remote.commands);
+
+    let report = Report {
+        local_version: local.version,
+        remote_version: remote.version,
+        telemetry: telemetry_differences,
+        commands: command_differences,
+    };
+
+    if local.version != remote.version {
+        return Err(report);
+    }
+
+    Ok(Agreement {
+        report,
+        telemetry,
+        commands,
+    })
+}
+
+fn compare<I: Copy + Eq + Hash>(
+    local: &[SchemaEntry<I>],
+    remote: &[SchemaEntry<I>],
+) -> (HashSet<I>, Vec<Difference<I>>) {
+    let mut agreed = HashSet::new();
+    let mut differences = Vec::new();
+
+    for entry in local {
+        match remote.iter().find(|remote| remote.id == entry.id) {
+            Some(remote) if remote.hash == entry.hash => {
+                agreed.insert(entry.id);
+            }
+            Some(_) => differences.push(Difference::Changed {
+                id: entry.id,
+                name: entry.name,
+            }),
+            None => differences.push(Difference::LocalOnly {
+                id: entry.id,
+                name: entry.name,
+            }),
+        }
+    }
+
+    for entry in remote {
+        if !local.iter().any(|local| local.id == entry.id) {
+            differences.push(Difference::RemoteOnly { id: entry.id });
+        }
+    }
+
+    (agreed, differences)
+}
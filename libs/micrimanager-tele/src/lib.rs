@@ -1,15 +1,93 @@
+#![cfg_attr(not(feature = "std"), no_std)]
 #![forbid(unsafe_code)]
 
+// Allow the derive macros to refer to `::micromanager_tele` from inside this crate
+extern crate self as micromanager_tele;
+
+/// Agreement between the ground and a device on what both can understand
+///
+/// Exchanged on connect so that a device running older firmware is detected,
+/// rather than having its packets silently decoded into garbage.
+pub mod handshake;
+
 /// Control remote devices
-/// 
+///
 /// Data returned from a telecommand differs from [`telemetry`](crate::telemetry)
 /// as its value is tied to an action taken on the commands behalf and the result
 /// being lost will cause a de-sync of state between the commander and the commanded
 pub mod telecommand;
 
 /// Collection of metrics from remote devices, often superfluous
-/// 
+///
 /// Telemetry data is meant to be interpreted as stand alone packets and should
 /// not rely on any previous data. Telemetry data must be treated as if it is
 /// always transmitted over a lossy medium, and every other packet has been lost.
 pub mod telemetry;
+
+/// Points in time as seen by the clock of the device that produced them
+pub mod time;
+
+/// Binary encoding of the frames exchanged with devices
+///
+/// Frames are checked with a CRC and delimited with COBS, so a frame corrupted
+/// in transit is dropped rather than decoded into garbage.
+pub mod wire;
+
+/// Limits of telemetry values, checked on the ground to raise alarms
+#[cfg(feature = "std")]
+pub mod alarm;
+
+/// Authentication of telecommands with a key shared by the ground and a device
+///
+/// Keeps anyone else in range of the link from commanding a device.
+pub mod auth;
+
+/// Mapping of device timestamps to ground and wall clock time
+#[cfg(feature = "std")]
+pub mod clock;
+
+/// Health of the link between the ground and a device
+///
+/// Heartbeats tell a quiet device apart from a broken link.
+pub mod link;
+
+/// Log lines of the firmware, forwarded to `tracing` on the ground
+pub mod log;
+
+/// Several independent streams of telemetry sharing a single link
+///
+/// Each [`ChannelId`](crate::telemetry::ChannelId) counts its packets separately
+/// and is delivered to its own subscribers on the ground.
+pub mod mux;
+
+/// Named, typed and bounded settings of a device, tunable from the ground
+pub mod param;
+
+/// Ordering of the frames sent over a link too slow to carry all of them
+pub mod scheduler;
+
+/// Commands stored on the device to be executed at a later time
+///
+/// Lets the ground queue work such as "start logging in 5 seconds, stop a
+/// minute later" without depending on the link at the time it is due.
+pub mod timeline;
+
+/// Append only recordings of the frames received during a session
+#[cfg(feature = "std")]
+pub mod recording;
+
+/// The latest value of every telemetry channel, and how old it is
+///
+/// Also computes the channels derived on the ground from those of a device.
+#[cfg(feature = "std")]
+pub mod store;
+
+/// Blobs too large for a single frame, moved in acknowledged chunks
+///
+/// Used to upload calibration tables and configurations to a device, or to
+/// download them back. Interrupted transfers resume where they stopped.
+pub mod transfer;
+
+/// Movement of encoded frames between the ground and devices
+#[cfg(feature = "std")]
+pub mod transport;
@@ -0,0 +1,33 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{telecommand::builtin, time::Timestamp};
+
+#[cfg(feature = "std")]
+pub use monitor::{LinkState, Monitor, Quality};
+
+#[cfg(feature = "std")]
+mod monitor;
+
+/// Probe the link, sent by the ground at a regular interval
+///
+/// Answered by the [`Dispatcher`](crate::telecommand::Dispatcher) with a
+/// [`Pulse`], without being remembered as an executed command. Heartbeats are
+/// never retransmitted, see [`Commander::untracked`](crate::telecommand::Commander::untracked).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Heartbeat {
+    /// Time the heartbeat was sent at, by the clock of the ground
+    pub sent: Timestamp,
+}
+
+builtin! {
+    Heartbeat => HEARTBEAT, Pulse;
+}
+
+/// The answer of a device to a [`Heartbeat`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Pulse {
+    /// [`Heartbeat::sent`] of the heartbeat answered
+    pub sent: Timestamp,
+    /// Time the heartbeat was received at, by the clock of the device
+    pub device: Timestamp,
+}
@@ -0,0 +1,312 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt::{self, Display},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    telecommand::{CommandFrame, CommandHeader, Commander, Sequence, Status, Telecommand},
+    telemetry::{ChannelId, Gap, TelemetryHeader},
+    time::Timestamp,
+    wire::{self, Frame, Header},
+};
+
+use super::{Heartbeat, Pulse};
+
+/// Consecutive unanswered heartbeats after which the link is considered down
+const MISSED_HEARTBEATS: u32 = 3;
+
+/// What the ground can tell about the link
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkState {
+    /// Nothing was heard from the device yet
+    Unknown,
+    /// Heartbeats are answered and telemetry is arriving
+    Up,
+    /// Heartbeats are answered, but the device is not sending telemetry
+    Quiet,
+    /// Heartbeats go unanswered
+    Down,
+}
+
+impl Display for LinkState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinkState::Unknown => write!(f, "unknown"),
+            LinkState::Up => write!(f, "up"),
+            LinkState::Quiet => write!(f, "quiet"),
+            LinkState::Down => write!(f, "down"),
+        }
+    }
+}
+
+/// Summary of the health of the link
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quality {
+    pub state: LinkState,
+    /// Smoothed round trip time of heartbeats
+    pub rtt: Option<Duration>,
+    /// Round trip time of the latest answered heartbeat
+    pub last_rtt: Option<Duration>,
+    /// Fraction of telemetry lost, from the gaps in its sequence numbers
+    pub telemetry_loss: f64,
+    /// Fraction of heartbeats that went unanswered
+    pub heartbeat_loss: f64,
+    /// Frames received intact
+    pub frames: u64,
+    /// Frames that failed their CRC
+    pub checksum_failures: u64,
+    /// Frames that could not be decoded for any other reason
+    pub malformed: u64,
+    /// Time since anything was heard from the device
+    pub silence: Option<Duration>,
+}
+
+impl Display for Quality {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "link {}", self.state)?;
+
+        if let Some(rtt) = self.rtt {
+            write!(f, ", rtt {:.1}ms", rtt.as_secs_f64() * 1000.0)?;
+        }
+
+        write!(
+            f,
+            ", {:.1}% telemetry lost, {:.1}% heartbeats lost, {} of {} frames corrupt",
+            self.telemetry_loss * 100.0,
+            self.heartbeat_loss * 100.0,
+            self.checksum_failures + self.malformed,
+            self.frames + self.checksum_failures + self.malformed,
+        )
+    }
+}
+
+#[derive(Debug, Default)]
+struct Gaps {
+    latest: Option<TelemetryHeader>,
+    received: u64,
+    lost: u64,
+}
+
+/// Ground side measurement of the quality of the link to a device
+///
+/// Sends a [`Heartbeat`] every [`interval`](Self::interval) and measures how
+/// long the device takes to answer. Every frame received from the device has
+/// to be passed to [`observe`](Self::observe), decoded or not, to count
+/// corrupt frames and gaps in the telemetry.
+#[derive(Debug)]
+pub struct Monitor {
+    epoch: Instant,
+    interval: Duration,
+    timeout: Duration,
+
+    last_sent: Option<Instant>,
+    outstanding: VecDeque<(Sequence, Instant)>,
+    heartbeats: u64,
+    unanswered: u64,
+    missed: u32,
+    rtt: Option<Duration>,
+    last_rtt: Option<Duration>,
+    last_pulse: Option<Pulse>,
+
+    gaps: BTreeMap<ChannelId, Gaps>,
+    last_heard: Option<Instant>,
+    last_telemetry: Option<Instant>,
+    frames: u64,
+    checksum_failures: u64,
+    malformed: u64,
+}
+
+impl Monitor {
+    /// Monitor a link, measuring the time of heartbeats from `epoch`
+    pub fn new(epoch: Instant) -> Self {
+        Self {
+            epoch,
+            interval: Duration::from_secs(1),
+            timeout: Duration::from_secs(2),
+            last_sent: None,
+            outstanding: VecDeque::new(),
+            heartbeats: 0,
+            unanswered: 0,
+            missed: 0,
+            rtt: None,
+            last_rtt: None,
+            last_pulse: None,
+            gaps: BTreeMap::new(),
+            last_heard: None,
+            last_telemetry: None,
+            frames: 0,
+            checksum_failures: 0,
+            malformed: 0,
+        }
+    }
+
+    /// Time between two heartbeats, one second by default
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Time after which a heartbeat is considered lost, two seconds by default
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Give up on old heartbeats, and return a new one to send if one is due
+    pub fn poll(
+        &mut self,
+        commander: &mut Commander<Vec<u8>>,
+        now: Instant,
+    ) -> Result<Option<Vec<u8>>, wire::Error> {
+        while let Some(&(_, sent)) = self.outstanding.front() {
+            if now.saturating_duration_since(sent) < self.timeout {
+                break;
+            }
+
+            self.outstanding.pop_front();
+            self.unanswered += 1;
+            self.missed += 1;
+        }
+
+        let due = self
+            .last_sent
+            .is_none_or(|last_sent| now.saturating_duration_since(last_sent) >= self.interval);
+        if !due {
+            return Ok(None);
+        }
+
+        let heartbeat = Heartbeat {
+            sent: Timestamp::from(now.saturating_duration_since(self.epoch)),
+        };
+        let mut frame = CommandFrame {
+            header: CommandHeader {
+                sequence: commander.untracked(),
+                command: Heartbeat::ID,
+            },
+            payload: postcard::to_stdvec(&heartbeat)?,
+        };
+        commander.seal(&frame.header, &mut frame.payload);
+
+        self.last_sent = Some(now);
+        self.outstanding.push_back((frame.header.sequence, now));
+        self.heartbeats += 1;
+
+        Ok(Some(frame.to_wire()))
+    }
+
+    /// Account for a frame received from the device at `now`
+    ///
+    /// Returns the [`Pulse`] if the frame answered a heartbeat.
+    pub fn observe(
+        &mut self,
+        frame: &Result<Frame<'_>, wire::Error>,
+        now: Instant,
+    ) -> Option<Pulse> {
+        let frame = match frame {
+            Ok(frame) => frame,
+            Err(wire::Error::Checksum) => {
+                self.checksum_failures += 1;
+                return None;
+            }
+            Err(_) => {
+                self.malformed += 1;
+                return None;
+            }
+        };
+
+        self.frames += 1;
+        self.last_heard = Some(now);
+
+        match frame.header {
+            Header::Telemetry(header) => {
+                self.last_telemetry = Some(now);
+
+                let gaps = self.gaps.entry(header.channel).or_default();
+                gaps.received += 1;
+
+                match gaps.latest.map(|latest| Gap::of(&header, &latest)) {
+                    Some(Gap::Ahead(lost)) => gaps.lost += lost as u64,
+                    // Packets from before the expected one are reordered, not lost
+                    Some(Gap::Late) => return None,
+                    Some(Gap::Restart) | None => {}
+                }
+                gaps.latest = Some(header);
+
+                None
+            }
+            Header::Response(header) => {
+                let index = self
+                    .outstanding
+                    .iter()
+                    .position(|&(sequence, _)| sequence == header.sequence)?;
+                let (_, sent) = self.outstanding.remove(index)?;
+
+                if header.status != Status::Ack {
+                    return None;
+                }
+                let pulse: Pulse = frame.decode_payload().ok()?;
+
+                let rtt = now.saturating_duration_since(sent);
+                self.rtt = Some(match self.rtt {
+                    Some(smoothed) => (smoothed * 7 + rtt) / 8,
+                    None => rtt,
+                });
+                self.last_rtt = Some(rtt);
+                self.last_pulse = Some(pulse);
+                self.missed = 0;
+
+                Some(pulse)
+            }
+            Header::Command(_) => None,
+        }
+    }
+
+    /// The latest answer to a heartbeat
+    pub fn last_pulse(&self) -> Option<Pulse> {
+        self.last_pulse
+    }
+
+    /// Summarise the health of the link
+    pub fn quality(&self, now: Instant) -> Quality {
+        let (received, lost) = self.gaps.values().fold((0, 0), |(received, lost), gaps| {
+            (received + gaps.received, lost + gaps.lost)
+        });
+        let answered = self.heartbeats - self.unanswered - self.outstanding.len() as u64;
+        let silence = self
+            .last_heard
+            .map(|last_heard| now.saturating_duration_since(last_heard));
+
+        let state = if self.missed >= MISSED_HEARTBEATS {
+            LinkState::Down
+        } else if self.last_heard.is_none() {
+            LinkState::Unknown
+        } else if self.last_telemetry.is_none_or(|last_telemetry| {
+            now.saturating_duration_since(last_telemetry) > self.interval * MISSED_HEARTBEATS
+        }) {
+            LinkState::Quiet
+        } else {
+            LinkState::Up
+        };
+
+        Quality {
+            state,
+            rtt: self.rtt,
+            last_rtt: self.last_rtt,
+            telemetry_loss: ratio(lost, received + lost),
+            heartbeat_loss: ratio(self.unanswered, self.unanswered + answered),
+            frames: self.frames,
+            checksum_failures: self.checksum_failures,
+            malformed: self.malformed,
+            silence,
+        }
+    }
+}
+
+fn ratio(part: u64, total: u64) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+
+    part as f64 / total as f64
+}
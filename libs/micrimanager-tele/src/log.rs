@@ -0,0 +1,206 @@
+use core::fmt::{self, Display, Write};
+
+use heapless::{String, Vec};
+use serde::{Deserialize, Serialize};
+
+use crate::telemetry::{ChannelId, Telemetry};
+
+#[cfg(feature = "std")]
+pub use bridge::{Bridge, TARGET};
+
+#[cfg(feature = "std")]
+mod bridge;
+
+/// Longest target of a record, usually the module it was logged from
+pub const MAX_TARGET_LEN: usize = 32;
+
+/// Longest message of a record
+pub const MAX_MESSAGE_LEN: usize = 96;
+
+/// Most key value fields of a record
+pub const MAX_FIELDS: usize = 4;
+
+/// Longest key of a field
+pub const MAX_KEY_LEN: usize = 16;
+
+/// Longest string value of a field
+pub const MAX_STR_LEN: usize = 24;
+
+/// Importance of a record, as in `tracing`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Level::Error => write!(f, "ERROR"),
+            Level::Warn => write!(f, "WARN"),
+            Level::Info => write!(f, "INFO"),
+            Level::Debug => write!(f, "DEBUG"),
+            Level::Trace => write!(f, "TRACE"),
+        }
+    }
+}
+
+/// The value of a field of a record
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum FieldValue {
+    Bool(bool),
+    I64(i64),
+    U64(u64),
+    F64(f64),
+    Str(String<MAX_STR_LEN>),
+}
+
+impl Display for FieldValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldValue::Bool(value) => Display::fmt(value, f),
+            FieldValue::I64(value) => Display::fmt(value, f),
+            FieldValue::U64(value) => Display::fmt(value, f),
+            FieldValue::F64(value) => Display::fmt(value, f),
+            FieldValue::Str(value) => write!(f, "{:?}", value.as_str()),
+        }
+    }
+}
+
+macro_rules! field_value {
+    ($($ty:ty => $variant:ident),* $(,)?) => {
+        $(
+            impl From<$ty> for FieldValue {
+                fn from(value: $ty) -> Self {
+                    FieldValue::$variant(value.into())
+                }
+            }
+        )*
+    };
+}
+
+field_value!(
+    bool => Bool,
+    i8 => I64,
+    i16 => I64,
+    i32 => I64,
+    i64 => I64,
+    u8 => U64,
+    u16 => U64,
+    u32 => U64,
+    u64 => U64,
+    f32 => F64,
+    f64 => F64,
+);
+
+/// Cut off after [`MAX_STR_LEN`] bytes
+impl From<&str> for FieldValue {
+    fn from(value: &str) -> Self {
+        let mut string = String::new();
+        truncate_into(&mut string, value);
+
+        FieldValue::Str(string)
+    }
+}
+
+/// A log line of the firmware, sent as telemetry
+///
+/// Text that does not fit is cut off rather than failing, so logging never
+/// gets in the way of the firmware.
+///
+/// ```
+/// use micromanager_tele::log::{Level, LogRecord};
+///
+/// let rate = 400;
+/// let record = LogRecord::new(Level::Warn, "imu", format_args!("sampling at {} Hz", rate))
+///     .with("overruns", 3u32);
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogRecord {
+    pub level: Level,
+    pub target: String<MAX_TARGET_LEN>,
+    pub message: String<MAX_MESSAGE_LEN>,
+    pub fields: Vec<(String<MAX_KEY_LEN>, FieldValue), MAX_FIELDS>,
+    /// Part of the record did not fit and was left out
+    pub truncated: bool,
+}
+
+impl LogRecord {
+    pub fn new(level: Level, target: &str, message: fmt::Arguments<'_>) -> Self {
+        let mut record = Self {
+            level,
+            target: String::new(),
+            message: String::new(),
+            fields: Vec::new(),
+            truncated: false,
+        };
+
+        record.truncated |= truncate_into(&mut record.target, target);
+
+        let mut writer = Truncating {
+            string: &mut record.message,
+            truncated: false,
+        };
+        // Truncating never fails, only formatting implementations can
+        let _ = writer.write_fmt(message);
+        record.truncated |= writer.truncated;
+
+        record
+    }
+
+    /// Attach a key value field to the record
+    pub fn with(mut self, key: &str, value: impl Into<FieldValue>) -> Self {
+        let mut string = String::new();
+        self.truncated |= truncate_into(&mut string, key);
+
+        self.truncated |= self.fields.push((string, value.into())).is_err();
+        self
+    }
+}
+
+impl Telemetry for LogRecord {
+    const CHANNEL: ChannelId = ChannelId::LOG;
+    const NAME: &'static str = "LogRecord";
+    const SCHEMA: u32 = 0;
+}
+
+/// Copy as much of `value` as fits into `string`, returning whether some was left out
+fn truncate_into<const N: usize>(string: &mut String<N>, value: &str) -> bool {
+    let mut writer = Truncating {
+        string,
+        truncated: false,
+    };
+    let _ = writer.write_str(value);
+
+    writer.truncated
+}
+
+struct Truncating<'a, const N: usize> {
+    string: &'a mut String<N>,
+    truncated: bool,
+}
+
+impl<const N: usize> Write for Truncating<'_, N> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        // Later pieces would end the string with fragments of what was cut
+        if self.truncated {
+            return Ok(());
+        }
+
+        if self.string.push_str(s).is_ok() {
+            return Ok(());
+        }
+
+        for c in s.chars() {
+            if self.string.push(c).is_err() {
+                self.truncated = true;
+                break;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,86 @@
+use std::fmt::{self, Display};
+
+use crate::telemetry::Packet;
+
+use super::{FieldValue, Level, LogRecord, MAX_KEY_LEN};
+
+/// Target of the `tracing` events the records of all devices are emitted as
+pub const TARGET: &str = "micromanager_tele::device";
+
+/// Re-emits the log records of a device as `tracing` events
+///
+/// Events have the level and message of the record, the name of the device
+/// and the target and timestamp of the record as `device`, `device.target`
+/// and `device.time` (in microseconds), and all key value fields of the record
+/// as `fields`. The callsites of `tracing` are static, so every event has
+/// [`TARGET`] as its target.
+///
+/// ```no_run
+/// use micromanager_tele::{log::{Bridge, LogRecord}, mux::Demultiplexer};
+///
+/// let bridge = Bridge::new("imu-board");
+/// let mut demultiplexer = Demultiplexer::new();
+/// demultiplexer.subscribe::<LogRecord>(move |packet| bridge.emit(&packet));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Bridge {
+    device: String,
+}
+
+impl Bridge {
+    pub fn new(device: impl Into<String>) -> Self {
+        Self {
+            device: device.into(),
+        }
+    }
+
+    pub fn device(&self) -> &str {
+        &self.device
+    }
+
+    /// Emit a record to the current `tracing` subscriber
+    pub fn emit(&self, packet: &Packet<LogRecord>) {
+        let record = &packet.payload;
+
+        macro_rules! emit {
+            ($level:expr) => {
+                tracing::event!(
+                    target: TARGET,
+                    $level,
+                    device = %self.device,
+                    device.target = %record.target,
+                    device.time = packet.timestamp.as_micros(),
+                    fields = %Fields(&record.fields),
+                    truncated = record.truncated,
+                    "{}",
+                    record.message
+                )
+            };
+        }
+
+        match record.level {
+            Level::Error => emit!(tracing::Level::ERROR),
+            Level::Warn => emit!(tracing::Level::WARN),
+            Level::Info => emit!(tracing::Level::INFO),
+            Level::Debug => emit!(tracing::Level::DEBUG),
+            Level::Trace => emit!(tracing::Level::TRACE),
+        }
+    }
+}
+
+/// Key value fields formatted as `key=value`, separated by spaces
+struct Fields<'a>(&'a [(heapless::String<MAX_KEY_LEN>, FieldValue)]);
+
+impl Display for Fields<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, (key, value)) in self.0.iter().enumerate() {
+            if index > 0 {
+                write!(f, " ")?;
+            }
+
+            write!(f, "{}={}", key, value)?;
+        }
+
+        Ok(())
+    }
+}
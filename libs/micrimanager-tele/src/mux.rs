@@ -0,0 +1,99 @@
+use core::fmt::{self, Display};
+
+use heapless::LinearMap;
+
+use crate::{
+    telemetry::{ChannelId, Packet, Telemetry},
+    wire::{self, Encoder},
+};
+
+#[cfg(feature = "std")]
+pub use demultiplexer::{ChannelStats, Demultiplexer};
+
+#[cfg(feature = "std")]
+mod demultiplexer;
+
+/// Reasons a packet could not be multiplexed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// The channel was not opened with [`Multiplexer::channel`]
+    UnknownChannel(ChannelId),
+    Wire(wire::Error),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::UnknownChannel(channel) => write!(f, "channel {} is not open", channel),
+            Error::Wire(error) => Display::fmt(error, f),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+impl From<wire::Error> for Error {
+    fn from(error: wire::Error) -> Self {
+        Error::Wire(error)
+    }
+}
+
+/// Device side encoding of telemetry from several channels onto one link
+///
+/// Every channel counts its packets in its own sequence space, so the ground
+/// can tell losses on one channel apart from a busy neighbouring channel. At
+/// most `CHANNELS` channels can be opened, and frames of up to `N` bytes encoded.
+#[derive(Debug)]
+pub struct Multiplexer<const N: usize, const CHANNELS: usize> {
+    encoder: Encoder<N>,
+    sequences: LinearMap<ChannelId, u16, CHANNELS>,
+}
+
+impl<const N: usize, const CHANNELS: usize> Multiplexer<N, CHANNELS> {
+    pub const fn new() -> Self {
+        Self {
+            encoder: Encoder::new(),
+            sequences: LinearMap::new(),
+        }
+    }
+
+    /// Open the channel of `T`
+    ///
+    /// # Panics
+    ///
+    /// If the channel is already open, or `CHANNELS` channels are.
+    pub fn channel<T: Telemetry>(mut self) -> Self {
+        match self.sequences.insert(T::CHANNEL, 0) {
+            Ok(None) => {}
+            Ok(Some(_)) => panic!("channel {} of {} is already open", T::CHANNEL, T::NAME),
+            Err(_) => panic!("multiplexer is full, can not open channel of {}", T::NAME),
+        }
+
+        self
+    }
+
+    /// The channels that are open
+    pub fn channels(&self) -> impl Iterator<Item = ChannelId> + '_ {
+        self.sequences.keys().copied()
+    }
+
+    /// Encode a packet with the next sequence number of its channel
+    pub fn telemetry<T: Telemetry>(&mut self, packet: &Packet<T>) -> Result<&[u8], Error> {
+        let sequence = self
+            .sequences
+            .get_mut(&T::CHANNEL)
+            .ok_or(Error::UnknownChannel(T::CHANNEL))?;
+
+        let current = *sequence;
+        *sequence = sequence.wrapping_add(1);
+
+        Ok(self.encoder.telemetry(current, packet)?)
+    }
+}
+
+impl<const N: usize, const CHANNELS: usize> Default for Multiplexer<N, CHANNELS> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,186 @@
+use std::{collections::BTreeMap, fmt, sync::mpsc};
+
+use crate::{
+    telemetry::{ChannelId, Gap, Packet, Telemetry, TelemetryHeader},
+    wire::{self, Frame, Header},
+};
+
+/// Count of the packets received on a single channel
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChannelStats {
+    pub received: u64,
+    /// Packets missing from the sequence space of the channel
+    pub lost: u64,
+    /// Packets arriving after a newer packet, duplicates included
+    pub late: u64,
+    /// Packets a subscriber could not decode
+    pub malformed: u64,
+}
+
+enum Delivery {
+    Delivered,
+    Malformed,
+    /// The subscriber is gone and must be removed
+    Closed,
+}
+
+type Subscriber = Box<dyn FnMut(&TelemetryHeader, &[u8]) -> Delivery + Send>;
+
+#[derive(Default)]
+struct Channel {
+    subscribers: Vec<Subscriber>,
+    /// Header of the latest packet, the one before any late packets
+    latest: Option<TelemetryHeader>,
+    stats: ChannelStats,
+}
+
+/// Ground side routing of the telemetry received over a link to its subscribers
+///
+/// Every channel is tracked in its own sequence space, and has its own
+/// subscribers, so several consumers can share a single link without knowing
+/// about each other.
+#[derive(Default)]
+pub struct Demultiplexer {
+    channels: BTreeMap<ChannelId, Channel>,
+}
+
+impl Demultiplexer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Call `callback` with every packet received on the channel of `T`
+    pub fn subscribe<T: Telemetry>(
+        &mut self,
+        mut callback: impl FnMut(Packet<T>) + Send + 'static,
+    ) {
+        self.subscribe_with(
+            T::CHANNEL,
+            move |header, payload| match postcard::from_bytes(payload) {
+                Ok(payload) => {
+                    callback(Packet::new(header.timestamp, payload));
+
+                    Delivery::Delivered
+                }
+                Err(_) => Delivery::Malformed,
+            },
+        );
+    }
+
+    /// Call `callback` with the header and encoded payload of every packet
+    /// received on `channel`
+    pub fn subscribe_raw(
+        &mut self,
+        channel: ChannelId,
+        mut callback: impl FnMut(&TelemetryHeader, &[u8]) + Send + 'static,
+    ) {
+        self.subscribe_with(channel, move |header, payload| {
+            callback(header, payload);
+
+            Delivery::Delivered
+        });
+    }
+
+    /// Receive every packet received on the channel of `T` from a stream
+    ///
+    /// The subscription ends when the receiver is dropped.
+    pub fn stream<T: Telemetry + Send + 'static>(&mut self) -> mpsc::Receiver<Packet<T>> {
+        let (sender, receiver) = mpsc::channel();
+
+        self.subscribe_with(
+            T::CHANNEL,
+            move |header, payload| match postcard::from_bytes(payload) {
+                Ok(payload) => match sender.send(Packet::new(header.timestamp, payload)) {
+                    Ok(()) => Delivery::Delivered,
+                    Err(_) => Delivery::Closed,
+                },
+                Err(_) => Delivery::Malformed,
+            },
+        );
+
+        receiver
+    }
+
+    /// Route a received frame to the subscribers of its channel
+    ///
+    /// Frames other than telemetry are handed back to the caller, to be passed
+    /// on to a [`Commander`](crate::telecommand::Commander) or similar.
+    pub fn receive<'a>(&mut self, frame: &'a mut [u8]) -> Result<Option<Frame<'a>>, wire::Error> {
+        let frame = wire::decode(frame)?;
+
+        let header = match frame.header {
+            Header::Telemetry(header) => header,
+            _ => return Ok(Some(frame)),
+        };
+
+        let channel = self.channels.entry(header.channel).or_default();
+        channel.stats.received += 1;
+
+        match channel.latest.map(|latest| Gap::of(&header, &latest)) {
+            Some(Gap::Late) => channel.stats.late += 1,
+            Some(Gap::Ahead(lost)) => {
+                channel.stats.lost += lost as u64;
+                channel.latest = Some(header);
+            }
+            Some(Gap::Restart) | None => channel.latest = Some(header),
+        }
+
+        let stats = &mut channel.stats;
+        channel
+            .subscribers
+            .retain_mut(|subscriber| match subscriber(&header, frame.payload) {
+                Delivery::Delivered => true,
+                Delivery::Malformed => {
+                    stats.malformed += 1;
+
+                    true
+                }
+                Delivery::Closed => false,
+            });
+
+        Ok(None)
+    }
+
+    /// Statistics of a channel, zero for channels nothing was received on
+    pub fn stats(&self, channel: ChannelId) -> ChannelStats {
+        self.channels
+            .get(&channel)
+            .map(|channel| channel.stats)
+            .unwrap_or_default()
+    }
+
+    /// Every channel something was received on or subscribed to
+    pub fn channels(&self) -> impl Iterator<Item = (ChannelId, ChannelStats)> + '_ {
+        self.channels
+            .iter()
+            .map(|(&id, channel)| (id, channel.stats))
+    }
+
+    /// Forget the sequence numbers and statistics of all channels
+    ///
+    /// Must be called when the device restarts, keeping the subscribers.
+    pub fn reset(&mut self) {
+        for channel in self.channels.values_mut() {
+            channel.latest = None;
+            channel.stats = ChannelStats::default();
+        }
+    }
+
+    fn subscribe_with(
+        &mut self,
+        channel: ChannelId,
+        subscriber: impl FnMut(&TelemetryHeader, &[u8]) -> Delivery + Send + 'static,
+    ) {
+        self.channels
+            .entry(channel)
+            .or_default()
+            .subscribers
+            .push(Box::new(subscriber));
+    }
+}
+
+impl fmt::Debug for Demultiplexer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.channels()).finish()
+    }
+}
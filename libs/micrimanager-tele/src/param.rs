@@ -0,0 +1,278 @@
+use core::{
+    cmp::Ordering,
+    fmt::{self, Display},
+};
+
+use heapless::{String, Vec};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    telecommand::{builtin, failure_codes},
+    telemetry::{ChannelId, Telemetry},
+};
+
+pub use registry::{Registry, Storage};
+#[cfg(feature = "std")]
+pub use table::Table;
+
+mod registry;
+#[cfg(feature = "std")]
+mod table;
+
+/// Longest name of a parameter
+pub const MAX_NAME_LEN: usize = 24;
+
+/// Parameters described by a single [`ParameterPage`]
+pub const PAGE_LEN: usize = 4;
+
+/// Identifier of a parameter of a device
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct ParamId(u16);
+
+impl ParamId {
+    pub const fn new(id: u16) -> Self {
+        Self(id)
+    }
+
+    pub const fn get(self) -> u16 {
+        self.0
+    }
+}
+
+impl Display for ParamId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "param#{}", self.0)
+    }
+}
+
+/// The type of a parameter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Kind {
+    Bool,
+    U32,
+    I32,
+    F32,
+}
+
+impl Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Kind::Bool => write!(f, "bool"),
+            Kind::U32 => write!(f, "u32"),
+            Kind::I32 => write!(f, "i32"),
+            Kind::F32 => write!(f, "f32"),
+        }
+    }
+}
+
+/// The value of a parameter of any type
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Value {
+    Bool(bool),
+    U32(u32),
+    I32(i32),
+    F32(f32),
+}
+
+impl Value {
+    pub fn kind(&self) -> Kind {
+        match self {
+            Value::Bool(_) => Kind::Bool,
+            Value::U32(_) => Kind::U32,
+            Value::I32(_) => Kind::I32,
+            Value::F32(_) => Kind::F32,
+        }
+    }
+
+    /// Check that the value has the kind of `min` and `max`, and lies between them
+    pub fn check(&self, min: &Value, max: &Value) -> Result<(), Error> {
+        if self.kind() != min.kind() {
+            return Err(Error::WrongKind);
+        }
+
+        let compare = |other: &Value| match (self, other) {
+            (Value::Bool(value), Value::Bool(other)) => value.partial_cmp(other),
+            (Value::U32(value), Value::U32(other)) => value.partial_cmp(other),
+            (Value::I32(value), Value::I32(other)) => value.partial_cmp(other),
+            (Value::F32(value), Value::F32(other)) => value.partial_cmp(other),
+            _ => None,
+        };
+
+        match (compare(min), compare(max)) {
+            (Some(Ordering::Greater | Ordering::Equal), Some(Ordering::Less | Ordering::Equal)) => {
+                Ok(())
+            }
+            _ => Err(Error::OutOfBounds),
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Bool(value) => Display::fmt(value, f),
+            Value::U32(value) => Display::fmt(value, f),
+            Value::I32(value) => Display::fmt(value, f),
+            Value::F32(value) => Display::fmt(value, f),
+        }
+    }
+}
+
+/// A type parameters can have
+pub trait ParamValue: Copy {
+    const KIND: Kind;
+
+    fn into_value(self) -> Value;
+
+    fn from_value(value: Value) -> Option<Self>;
+}
+
+macro_rules! param_value {
+    ($($ty:ty => $variant:ident),* $(,)?) => {
+        $(
+            impl ParamValue for $ty {
+                const KIND: Kind = Kind::$variant;
+
+                fn into_value(self) -> Value {
+                    Value::$variant(self)
+                }
+
+                fn from_value(value: Value) -> Option<Self> {
+                    match value {
+                        Value::$variant(value) => Some(value),
+                        _ => None,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+param_value!(bool => Bool, u32 => U32, i32 => I32, f32 => F32);
+
+/// Declaration of a parameter, shared between the device and the ground
+///
+/// ```
+/// use micromanager_tele::param::{ParamId, Parameter};
+///
+/// const GYRO_GAIN: Parameter<f32> = Parameter {
+///     id: ParamId::new(1),
+///     name: "gyro.gain",
+///     default: 1.0,
+///     min: 0.0,
+///     max: 8.0,
+/// };
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Parameter<T> {
+    pub id: ParamId,
+    /// At most [`MAX_NAME_LEN`] bytes
+    pub name: &'static str,
+    pub default: T,
+    pub min: T,
+    pub max: T,
+}
+
+/// Everything the ground needs to know about a parameter
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Descriptor {
+    pub id: ParamId,
+    pub name: String<MAX_NAME_LEN>,
+    pub default: Value,
+    pub min: Value,
+    pub max: Value,
+}
+
+impl Descriptor {
+    pub fn kind(&self) -> Kind {
+        self.default.kind()
+    }
+}
+
+/// Why a parameter could not be read or written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    UnknownParameter,
+    /// The value does not have the type of the parameter
+    WrongKind,
+    /// The value lies outside the bounds of the parameter
+    OutOfBounds,
+    /// The parameters could not be written to non-volatile memory
+    Storage,
+}
+
+failure_codes! {
+    /// Recover the error a device refused a parameter command with
+    Error {
+        UnknownParameter = 1,
+        WrongKind = 2,
+        OutOfBounds = 3,
+        Storage = 4,
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::UnknownParameter => write!(f, "unknown parameter"),
+            Error::WrongKind => write!(f, "value has the wrong type for the parameter"),
+            Error::OutOfBounds => write!(f, "value out of bounds for the parameter"),
+            Error::Storage => write!(f, "parameters could not be persisted"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+/// Describe the parameters of the device, starting with the `start`th one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ListParameters {
+    pub start: u16,
+}
+
+/// Up to [`PAGE_LEN`] parameters, in response to [`ListParameters`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParameterPage {
+    /// Number of parameters of the device
+    pub total: u16,
+    pub start: u16,
+    pub parameters: Vec<(Descriptor, Value), PAGE_LEN>,
+}
+
+/// Read the value of a parameter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GetParameter {
+    pub id: ParamId,
+}
+
+/// Write the value of a parameter, responded to with the new value
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SetParameter {
+    pub id: ParamId,
+    pub value: Value,
+}
+
+/// Write the values of all parameters to non-volatile memory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PersistParameters;
+
+/// A parameter changed on the device, sent as telemetry
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ParameterChanged {
+    pub id: ParamId,
+    pub value: Value,
+}
+
+builtin! {
+    ListParameters => LIST_PARAMETERS, ParameterPage;
+    GetParameter => GET_PARAMETER, Value;
+    SetParameter => SET_PARAMETER, Value;
+    PersistParameters => PERSIST_PARAMETERS, ();
+}
+
+impl Telemetry for ParameterChanged {
+    const CHANNEL: ChannelId = ChannelId::PARAMETERS;
+    const NAME: &'static str = "ParameterChanged";
+    const SCHEMA: u32 = 0;
+}
@@ -0,0 +1,259 @@
+use heapless::{Deque, String, Vec};
+
+use crate::telecommand::{CommandHandler, Router};
+
+use super::{
+    Descriptor, Error, GetParameter, ListParameters, ParamId, ParamValue, Parameter,
+    ParameterChanged, ParameterPage, PersistParameters, SetParameter, Value, MAX_NAME_LEN,
+    PAGE_LEN,
+};
+
+/// Non-volatile memory parameters are persisted to
+pub trait Storage {
+    type Error;
+
+    /// The persisted value of a parameter, if any
+    fn load(&mut self, id: ParamId) -> Option<Value>;
+
+    /// Persist the value of a parameter
+    fn store(&mut self, id: ParamId, value: Value) -> Result<(), Self::Error>;
+}
+
+/// No non-volatile memory, parameters are lost on reset and can not be persisted
+impl Storage for () {
+    type Error = ();
+
+    fn load(&mut self, _id: ParamId) -> Option<Value> {
+        None
+    }
+
+    fn store(&mut self, _id: ParamId, _value: Value) -> Result<(), ()> {
+        Err(())
+    }
+}
+
+#[derive(Debug)]
+struct Entry {
+    id: ParamId,
+    name: &'static str,
+    default: Value,
+    min: Value,
+    max: Value,
+    value: Value,
+}
+
+/// Device side store of up to `N` typed and bounded parameters
+///
+/// Handles the [`ListParameters`], [`GetParameter`], [`SetParameter`] and
+/// [`PersistParameters`] commands, see [`router`](Self::router). Every change
+/// of a parameter, by the ground or by the device itself, is queued as a
+/// [`ParameterChanged`] notification to be sent as telemetry.
+#[derive(Debug)]
+pub struct Registry<S, const N: usize> {
+    entries: Vec<Entry, N>,
+    changes: Deque<ParamId, N>,
+    storage: S,
+}
+
+impl<S: Storage, const N: usize> Registry<S, N> {
+    pub const fn new(storage: S) -> Self {
+        Self {
+            entries: Vec::new(),
+            changes: Deque::new(),
+            storage,
+        }
+    }
+
+    /// Routes the parameter commands to a registry
+    pub fn router() -> Router<Self, 4> {
+        Router::new()
+            .route::<ListParameters>()
+            .route::<GetParameter>()
+            .route::<SetParameter>()
+            .route::<PersistParameters>()
+    }
+
+    /// Declare a parameter, set to its persisted value if valid or to its default
+    ///
+    /// # Panics
+    ///
+    /// If the name of the parameter is too long, its default is out of its
+    /// bounds, another parameter has the same id or the registry is full.
+    pub fn declare<T: ParamValue>(mut self, parameter: &Parameter<T>) -> Self {
+        assert!(
+            parameter.name.len() <= MAX_NAME_LEN,
+            "parameter name {} is too long",
+            parameter.name
+        );
+        assert!(
+            self.position(parameter.id).is_err(),
+            "duplicate parameter {}",
+            parameter.id
+        );
+
+        let default = parameter.default.into_value();
+        let min = parameter.min.into_value();
+        let max = parameter.max.into_value();
+        assert!(
+            default.check(&min, &max).is_ok(),
+            "default of parameter {} is out of bounds",
+            parameter.name
+        );
+
+        let value = match self.storage.load(parameter.id) {
+            Some(value) if value.check(&min, &max).is_ok() => value,
+            _ => default,
+        };
+
+        let entry = Entry {
+            id: parameter.id,
+            name: parameter.name,
+            default,
+            min,
+            max,
+            value,
+        };
+
+        let index = self.position(parameter.id).unwrap_err();
+        if self.entries.insert(index, entry).is_err() {
+            panic!("registry is full, can not declare {}", parameter.name);
+        }
+
+        self
+    }
+
+    /// The current value of a parameter
+    ///
+    /// # Panics
+    ///
+    /// If the parameter was not declared.
+    pub fn get<T: ParamValue>(&self, parameter: &Parameter<T>) -> T {
+        self.value(parameter.id)
+            .and_then(T::from_value)
+            .unwrap_or_else(|| panic!("parameter {} is not declared", parameter.name))
+    }
+
+    /// Change a parameter from the device
+    pub fn set<T: ParamValue>(&mut self, parameter: &Parameter<T>, value: T) -> Result<(), Error> {
+        self.set_value(parameter.id, value.into_value()).map(|_| ())
+    }
+
+    /// The current value of a parameter, if declared
+    pub fn value(&self, id: ParamId) -> Option<Value> {
+        let index = self.position(id).ok()?;
+
+        Some(self.entries[index].value)
+    }
+
+    /// Change a parameter, returning its new value
+    pub fn set_value(&mut self, id: ParamId, value: Value) -> Result<Value, Error> {
+        let index = self.position(id).map_err(|_| Error::UnknownParameter)?;
+        let entry = &mut self.entries[index];
+
+        value.check(&entry.min, &entry.max)?;
+
+        if entry.value != value {
+            entry.value = value;
+
+            if !self.changes.iter().any(|&changed| changed == id) {
+                // Holds one change per declared parameter, so never full
+                let _ = self.changes.push_back(id);
+            }
+        }
+
+        Ok(value)
+    }
+
+    /// Every declared parameter, ordered by id
+    pub fn descriptors(&self) -> impl Iterator<Item = Descriptor> + '_ {
+        self.entries.iter().map(Entry::descriptor)
+    }
+
+    /// Write the values of all parameters to the storage
+    pub fn persist(&mut self) -> Result<(), Error> {
+        for entry in &self.entries {
+            self.storage
+                .store(entry.id, entry.value)
+                .map_err(|_| Error::Storage)?;
+        }
+
+        Ok(())
+    }
+
+    /// The next change notification to send to the ground
+    pub fn next_change(&mut self) -> Option<ParameterChanged> {
+        let id = self.changes.pop_front()?;
+
+        Some(ParameterChanged {
+            id,
+            value: self.value(id)?,
+        })
+    }
+
+    pub fn storage(&mut self) -> &mut S {
+        &mut self.storage
+    }
+
+    fn position(&self, id: ParamId) -> Result<usize, usize> {
+        self.entries.binary_search_by_key(&id, |entry| entry.id)
+    }
+}
+
+impl Entry {
+    fn descriptor(&self) -> Descriptor {
+        let mut name = String::new();
+        name.push_str(self.name).expect("checked on declaration");
+
+        Descriptor {
+            id: self.id,
+            name,
+            default: self.default,
+            min: self.min,
+            max: self.max,
+        }
+    }
+}
+
+impl<S: Storage, const N: usize> CommandHandler<ListParameters> for Registry<S, N> {
+    type Error = core::convert::Infallible;
+
+    fn handle(&mut self, command: ListParameters) -> Result<ParameterPage, Self::Error> {
+        let parameters = self
+            .entries
+            .iter()
+            .skip(command.start as usize)
+            .take(PAGE_LEN)
+            .map(|entry| (entry.descriptor(), entry.value))
+            .collect();
+
+        Ok(ParameterPage {
+            total: self.entries.len() as u16,
+            start: command.start,
+            parameters,
+        })
+    }
+}
+
+impl<S: Storage, const N: usize> CommandHandler<GetParameter> for Registry<S, N> {
+    type Error = Error;
+
+    fn handle(&mut self, command: GetParameter) -> Result<Value, Error> {
+        self.value(command.id).ok_or(Error::UnknownParameter)
+    }
+}
+
+impl<S: Storage, const N: usize> CommandHandler<SetParameter> for Registry<S, N> {
+    type Error = Error;
+
+    fn handle(&mut self, command: SetParameter) -> Result<Value, Error> {
+        self.set_value(command.id, command.value)
+    }
+}
+
+impl<S: Storage, const N: usize> CommandHandler<PersistParameters> for Registry<S, N> {
+    type Error = Error;
+
+    fn handle(&mut self, PersistParameters: PersistParameters) -> Result<(), Error> {
+        self.persist()
+    }
+}
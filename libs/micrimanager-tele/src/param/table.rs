@@ -0,0 +1,114 @@
+use std::{collections::BTreeMap, fmt};
+
+use super::{Descriptor, Error, ListParameters, ParamId, ParameterPage, SetParameter, Value};
+
+type Listener = Box<dyn FnMut(&Descriptor, Value) + Send>;
+
+/// Ground side copy of the parameters of a device
+///
+/// Filled from the responses to [`ListParameters`], and kept up to date with
+/// the responses to the other parameter commands and the
+/// [`ParameterChanged`](super::ParameterChanged) telemetry.
+#[derive(Default)]
+pub struct Table {
+    parameters: BTreeMap<ParamId, (Descriptor, Value)>,
+    total: Option<u16>,
+    listeners: Vec<Listener>,
+}
+
+impl Table {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the parameters listed in a page
+    ///
+    /// Returns the command fetching the next page, or `None` if every
+    /// parameter was listed.
+    pub fn insert_page(&mut self, page: &ParameterPage) -> Option<ListParameters> {
+        self.total = Some(page.total);
+
+        for (descriptor, value) in &page.parameters {
+            self.parameters
+                .insert(descriptor.id, (descriptor.clone(), *value));
+            self.notify(descriptor.id);
+        }
+
+        let next = page.start as usize + page.parameters.len();
+        (next < page.total as usize && !page.parameters.is_empty())
+            .then_some(ListParameters { start: next as u16 })
+    }
+
+    /// Whether every parameter of the device was listed
+    pub fn is_complete(&self) -> bool {
+        self.total == Some(self.parameters.len() as u16)
+    }
+
+    /// Record the value of a parameter as reported by the device
+    pub fn update(&mut self, id: ParamId, value: Value) {
+        let changed = match self.parameters.get_mut(&id) {
+            Some((_, current)) if *current != value => {
+                *current = value;
+                true
+            }
+            _ => false,
+        };
+
+        if changed {
+            self.notify(id);
+        }
+    }
+
+    /// Call `listener` with every parameter listed or changed from now on
+    pub fn subscribe(&mut self, listener: impl FnMut(&Descriptor, Value) + Send + 'static) {
+        self.listeners.push(Box::new(listener));
+    }
+
+    pub fn get(&self, id: ParamId) -> Option<(&Descriptor, Value)> {
+        self.parameters
+            .get(&id)
+            .map(|(descriptor, value)| (descriptor, *value))
+    }
+
+    pub fn find(&self, name: &str) -> Option<(&Descriptor, Value)> {
+        self.iter().find(|(descriptor, _)| descriptor.name == name)
+    }
+
+    /// Every listed parameter, ordered by id
+    pub fn iter(&self) -> impl Iterator<Item = (&Descriptor, Value)> + '_ {
+        self.parameters
+            .values()
+            .map(|(descriptor, value)| (descriptor, *value))
+    }
+
+    /// The command setting the parameter called `name`, checked against its bounds
+    pub fn set(&self, name: &str, value: Value) -> Result<SetParameter, Error> {
+        let (descriptor, _) = self.find(name).ok_or(Error::UnknownParameter)?;
+
+        value.check(&descriptor.min, &descriptor.max)?;
+
+        Ok(SetParameter {
+            id: descriptor.id,
+            value,
+        })
+    }
+
+    fn notify(&mut self, id: ParamId) {
+        if let Some((descriptor, value)) = self.parameters.get(&id) {
+            for listener in &mut self.listeners {
+                listener(descriptor, *value);
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Table {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(
+                self.iter()
+                    .map(|(descriptor, value)| (descriptor.name.as_str(), value)),
+            )
+            .finish()
+    }
+}
@@ -0,0 +1,131 @@
+use std::{
+    fmt::{self, Display},
+    io,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crc::{Crc, CRC_32_ISO_HDLC};
+use serde::{Deserialize, Serialize};
+
+use crate::wire;
+
+pub use reader::Reader;
+pub use recorder::Recorder;
+
+mod reader;
+mod recorder;
+
+/// Identifies a recording, at the very start of the file
+const MAGIC: &[u8; 6] = b"MMTLOG";
+/// Version of the recording format, following [`MAGIC`]
+pub const FORMAT_VERSION: u16 = 1;
+const HEADER_LEN: usize = MAGIC.len() + 2;
+
+/// Longest record accepted by the [`Reader`], guarding against corrupt lengths
+const MAX_RECORD_LEN: usize = 1 << 20;
+const LEN_LEN: usize = 4;
+const CRC_LEN: usize = 4;
+const CRC: Crc<u32> = Crc::<u32>::new(&CRC_32_ISO_HDLC);
+
+/// A link frames were received over, declared once per recording
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct LinkId(u16);
+
+impl LinkId {
+    pub const fn new(id: u16) -> Self {
+        Self(id)
+    }
+
+    pub const fn get(self) -> u16 {
+        self.0
+    }
+}
+
+impl Display for LinkId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "link#{}", self.0)
+    }
+}
+
+/// Whether a recorded frame could be decoded when it was received
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum FrameStatus {
+    Valid,
+    /// The frame failed its CRC
+    Checksum,
+    /// The frame could not be decoded for any other reason
+    Malformed,
+}
+
+impl FrameStatus {
+    /// Decode a frame to find its status
+    pub fn of(frame: &[u8]) -> Self {
+        match wire::decode(&mut frame.to_vec()) {
+            Ok(_) => FrameStatus::Valid,
+            Err(wire::Error::Checksum) => FrameStatus::Checksum,
+            Err(_) => FrameStatus::Malformed,
+        }
+    }
+}
+
+/// A single frame read back from a recording
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    /// Wall clock time the frame was received at
+    pub received: SystemTime,
+    pub link: LinkId,
+    pub status: FrameStatus,
+    /// The frame exactly as received, including the trailing delimiter
+    pub frame: Vec<u8>,
+}
+
+/// What is stored in a single record of the file
+#[derive(Debug, Serialize, Deserialize)]
+enum Entry<'a> {
+    Link {
+        id: LinkId,
+        name: &'a str,
+    },
+    Frame {
+        /// Microseconds since the unix epoch
+        received: u64,
+        link: LinkId,
+        status: FrameStatus,
+        frame: &'a [u8],
+    },
+}
+
+fn header() -> [u8; HEADER_LEN] {
+    let mut header = [0; HEADER_LEN];
+    header[..MAGIC.len()].copy_from_slice(MAGIC);
+    header[MAGIC.len()..].copy_from_slice(&FORMAT_VERSION.to_le_bytes());
+
+    header
+}
+
+/// Encode an entry as `length | body | crc32 of body`, lengths little endian
+fn encode(entry: &Entry<'_>) -> io::Result<Vec<u8>> {
+    let body = postcard::to_allocvec(entry)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))?;
+    let len = u32::try_from(body.len())
+        .ok()
+        .filter(|&len| len as usize <= MAX_RECORD_LEN)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "record is too long"))?;
+
+    let mut record = Vec::with_capacity(LEN_LEN + body.len() + CRC_LEN);
+    record.extend_from_slice(&len.to_le_bytes());
+    record.extend_from_slice(&body);
+    record.extend_from_slice(&CRC.checksum(&body).to_le_bytes());
+
+    Ok(record)
+}
+
+fn to_micros(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_micros() as u64
+}
+
+fn from_micros(micros: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_micros(micros)
+}
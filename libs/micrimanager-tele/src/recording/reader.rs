@@ -0,0 +1,177 @@
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, BufReader, Read},
+    path::Path,
+};
+
+use super::{
+    from_micros, header, Entry, LinkId, Record, CRC, CRC_LEN, HEADER_LEN, LEN_LEN, MAGIC,
+    MAX_RECORD_LEN,
+};
+
+/// Iterates the frames of a recording, oldest first
+///
+/// A partially written or corrupt record at the end of the recording, left
+/// behind by a crash, ends the iteration and is reported by
+/// [`is_torn`](Self::is_torn). A corrupt record anywhere else is an error.
+#[derive(Debug)]
+pub struct Reader<R> {
+    inner: R,
+    links: BTreeMap<LinkId, String>,
+    /// Length of the recording up to the end of the last complete record
+    valid_len: u64,
+    torn: bool,
+    done: bool,
+}
+
+impl Reader<BufReader<File>> {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> Reader<R> {
+    /// Read a recording from `inner`, checking its header
+    pub fn new(mut inner: R) -> io::Result<Self> {
+        let expected = header();
+        let mut actual = [0; HEADER_LEN];
+        inner.read_exact(&mut actual)?;
+
+        let (magic, version) = actual.split_at(MAGIC.len());
+        if magic != MAGIC {
+            return Err(invalid("not a telemetry recording"));
+        }
+
+        if version != &expected[MAGIC.len()..] {
+            return Err(invalid("unsupported recording format version"));
+        }
+
+        Ok(Self {
+            inner,
+            links: BTreeMap::new(),
+            valid_len: HEADER_LEN as u64,
+            torn: false,
+            done: false,
+        })
+    }
+
+    /// Name of a link declared in the records read so far
+    pub fn link_name(&self, link: LinkId) -> Option<&str> {
+        self.links.get(&link).map(String::as_str)
+    }
+
+    /// Links declared in the records read so far
+    pub fn links(&self) -> impl Iterator<Item = (LinkId, &str)> {
+        self.links.iter().map(|(&id, name)| (id, name.as_str()))
+    }
+
+    /// Whether the recording ended in a partially written record
+    pub fn is_torn(&self) -> bool {
+        self.torn
+    }
+
+    pub(super) fn valid_len(&self) -> u64 {
+        self.valid_len
+    }
+
+    fn read_record(&mut self) -> io::Result<Option<Record>> {
+        loop {
+            let mut len = [0; LEN_LEN];
+            match read_full(&mut self.inner, &mut len)? {
+                0 => return Ok(None),
+                LEN_LEN => {}
+                _ => return Ok(self.tear()),
+            }
+
+            let len = u32::from_le_bytes(len) as usize;
+            if len > MAX_RECORD_LEN {
+                // A length garbled by a crash claims more than is left
+                let claimed = (len + CRC_LEN) as u64;
+                let left = io::copy(&mut (&mut self.inner).take(claimed), &mut io::sink())?;
+                if left < claimed {
+                    return Ok(self.tear());
+                }
+
+                return Err(invalid("record is too long"));
+            }
+
+            let mut record = vec![0; len + CRC_LEN];
+            if read_full(&mut self.inner, &mut record)? != record.len() {
+                return Ok(self.tear());
+            }
+
+            let (body, crc) = record.split_at(len);
+            if CRC.checksum(body).to_le_bytes() != crc {
+                if read_full(&mut self.inner, &mut [0])? == 0 {
+                    return Ok(self.tear());
+                }
+
+                return Err(invalid("record failed its checksum"));
+            }
+
+            let entry = postcard::from_bytes(body).map_err(invalid)?;
+            self.valid_len += (LEN_LEN + record.len()) as u64;
+
+            match entry {
+                Entry::Link { id, name } => {
+                    self.links.insert(id, name.to_owned());
+                }
+                Entry::Frame {
+                    received,
+                    link,
+                    status,
+                    frame,
+                } => {
+                    return Ok(Some(Record {
+                        received: from_micros(received),
+                        link,
+                        status,
+                        frame: frame.to_vec(),
+                    }))
+                }
+            }
+        }
+    }
+
+    fn tear(&mut self) -> Option<Record> {
+        self.torn = true;
+
+        None
+    }
+}
+
+impl<R: Read> Iterator for Reader<R> {
+    type Item = io::Result<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let record = self.read_record().transpose();
+        self.done = !matches!(record, Some(Ok(_)));
+
+        record
+    }
+}
+
+/// Read until `buffer` is full or the end of the input, returning the length read
+fn read_full(inner: &mut impl Read, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut len = 0;
+
+    while len < buffer.len() {
+        match inner.read(&mut buffer[len..]) {
+            Ok(0) => break,
+            Ok(read) => len += read,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+            Err(error) => return Err(error),
+        }
+    }
+
+    Ok(len)
+}
+
+fn invalid(error: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
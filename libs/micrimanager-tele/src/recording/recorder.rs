@@ -0,0 +1,104 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, BufReader, Seek, SeekFrom, Write},
+    path::Path,
+    time::SystemTime,
+};
+
+use super::{encode, header, to_micros, Entry, FrameStatus, LinkId, Reader};
+
+/// Appends received frames to a recording
+///
+/// Every record is handed to the writer in a single write, so a crash leaves
+/// at most the last record partially written. Such a torn record is ignored by
+/// the [`Reader`] and cut off by [`Recorder::append`].
+#[derive(Debug)]
+pub struct Recorder<W> {
+    inner: W,
+    links: u16,
+}
+
+impl Recorder<File> {
+    /// Start a new recording, refusing to overwrite an existing file
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::new(OpenOptions::new().write(true).create_new(true).open(path)?)
+    }
+
+    /// Continue an existing recording, dropping a torn record at its end
+    pub fn append(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+
+        let mut reader = Reader::new(BufReader::new(&file))?;
+        for record in &mut reader {
+            record?;
+        }
+
+        let links = reader.links().count() as u16;
+        let end = reader.valid_len();
+
+        file.set_len(end)?;
+        file.seek(SeekFrom::Start(end))?;
+
+        Ok(Self { inner: file, links })
+    }
+
+    /// Wait for every record to reach the disk
+    pub fn sync(&mut self) -> io::Result<()> {
+        self.inner.sync_data()
+    }
+}
+
+impl<W: Write> Recorder<W> {
+    /// Start a new recording in `inner`
+    pub fn new(mut inner: W) -> io::Result<Self> {
+        inner.write_all(&header())?;
+        inner.flush()?;
+
+        Ok(Self { inner, links: 0 })
+    }
+
+    /// Declare a link frames will be recorded from, described by `name`
+    pub fn link(&mut self, name: &str) -> io::Result<LinkId> {
+        let id = LinkId::new(self.links);
+        let next = self
+            .links
+            .checked_add(1)
+            .ok_or_else(|| io::Error::other("too many links"))?;
+
+        self.write(&Entry::Link { id, name })?;
+        self.links = next;
+
+        Ok(id)
+    }
+
+    /// Record a frame received over `link` at `received`
+    ///
+    /// The frame is stored as is, even when it is corrupt, along with the
+    /// result of decoding it.
+    pub fn record(
+        &mut self,
+        link: LinkId,
+        frame: &[u8],
+        received: SystemTime,
+    ) -> io::Result<FrameStatus> {
+        let status = FrameStatus::of(frame);
+
+        self.write(&Entry::Frame {
+            received: to_micros(received),
+            link,
+            status,
+            frame,
+        })?;
+
+        Ok(status)
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    fn write(&mut self, entry: &Entry<'_>) -> io::Result<()> {
+        self.inner.write_all(&encode(entry)?)?;
+        self.inner.flush()
+    }
+}
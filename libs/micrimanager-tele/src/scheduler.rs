@@ -0,0 +1,315 @@
+use core::{
+    fmt::{self, Display},
+    time::Duration,
+};
+
+use heapless::{Deque, Vec};
+
+use crate::{
+    telemetry::{ChannelId, Telemetry},
+    time::Timestamp,
+};
+
+/// Reasons a frame could not be queued
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// The channel was not added with [`Scheduler::channel`]
+    UnknownChannel(ChannelId),
+    /// The frame is longer than the scheduler can hold
+    TooLong,
+    /// Too many responses are already waiting to be sent
+    Full,
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::UnknownChannel(channel) => {
+                write!(f, "channel {} is not scheduled", channel)
+            }
+            Error::TooLong => write!(f, "frame too long to schedule"),
+            Error::Full => write!(f, "response queue is full"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+/// A rate limit, in bytes of encoded frames
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Budget {
+    pub bytes_per_second: u32,
+    /// Bytes that may be sent at once after a quiet period
+    ///
+    /// A frame longer than the burst is let through once the full burst is
+    /// available, rather than never. A burst of zero lets nothing through.
+    pub burst: u32,
+}
+
+/// How the telemetry of a channel is scheduled
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChannelPolicy {
+    /// Channels with higher priorities are sent first
+    pub priority: u8,
+    /// Bandwidth the channel may use, on top of the budget of the link
+    pub budget: Option<Budget>,
+    /// Age after which a queued packet is dropped rather than sent
+    pub max_age: Option<Duration>,
+}
+
+/// Count of the packets of a single channel
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChannelCounters {
+    pub sent: u64,
+    /// Packets replaced by a newer packet before they could be sent
+    pub superseded: u64,
+    /// Packets that waited longer than their [`max_age`](ChannelPolicy::max_age)
+    pub stale: u64,
+}
+
+impl ChannelCounters {
+    pub fn dropped(&self) -> u64 {
+        self.superseded + self.stale
+    }
+}
+
+/// Token bucket counting in byte microseconds, to not lose fractional bytes
+#[derive(Debug)]
+struct Bucket {
+    budget: Budget,
+    credit: u64,
+    updated: Option<Timestamp>,
+}
+
+impl Bucket {
+    const fn new(budget: Budget) -> Self {
+        Self {
+            budget,
+            credit: budget.burst as u64 * 1_000_000,
+            updated: None,
+        }
+    }
+
+    fn refill(&mut self, now: Timestamp) {
+        if let Some(updated) = self.updated {
+            let elapsed = now.saturating_duration_since(updated).as_micros() as u64;
+
+            self.credit = self
+                .credit
+                .saturating_add(elapsed.saturating_mul(self.budget.bytes_per_second as u64))
+                .min(self.budget.burst as u64 * 1_000_000);
+        }
+
+        self.updated = Some(now);
+    }
+
+    fn allows(&self, len: usize) -> bool {
+        self.budget.burst > 0
+            && self.credit >= len.min(self.budget.burst as usize) as u64 * 1_000_000
+    }
+
+    fn take(&mut self, len: usize) {
+        self.credit = self.credit.saturating_sub(len as u64 * 1_000_000);
+    }
+}
+
+#[derive(Debug)]
+struct Slot<const N: usize> {
+    channel: ChannelId,
+    policy: ChannelPolicy,
+    bucket: Option<Bucket>,
+    /// Newest packet of the channel, with the time it was queued at
+    pending: Option<(Timestamp, Vec<u8, N>)>,
+    last_sent: Option<Timestamp>,
+    counters: ChannelCounters,
+}
+
+/// Device side ordering of the frames sent to the ground over a slow link
+///
+/// Telecommand responses are always sent first, in order, so telemetry can
+/// never starve them. Telemetry is sent by priority, and only the newest
+/// packet of every channel is kept: a packet that can not be sent before the
+/// next one arrives is dropped rather than queued, as is a packet older than
+/// the [`max_age`](ChannelPolicy::max_age) of its channel.
+///
+/// Frames are at most `N` bytes, up to `RESPONSES` responses can be waiting
+/// and up to `CHANNELS` channels scheduled.
+#[derive(Debug)]
+pub struct Scheduler<const N: usize, const CHANNELS: usize, const RESPONSES: usize> {
+    link: Option<Bucket>,
+    responses: Deque<Vec<u8, N>, RESPONSES>,
+    slots: Vec<Slot<N>, CHANNELS>,
+    current: Vec<u8, N>,
+}
+
+impl<const N: usize, const CHANNELS: usize, const RESPONSES: usize>
+    Scheduler<N, CHANNELS, RESPONSES>
+{
+    pub const fn new() -> Self {
+        Self {
+            link: None,
+            responses: Deque::new(),
+            slots: Vec::new(),
+            current: Vec::new(),
+        }
+    }
+
+    /// Limit the bandwidth used on the link as a whole
+    pub fn link_budget(mut self, budget: Budget) -> Self {
+        self.link = Some(Bucket::new(budget));
+        self
+    }
+
+    /// Schedule the telemetry of `T` according to `policy`
+    ///
+    /// # Panics
+    ///
+    /// If the channel is already scheduled, or `CHANNELS` channels are.
+    pub fn channel<T: Telemetry>(mut self, policy: ChannelPolicy) -> Self {
+        if self.slots.iter().any(|slot| slot.channel == T::CHANNEL) {
+            panic!("channel {} of {} is already scheduled", T::CHANNEL, T::NAME);
+        }
+
+        let slot = Slot {
+            channel: T::CHANNEL,
+            policy,
+            bucket: policy.budget.map(Bucket::new),
+            pending: None,
+            last_sent: None,
+            counters: ChannelCounters::default(),
+        };
+
+        if self.slots.push(slot).is_err() {
+            panic!("scheduler is full, can not add channel of {}", T::NAME);
+        }
+
+        self
+    }
+
+    /// Queue an encoded telecommand response
+    pub fn response(&mut self, frame: &[u8]) -> Result<(), Error> {
+        let frame = Vec::from_slice(frame).map_err(|()| Error::TooLong)?;
+
+        self.responses.push_back(frame).map_err(|_| Error::Full)
+    }
+
+    /// Queue an encoded telemetry packet, replacing the pending packet of its channel
+    pub fn telemetry(
+        &mut self,
+        channel: ChannelId,
+        frame: &[u8],
+        now: Timestamp,
+    ) -> Result<(), Error> {
+        let slot = self
+            .slots
+            .iter_mut()
+            .find(|slot| slot.channel == channel)
+            .ok_or(Error::UnknownChannel(channel))?;
+        let frame = Vec::from_slice(frame).map_err(|()| Error::TooLong)?;
+
+        if slot.pending.replace((now, frame)).is_some() {
+            slot.counters.superseded += 1;
+        }
+
+        Ok(())
+    }
+
+    /// The next frame to send, if the budgets allow sending one now
+    pub fn poll(&mut self, now: Timestamp) -> Option<&[u8]> {
+        if let Some(link) = &mut self.link {
+            link.refill(now);
+        }
+
+        for slot in &mut self.slots {
+            if let Some(bucket) = &mut slot.bucket {
+                bucket.refill(now);
+            }
+
+            if let (Some((queued, _)), Some(max_age)) = (&slot.pending, slot.policy.max_age) {
+                if now.saturating_duration_since(*queued) > max_age {
+                    slot.pending = None;
+                    slot.counters.stale += 1;
+                }
+            }
+        }
+
+        let link = &self.link;
+        let link_allows = |len| link.as_ref().is_none_or(|link| link.allows(len));
+
+        // Telemetry waits for every response, even when it would fit the budget
+        if let Some(response) = self.responses.front() {
+            if !link_allows(response.len()) {
+                return None;
+            }
+
+            let response = self.responses.pop_front().expect("front exists");
+            return Some(self.send(response));
+        }
+
+        let slot = self
+            .slots
+            .iter_mut()
+            .filter(|slot| match &slot.pending {
+                Some((_, frame)) => {
+                    slot.bucket
+                        .as_ref()
+                        .is_none_or(|bucket| bucket.allows(frame.len()))
+                        && link_allows(frame.len())
+                }
+                None => false,
+            })
+            // Highest priority first, then the channel that waited longest
+            .min_by(|a, b| {
+                b.policy
+                    .priority
+                    .cmp(&a.policy.priority)
+                    .then_with(|| a.last_sent.cmp(&b.last_sent))
+            })?;
+
+        let (_, frame) = slot.pending.take().expect("only pending slots are chosen");
+        if let Some(bucket) = &mut slot.bucket {
+            bucket.take(frame.len());
+        }
+        slot.last_sent = Some(now);
+        slot.counters.sent += 1;
+
+        Some(self.send(frame))
+    }
+
+    /// Counters of a scheduled channel
+    pub fn counters(&self, channel: ChannelId) -> Option<ChannelCounters> {
+        self.slots
+            .iter()
+            .find(|slot| slot.channel == channel)
+            .map(|slot| slot.counters)
+    }
+
+    /// Telemetry packets dropped over all channels
+    pub fn dropped(&self) -> u64 {
+        self.slots.iter().map(|slot| slot.counters.dropped()).sum()
+    }
+
+    /// Whether nothing is waiting to be sent
+    pub fn is_idle(&self) -> bool {
+        self.responses.is_empty() && self.slots.iter().all(|slot| slot.pending.is_none())
+    }
+
+    fn send(&mut self, frame: Vec<u8, N>) -> &[u8] {
+        if let Some(link) = &mut self.link {
+            link.take(frame.len());
+        }
+
+        self.current = frame;
+
+        &self.current
+    }
+}
+
+impl<const N: usize, const CHANNELS: usize, const RESPONSES: usize> Default
+    for Scheduler<N, CHANNELS, RESPONSES>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
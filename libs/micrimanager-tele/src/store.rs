@@ -0,0 +1,322 @@
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    telemetry::{ChannelId, Gap, Telemetry, TelemetryHeader},
+    time::Timestamp,
+    wire,
+};
+
+pub use derived::Inputs;
+
+use derived::Derivation;
+
+mod derived;
+
+/// How up to date the value of a channel is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Freshness {
+    /// Ground time the latest value was received at
+    pub received: Instant,
+    /// Time since the latest value was received
+    pub age: Duration,
+    /// Time expected between two values, configured or estimated
+    pub interval: Option<Duration>,
+    /// No value was received for several expected intervals
+    pub stale: bool,
+}
+
+/// The latest value of a channel
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Reading<T> {
+    pub value: T,
+    /// Device time the value was sampled at
+    pub timestamp: Timestamp,
+    pub sequence: u16,
+    pub freshness: Freshness,
+}
+
+#[derive(Debug)]
+struct Latest {
+    header: TelemetryHeader,
+    payload: Vec<u8>,
+    received: Instant,
+    /// Configured time between values
+    expected: Option<Duration>,
+    /// Smoothed measured time between values
+    estimate: Option<Duration>,
+}
+
+/// Ground side memory of the latest value of every telemetry channel
+///
+/// Keeps track of when each channel was last heard from, so displays can tell
+/// a current value from one that stopped updating. The time expected between
+/// two values is estimated from the values received unless configured with
+/// [`expect`](Self::expect), and a channel is stale once no value was received
+/// for [`tolerance`](Self::tolerance) times that interval.
+///
+/// Channels computed on the ground from others are declared with
+/// [`derive`](Self::derive), and read like the channels of the device.
+#[derive(Debug)]
+pub struct Store {
+    channels: BTreeMap<ChannelId, Latest>,
+    expected: BTreeMap<ChannelId, Duration>,
+    tolerance: u32,
+    derived: Vec<Derivation>,
+}
+
+impl Store {
+    pub fn new() -> Self {
+        Self {
+            channels: BTreeMap::new(),
+            expected: BTreeMap::new(),
+            tolerance: 3,
+            derived: Vec::new(),
+        }
+    }
+
+    /// Expect a value of `T` every `interval`, instead of estimating it
+    pub fn expect<T: Telemetry>(mut self, interval: Duration) -> Self {
+        self.expected.insert(T::CHANNEL, interval);
+
+        if let Some(latest) = self.channels.get_mut(&T::CHANNEL) {
+            latest.expected = Some(interval);
+        }
+
+        self
+    }
+
+    /// Number of expected intervals without a value after which a channel is stale
+    pub fn tolerance(mut self, intervals: u32) -> Self {
+        self.tolerance = intervals;
+        self
+    }
+
+    /// Compute `T` from `I` every time a value of one of its inputs is stored
+    ///
+    /// `I` is a tuple of the input types, whose latest values are handed to
+    /// `compute` once a value of each was received. Inputs may be channels
+    /// derived before `T`. A derived channel is as fresh as its least fresh
+    /// input, and goes stale with it.
+    ///
+    /// ```
+    /// # use micromanager_tele::{store::Store, telemetry::Telemetry};
+    /// # use serde::{Deserialize, Serialize};
+    /// #[derive(Serialize, Deserialize, Telemetry)]
+    /// #[telemetry(channel = 1)]
+    /// struct Volts(f32);
+    ///
+    /// #[derive(Serialize, Deserialize, Telemetry)]
+    /// #[telemetry(channel = 2)]
+    /// struct Amps(f32);
+    ///
+    /// #[derive(Serialize, Deserialize, Telemetry)]
+    /// #[telemetry(channel = 0x100)]
+    /// struct Watts(f32);
+    ///
+    /// let store = Store::new().derive(|(Volts(v), Amps(a))| Watts(v * a));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// If `T` is already derived, is one of its own inputs, or is an input of a
+    /// channel derived before it.
+    pub fn derive<T: Telemetry, I: Inputs>(
+        mut self,
+        compute: impl Fn(I) -> T + Send + 'static,
+    ) -> Self {
+        assert!(
+            !self.is_derived(T::CHANNEL),
+            "channel {} is already derived",
+            T::CHANNEL
+        );
+        assert!(
+            !I::CHANNELS.contains(&T::CHANNEL),
+            "channel {} is derived from itself",
+            T::CHANNEL
+        );
+        if let Some(dependent) = self
+            .derived
+            .iter()
+            .find(|derivation| derivation.inputs.contains(&T::CHANNEL))
+        {
+            panic!(
+                "channel {} is derived after channel {}, which is derived from it",
+                T::CHANNEL,
+                dependent.channel
+            );
+        }
+
+        self.derived.push(Derivation::new(compute));
+        self
+    }
+
+    /// Store a value received at `now`
+    ///
+    /// Returns whether the value replaced the latest value of its channel,
+    /// which it does not when it arrives after a newer value, or when its
+    /// channel is [derived](Self::derive) on the ground. Channels derived from
+    /// the value are computed again.
+    pub fn update(&mut self, header: &TelemetryHeader, payload: &[u8], now: Instant) -> bool {
+        if self.is_derived(header.channel) || !self.insert(header, payload, now) {
+            return false;
+        }
+
+        self.derive_from(header.channel, now);
+        true
+    }
+
+    /// The latest value of `T`, or `None` if none was received yet
+    pub fn get<T: Telemetry>(&self, now: Instant) -> Result<Option<Reading<T>>, wire::Error> {
+        let latest = match self.channels.get(&T::CHANNEL) {
+            Some(latest) => latest,
+            None => return Ok(None),
+        };
+
+        Ok(Some(Reading {
+            value: postcard::from_bytes(&latest.payload)?,
+            timestamp: latest.header.timestamp,
+            sequence: latest.header.sequence,
+            freshness: self.freshness_of(T::CHANNEL, latest, now),
+        }))
+    }
+
+    /// The header and encoded payload of the latest value of a channel
+    pub fn get_raw(&self, channel: ChannelId) -> Option<(&TelemetryHeader, &[u8])> {
+        self.channels
+            .get(&channel)
+            .map(|latest| (&latest.header, latest.payload.as_slice()))
+    }
+
+    /// How up to date the value of a channel is, if any was received
+    pub fn freshness(&self, channel: ChannelId, now: Instant) -> Option<Freshness> {
+        self.channels
+            .get(&channel)
+            .map(|latest| self.freshness_of(channel, latest, now))
+    }
+
+    /// Every channel a value was received on, with its freshness
+    pub fn channels(&self, now: Instant) -> impl Iterator<Item = (ChannelId, Freshness)> + '_ {
+        self.channels
+            .iter()
+            .map(move |(&channel, latest)| (channel, self.freshness_of(channel, latest, now)))
+    }
+
+    fn insert(&mut self, header: &TelemetryHeader, payload: &[u8], now: Instant) -> bool {
+        let latest = match self.channels.get_mut(&header.channel) {
+            Some(latest) => latest,
+            None => {
+                self.channels.insert(
+                    header.channel,
+                    Latest {
+                        header: *header,
+                        payload: payload.to_vec(),
+                        received: now,
+                        expected: self.expected.get(&header.channel).copied(),
+                        estimate: None,
+                    },
+                );
+
+                return true;
+            }
+        };
+
+        latest.estimate = match Gap::of(header, &latest.header) {
+            Gap::Late => return false,
+            Gap::Ahead(lost) => {
+                // Lost values still count towards the rate they were sent at
+                let interval = now.saturating_duration_since(latest.received) / (lost as u32 + 1);
+
+                Some(match latest.estimate {
+                    Some(estimate) => (estimate * 7 + interval) / 8,
+                    None => interval,
+                })
+            }
+            // The device restarted, and its rate may have changed with it
+            Gap::Restart => None,
+        };
+
+        latest.header = *header;
+        latest.payload.clear();
+        latest.payload.extend_from_slice(payload);
+        latest.received = now;
+
+        true
+    }
+
+    fn is_derived(&self, channel: ChannelId) -> bool {
+        self.derived
+            .iter()
+            .any(|derivation| derivation.channel == channel)
+    }
+
+    /// Compute again the channels derived from `channel`, directly or not
+    fn derive_from(&mut self, channel: ChannelId, now: Instant) {
+        let mut changed = vec![channel];
+
+        for derivation in &self.derived {
+            if !derivation
+                .inputs
+                .iter()
+                .any(|input| changed.contains(input))
+            {
+                continue;
+            }
+            let Some((timestamp, payload)) = (derivation.compute)(self) else {
+                continue;
+            };
+
+            let channel = derivation.channel;
+            let sequence = self
+                .channels
+                .get(&channel)
+                .map_or(0, |latest| latest.header.sequence.wrapping_add(1));
+
+            self.channels.insert(
+                channel,
+                Latest {
+                    header: TelemetryHeader {
+                        channel,
+                        sequence,
+                        timestamp,
+                    },
+                    payload,
+                    received: now,
+                    expected: None,
+                    estimate: None,
+                },
+            );
+            changed.push(channel);
+        }
+    }
+
+    fn freshness_of(&self, channel: ChannelId, latest: &Latest, now: Instant) -> Freshness {
+        if let Some(derivation) = self.derived.iter().find(|d| d.channel == channel) {
+            // Every input has a value, or the channel would not have one
+            return derivation
+                .inputs
+                .iter()
+                .filter_map(|&input| self.freshness(input, now))
+                .max_by_key(|freshness| (freshness.stale, freshness.age))
+                .expect("derived channels have inputs");
+        }
+
+        let age = now.saturating_duration_since(latest.received);
+        let interval = latest.expected.or(latest.estimate);
+
+        Freshness {
+            received: latest.received,
+            age,
+            interval,
+            stale: interval.is_some_and(|interval| age > interval * self.tolerance),
+        }
+    }
+}
+
+impl Default for Store {
+    fn default() -> Self {
+        Self::new()
+    }
+}
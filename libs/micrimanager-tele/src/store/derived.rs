@@ -0,0 +1,79 @@
+use std::fmt;
+
+use crate::{
+    telemetry::{ChannelId, Telemetry},
+    time::Timestamp,
+};
+
+use super::Store;
+
+/// The channels a derived channel is computed from
+///
+/// Implemented for tuples of up to four [`Telemetry`] types.
+pub trait Inputs: Sized {
+    const CHANNELS: &'static [ChannelId];
+
+    /// The latest value of every input, and the device time of the newest
+    ///
+    /// Returns `None` until a value of every input was received.
+    fn read(store: &Store) -> Option<(Self, Timestamp)>;
+}
+
+macro_rules! inputs {
+    ($($ty:ident),+) => {
+        impl<$($ty: Telemetry),+> Inputs for ($($ty,)+) {
+            const CHANNELS: &'static [ChannelId] = &[$($ty::CHANNEL),+];
+
+            #[allow(non_snake_case)]
+            fn read(store: &Store) -> Option<(Self, Timestamp)> {
+                let mut timestamp = Timestamp::ZERO;
+
+                $(
+                    let (header, payload) = store.get_raw($ty::CHANNEL)?;
+                    timestamp = timestamp.max(header.timestamp);
+                    let $ty = postcard::from_bytes::<$ty>(payload).ok()?;
+                )+
+
+                Some((($($ty,)+), timestamp))
+            }
+        }
+    };
+}
+
+inputs!(A);
+inputs!(A, B);
+inputs!(A, B, C);
+inputs!(A, B, C, D);
+
+type Compute = Box<dyn Fn(&Store) -> Option<(Timestamp, Vec<u8>)> + Send>;
+
+/// A channel computed from others, see [`Store::derive`]
+pub(super) struct Derivation {
+    pub(super) channel: ChannelId,
+    pub(super) inputs: &'static [ChannelId],
+    pub(super) compute: Compute,
+}
+
+impl Derivation {
+    pub(super) fn new<T: Telemetry, I: Inputs>(compute: impl Fn(I) -> T + Send + 'static) -> Self {
+        Self {
+            channel: T::CHANNEL,
+            inputs: I::CHANNELS,
+            compute: Box::new(move |store| {
+                let (inputs, timestamp) = I::read(store)?;
+                let payload = postcard::to_stdvec(&compute(inputs)).ok()?;
+
+                Some((timestamp, payload))
+            }),
+        }
+    }
+}
+
+impl fmt::Debug for Derivation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Derivation")
+            .field("channel", &self.channel)
+            .field("inputs", &self.inputs)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,260 @@
+use core::fmt::{self, Display};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+#[cfg(feature = "std")]
+pub use commander::{Commander, Event, Outcome, Reconciliation, RetryPolicy};
+pub use dispatcher::{Dispatcher, Reply};
+#[cfg(feature = "std")]
+pub use guard::Guard;
+pub use interlock::{
+    Arm, ArmError, Armed, Disarm, Interlock, Interlocked, Interlocks, InvalidMode, Mode,
+    ModeReport, Modes,
+};
+pub use micromanager_tele_derive::Telecommand;
+pub use resync::{CommandState, Resync, ResyncResponse, MAX_RESYNC};
+pub use router::{CommandHandler, Router};
+
+#[cfg(feature = "std")]
+mod commander;
+mod dispatcher;
+#[cfg(feature = "std")]
+mod guard;
+mod interlock;
+mod resync;
+mod router;
+
+/// Identifier of a kind of telecommand
+///
+/// Identifiers from `0xff00` upwards are reserved for the protocol itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct CommandId(u16);
+
+impl CommandId {
+    /// Identifier of [`Resync`]
+    pub const RESYNC: CommandId = CommandId(0xff00);
+    /// Identifier of [`Handshake`](crate::handshake::Handshake)
+    pub const HANDSHAKE: CommandId = CommandId(0xff01);
+    /// Identifier of [`Heartbeat`](crate::link::Heartbeat)
+    pub const HEARTBEAT: CommandId = CommandId(0xff02);
+    /// Identifier of [`ListParameters`](crate::param::ListParameters)
+    pub const LIST_PARAMETERS: CommandId = CommandId(0xff10);
+    /// Identifier of [`GetParameter`](crate::param::GetParameter)
+    pub const GET_PARAMETER: CommandId = CommandId(0xff11);
+    /// Identifier of [`SetParameter`](crate::param::SetParameter)
+    pub const SET_PARAMETER: CommandId = CommandId(0xff12);
+    /// Identifier of [`PersistParameters`](crate::param::PersistParameters)
+    pub const PERSIST_PARAMETERS: CommandId = CommandId(0xff13);
+    /// Identifier of [`StartUpload`](crate::transfer::StartUpload)
+    pub const START_UPLOAD: CommandId = CommandId(0xff20);
+    /// Identifier of [`UploadChunk`](crate::transfer::UploadChunk)
+    pub const UPLOAD_CHUNK: CommandId = CommandId(0xff21);
+    /// Identifier of [`FinishUpload`](crate::transfer::FinishUpload)
+    pub const FINISH_UPLOAD: CommandId = CommandId(0xff22);
+    /// Identifier of [`StartDownload`](crate::transfer::StartDownload)
+    pub const START_DOWNLOAD: CommandId = CommandId(0xff23);
+    /// Identifier of [`DownloadChunk`](crate::transfer::DownloadChunk)
+    pub const DOWNLOAD_CHUNK: CommandId = CommandId(0xff24);
+    /// Identifier of [`ScheduleCommand`](crate::timeline::ScheduleCommand)
+    pub const SCHEDULE_COMMAND: CommandId = CommandId(0xff30);
+    /// Identifier of [`ListScheduled`](crate::timeline::ListScheduled)
+    pub const LIST_SCHEDULED: CommandId = CommandId(0xff31);
+    /// Identifier of [`CancelScheduled`](crate::timeline::CancelScheduled)
+    pub const CANCEL_SCHEDULED: CommandId = CommandId(0xff32);
+    /// Identifier of [`QueryScheduled`](crate::timeline::QueryScheduled)
+    pub const QUERY_SCHEDULED: CommandId = CommandId(0xff33);
+    /// Identifier of [`Arm`]
+    pub const ARM: CommandId = CommandId(0xff40);
+    /// Identifier of [`Disarm`]
+    pub const DISARM: CommandId = CommandId(0xff41);
+
+    pub const fn new(id: u16) -> Self {
+        Self(id)
+    }
+
+    pub const fn get(self) -> u16 {
+        self.0
+    }
+}
+
+impl Display for CommandId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "cmd#{}", self.0)
+    }
+}
+
+/// Wrapping counter identifying a single issued telecommand
+///
+/// Every command issued by the ground gets a new sequence number, which
+/// the device echoes back in its response. Retransmissions of the same command
+/// reuse its sequence number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Sequence(u16);
+
+impl Sequence {
+    pub const fn new(sequence: u16) -> Self {
+        Self(sequence)
+    }
+
+    pub const fn get(self) -> u16 {
+        self.0
+    }
+
+    pub const fn next(self) -> Self {
+        Self(self.0.wrapping_add(1))
+    }
+
+    /// Whether `self` was issued after `other`, taking wrapping into account
+    ///
+    /// Only meaningful for sequence numbers less than half the range apart.
+    pub const fn is_after(self, other: Sequence) -> bool {
+        (self.0.wrapping_sub(other.0) as i16) > 0
+    }
+}
+
+impl Display for Sequence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "seq#{}", self.0)
+    }
+}
+
+/// A command that can be sent to a device
+///
+/// Shared between the device and the ground so both agree on the layout of
+/// the command and of its response.
+pub trait Telecommand: Serialize + DeserializeOwned {
+    /// Identifier the command is sent with
+    const ID: CommandId;
+
+    /// Name of the command, used when reporting schema mismatches
+    const NAME: &'static str;
+
+    /// Hash of the command and response definitions, see
+    /// [`schema_hash`](crate::handshake::schema_hash)
+    ///
+    /// Zero for the commands built into the protocol, which are covered by
+    /// the protocol version instead.
+    const SCHEMA: u32;
+
+    /// Data returned by the device once the command has been executed
+    type Response: Serialize + DeserializeOwned;
+}
+
+/// Implement [`Telecommand`] for commands built into the protocol, given as
+/// `Command => ID, Response;` with the identifier a constant of [`CommandId`]
+macro_rules! builtin {
+    ($($command:ident => $id:ident, $response:ty;)*) => {
+        $(
+            impl $crate::telecommand::Telecommand for $command {
+                const ID: $crate::telecommand::CommandId = $crate::telecommand::CommandId::$id;
+                const NAME: &'static str = stringify!($command);
+                const SCHEMA: u32 = 0;
+
+                type Response = $response;
+            }
+        )*
+    };
+}
+
+pub(crate) use builtin;
+
+/// Convert a command specific error to and from [`NackReason::Failed`], given
+/// as `Error { Variant = code, }` with the code of every variant of the error
+macro_rules! failure_codes {
+    ($(#[$doc:meta])* $error:ident { $($variant:ident = $code:literal,)* }) => {
+        impl $error {
+            $(#[$doc])*
+            pub fn from_reason(reason: $crate::telecommand::NackReason) -> Option<Self> {
+                match reason {
+                    $($crate::telecommand::NackReason::Failed($code) => Some($error::$variant),)*
+                    _ => None,
+                }
+            }
+        }
+
+        impl From<$error> for $crate::telecommand::NackReason {
+            fn from(error: $error) -> Self {
+                $crate::telecommand::NackReason::Failed(match error {
+                    $($error::$variant => $code,)*
+                })
+            }
+        }
+    };
+}
+
+pub(crate) use failure_codes;
+
+/// Routing information sent ahead of every command
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CommandHeader {
+    pub sequence: Sequence,
+    pub command: CommandId,
+}
+
+/// A command header paired with its (possibly already encoded) payload
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandFrame<P> {
+    pub header: CommandHeader,
+    pub payload: P,
+}
+
+/// Why a device refused to execute a command
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum NackReason {
+    /// The device does not know the command id
+    UnknownCommand,
+    /// The payload could not be decoded as the command
+    Malformed,
+    /// The device can not accept commands right now, the command may be retried later
+    Busy,
+    /// The command was executed but failed, with a command specific error code
+    Failed(u8),
+    /// The command is too old for the device to know whether it was already
+    /// executed, so it was not executed again
+    Stale,
+    /// The command was executed but its response did not fit in the response buffer
+    ResponseTooLarge,
+    /// The command did not carry a valid tag for the key of the device, see
+    /// [`auth`](crate::auth)
+    Unauthenticated,
+    /// The command was authentic, but its counter was already used
+    Replayed,
+    /// The device is not in a mode the command is allowed in, see [`Interlock`]
+    WrongMode,
+    /// The command has to be [`Arm`]ed before it is sent
+    NotArmed,
+}
+
+impl Display for NackReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NackReason::UnknownCommand => write!(f, "unknown command"),
+            NackReason::Malformed => write!(f, "malformed command payload"),
+            NackReason::Busy => write!(f, "device busy"),
+            NackReason::Failed(code) => write!(f, "command failed with code {}", code),
+            NackReason::Stale => write!(f, "command too old to execute"),
+            NackReason::ResponseTooLarge => write!(f, "response too large"),
+            NackReason::Unauthenticated => write!(f, "command not authenticated"),
+            NackReason::Replayed => write!(f, "command replayed"),
+            NackReason::WrongMode => write!(f, "command not allowed in the current mode"),
+            NackReason::NotArmed => write!(f, "command not armed"),
+        }
+    }
+}
+
+/// Whether a command took effect
+///
+/// An [`Ack`](Status::Ack) is followed by the encoded
+/// [`Telecommand::Response`] of the command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Status {
+    Ack,
+    Nack(NackReason),
+}
+
+/// Header of the response to the command with the same [`Sequence`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ResponseHeader {
+    pub sequence: Sequence,
+    pub status: Status,
+}
@@ -0,0 +1,329 @@
+use core::time::Duration;
+use std::collections::VecDeque;
+
+use crate::{
+    auth::Signer,
+    time::Timestamp,
+    wire::{self, Header},
+};
+
+use super::{
+    CommandFrame, CommandHeader, CommandId, CommandState, NackReason, ResponseHeader, Resync,
+    ResyncResponse, Sequence, Status, Telecommand,
+};
+
+/// How persistently a [`Commander`] tries to get a command through
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Time to wait for a response before retransmitting
+    pub timeout: Duration,
+    /// Number of retransmissions before giving up on a command
+    pub retries: u8,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_millis(500),
+            retries: 3,
+        }
+    }
+}
+
+/// The final fate of an issued command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// The device executed the command
+    Acknowledged(CommandHeader),
+    /// The device received the command but refused it
+    Rejected(CommandHeader, NackReason),
+    /// No response was received after all retransmissions. The command may or
+    /// may not have been executed
+    TimedOut(CommandHeader),
+    /// The device no longer remembers the command. It may or may not have been
+    /// executed
+    Unknown(CommandHeader),
+}
+
+impl Outcome {
+    pub fn header(&self) -> CommandHeader {
+        match *self {
+            Outcome::Acknowledged(header)
+            | Outcome::Rejected(header, _)
+            | Outcome::TimedOut(header)
+            | Outcome::Unknown(header) => header,
+        }
+    }
+}
+
+/// The result of reconciling pending commands with the state of the device
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Reconciliation {
+    /// Most recently executed command as reported by the device
+    pub last_executed: Option<Sequence>,
+    /// Commands that never reached the device and were sent again
+    pub resent: Vec<CommandHeader>,
+    /// Commands the device had already handled, whose responses were lost
+    pub completed: Vec<Outcome>,
+    /// Commands the device could not tell anything about
+    pub unknown: Vec<CommandHeader>,
+}
+
+/// Something the owner of a [`Commander`] has to act on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event<P> {
+    /// The frame has to be sent to the device again
+    Retransmit(CommandFrame<P>),
+    /// A command is no longer pending
+    Resolved(Outcome),
+    /// The pending commands were reconciled with the state of the device
+    Resynchronised(Reconciliation),
+}
+
+#[derive(Debug)]
+struct Pending<P> {
+    frame: CommandFrame<P>,
+    sent_at: Timestamp,
+    transmissions: u8,
+}
+
+/// Signs the payload of a command, see [`Commander::with_signer`]
+type Seal<P> = (Signer, fn(&mut Signer, &CommandHeader, &mut P));
+
+/// Ground side bookkeeping of issued telecommands
+///
+/// The commander does not perform any IO itself. Frames returned from
+/// [`issue`](Commander::issue) and [`poll`](Commander::poll) must be sent to the
+/// device, and every response received must be handed to
+/// [`receive`](Commander::receive). `P` is the payload kept around for
+/// retransmission, usually the already encoded command.
+#[derive(Debug)]
+pub struct Commander<P> {
+    policy: RetryPolicy,
+    next: Sequence,
+    pending: Vec<Pending<P>>,
+    events: VecDeque<Event<P>>,
+    seal: Option<Seal<P>>,
+}
+
+impl<P: Clone> Commander<P> {
+    pub fn new(policy: RetryPolicy) -> Self {
+        Self {
+            policy,
+            next: Sequence::new(0),
+            pending: Vec::new(),
+            events: VecDeque::new(),
+            seal: None,
+        }
+    }
+
+    pub fn policy(&self) -> RetryPolicy {
+        self.policy
+    }
+
+    /// Assign a sequence number to a command and start tracking it
+    ///
+    /// Returns the frame to send to the device, signed if the commander has a
+    /// [signer](Commander::with_signer).
+    pub fn issue(&mut self, command: CommandId, mut payload: P, now: Timestamp) -> CommandFrame<P> {
+        let header = CommandHeader {
+            sequence: self.next,
+            command,
+        };
+        self.next = self.next.next();
+        self.seal(&header, &mut payload);
+
+        let frame = CommandFrame { header, payload };
+
+        self.pending.push(Pending {
+            frame: frame.clone(),
+            sent_at: now,
+            transmissions: 1,
+        });
+
+        frame
+    }
+
+    /// Assign a sequence number to a command that is sent once and not tracked
+    ///
+    /// For commands that are better lost than retransmitted late, such as a
+    /// [`Heartbeat`](crate::link::Heartbeat). Responses to them are ignored by
+    /// [`receive`](Commander::receive).
+    pub fn untracked(&mut self) -> Sequence {
+        let sequence = self.next;
+        self.next = self.next.next();
+
+        sequence
+    }
+
+    /// Sign the payload of a command not issued through the commander, such as
+    /// one with an [untracked](Commander::untracked) sequence number
+    ///
+    /// Does nothing unless the commander has a [signer](Commander::with_signer).
+    pub fn seal(&mut self, header: &CommandHeader, payload: &mut P) {
+        if let Some((signer, seal)) = &mut self.seal {
+            seal(signer, header, payload);
+        }
+    }
+
+    /// Match a response from the device with the command it answers
+    ///
+    /// Returns `None` if the response does not belong to a pending command, as
+    /// is the case for duplicated responses to retransmitted commands.
+    pub fn receive(&mut self, response: &ResponseHeader) -> Option<Outcome> {
+        let index = self.position(response.sequence)?;
+        let header = self.pending.remove(index).frame.header;
+
+        Some(match response.status {
+            Status::Ack => Outcome::Acknowledged(header),
+            Status::Nack(reason) => Outcome::Rejected(header, reason),
+        })
+    }
+
+    /// Reconcile pending commands with the state reported by the device
+    ///
+    /// Must be called with the response to a [`Resync`] once it has been
+    /// [acknowledged](Outcome::Acknowledged). Commands the device never received
+    /// are retransmitted, commands it handled are resolved and commands it
+    /// forgot are resolved as [`Unknown`](Outcome::Unknown). The result is
+    /// reported through [`poll`](Commander::poll).
+    pub fn reconcile(&mut self, response: &ResyncResponse, now: Timestamp) {
+        let mut reconciliation = Reconciliation {
+            last_executed: response.last_executed,
+            ..Reconciliation::default()
+        };
+
+        for &(sequence, state) in &response.commands {
+            let index = match self.position(sequence) {
+                Some(index) => index,
+                None => continue,
+            };
+
+            match state {
+                CommandState::NotReceived => {
+                    let pending = &mut self.pending[index];
+                    pending.sent_at = now;
+                    pending.transmissions = 1;
+
+                    reconciliation.resent.push(pending.frame.header);
+                    self.events
+                        .push_back(Event::Retransmit(pending.frame.clone()));
+                }
+                CommandState::Executed(status) => {
+                    let header = self.pending.remove(index).frame.header;
+                    let outcome = match status {
+                        Status::Ack => Outcome::Acknowledged(header),
+                        Status::Nack(reason) => Outcome::Rejected(header, reason),
+                    };
+
+                    reconciliation.completed.push(outcome);
+                    self.events.push_back(Event::Resolved(outcome));
+                }
+                CommandState::Forgotten => {
+                    let header = self.pending.remove(index).frame.header;
+
+                    reconciliation.unknown.push(header);
+                    self.events
+                        .push_back(Event::Resolved(Outcome::Unknown(header)));
+                }
+            }
+        }
+
+        self.events.push_back(Event::Resynchronised(reconciliation));
+    }
+
+    /// Advance the retransmission timers and report reconciliations
+    ///
+    /// Must be called repeatedly until it returns `None`.
+    pub fn poll(&mut self, now: Timestamp) -> Option<Event<P>> {
+        if let Some(event) = self.events.pop_front() {
+            return Some(event);
+        }
+
+        let timeout = self.policy.timeout;
+        let index = self
+            .pending
+            .iter()
+            .position(|pending| now.saturating_duration_since(pending.sent_at) >= timeout)?;
+
+        let pending = &mut self.pending[index];
+
+        if pending.transmissions > self.policy.retries {
+            let header = self.pending.remove(index).frame.header;
+
+            return Some(Event::Resolved(Outcome::TimedOut(header)));
+        }
+
+        pending.transmissions += 1;
+        pending.sent_at = now;
+
+        Some(Event::Retransmit(pending.frame.clone()))
+    }
+
+    /// Headers of all commands still awaiting a response
+    pub fn pending(&self) -> impl Iterator<Item = &CommandHeader> + '_ {
+        self.pending.iter().map(|pending| &pending.frame.header)
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.pending.is_empty() && self.events.is_empty()
+    }
+
+    fn position(&self, sequence: Sequence) -> Option<usize> {
+        self.pending
+            .iter()
+            .position(|pending| pending.frame.header.sequence == sequence)
+    }
+}
+
+impl Commander<Vec<u8>> {
+    /// Sign every command with `signer`, for devices that
+    /// [authenticate](crate::auth) their commands
+    pub fn with_signer(mut self, signer: Signer) -> Self {
+        self.seal = Some((signer, |signer, header, payload| {
+            let trailer = signer.seal(header, payload);
+            payload.extend_from_slice(&trailer);
+        }));
+        self
+    }
+
+    /// The signer of the commands, to save its counter from
+    pub fn signer(&self) -> Option<&Signer> {
+        self.seal.as_ref().map(|(signer, _)| signer)
+    }
+
+    /// Issue a typed command, returning the encoded frame to send
+    pub fn issue_command<C: Telecommand>(
+        &mut self,
+        command: &C,
+        now: Timestamp,
+    ) -> Result<Vec<u8>, wire::Error> {
+        let payload = postcard::to_stdvec(command)?;
+
+        Ok(self.issue(C::ID, payload, now).to_wire())
+    }
+
+    /// Ask the device about the oldest pending commands, to be used after a
+    /// link outage
+    ///
+    /// Returns the encoded frame to send. Once the response is acknowledged it
+    /// has to be handed to [`reconcile`](Commander::reconcile).
+    pub fn resync(&mut self, now: Timestamp) -> Result<Vec<u8>, wire::Error> {
+        let sequences = self
+            .pending
+            .iter()
+            .filter(|pending| pending.frame.header.command != CommandId::RESYNC)
+            .map(|pending| pending.frame.header.sequence)
+            .take(super::MAX_RESYNC)
+            .collect();
+
+        self.issue_command(&Resync { sequences }, now)
+    }
+}
+
+impl CommandFrame<Vec<u8>> {
+    /// Encode the frame for sending, as needed for retransmissions
+    pub fn to_wire(&self) -> Vec<u8> {
+        wire::to_vec_raw(&Header::Command(self.header), &self.payload)
+    }
+}
@@ -0,0 +1,358 @@
+use heapless::{Deque, Vec};
+
+use crate::{
+    auth::Verifier,
+    handshake::{Handshake, Schema},
+    link::{Heartbeat, Pulse},
+    time::Timestamp,
+};
+
+use super::{
+    CommandHeader, CommandId, CommandState, NackReason, ResponseHeader, Resync, ResyncResponse,
+    Sequence, Status,
+};
+
+/// Longest encoded [`ResyncResponse`] or [`Schema`]
+const SCRATCH_LEN: usize = 264;
+
+/// The response to send back for a dispatched command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reply<'a> {
+    pub header: ResponseHeader,
+    /// The encoded [`Telecommand::Response`](super::Telecommand::Response),
+    /// empty if the command was refused
+    pub payload: &'a [u8],
+    /// The command had already been executed, and the cached response was replayed
+    pub duplicate: bool,
+}
+
+#[derive(Debug)]
+struct Executed<const RESPONSE: usize> {
+    sequence: Sequence,
+    status: Status,
+    response: Vec<u8, RESPONSE>,
+}
+
+/// Device side execution of telecommands, at most once per sequence number
+///
+/// When the acknowledgement of a command is lost the ground retransmits it.
+/// The dispatcher remembers the last `WINDOW` commands it executed along with
+/// their responses of up to `RESPONSE` bytes, and replays the response instead
+/// of executing a retransmitted command again. [`Resync`] queries are answered
+/// from the same memory.
+///
+/// A retransmission arriving after `WINDOW` newer commands have been executed
+/// can no longer be told apart from an executed command, and is refused as
+/// [`Stale`](NackReason::Stale). A `WINDOW` of zero does not compile, as every
+/// retransmission would be refused.
+///
+/// A [`Handshake`] starts a new session, forgetting all executed commands, and
+/// is answered with the schema given to [`with_schema`](Self::with_schema).
+/// A [`Heartbeat`] is answered right away and not remembered.
+///
+/// Given a [`Verifier`] with [`with_verifier`](Self::with_verifier), every
+/// command has to be [authenticated](crate::auth). Commands with a counter
+/// already used are refused as [`Replayed`](NackReason::Replayed) unless they
+/// are retransmissions of commands still remembered. The built in commands are
+/// not remembered, so a retransmitted one is refused and has to be issued again.
+#[derive(Debug)]
+pub struct Dispatcher<const WINDOW: usize, const RESPONSE: usize> {
+    executed: Deque<Executed<RESPONSE>, WINDOW>,
+    last_executed: Option<Sequence>,
+    /// Newest sequence number that was pushed out of the window
+    horizon: Option<Sequence>,
+    schema: Schema,
+    verifier: Option<Verifier>,
+    scratch: [u8; SCRATCH_LEN],
+}
+
+impl<const WINDOW: usize, const RESPONSE: usize> Dispatcher<WINDOW, RESPONSE> {
+    const WINDOW_IS_NOT_EMPTY: () = assert!(WINDOW > 0, "dispatcher window must not be empty");
+
+    pub const fn new() -> Self {
+        let () = Self::WINDOW_IS_NOT_EMPTY;
+
+        Self {
+            executed: Deque::new(),
+            last_executed: None,
+            horizon: None,
+            schema: Schema::new(),
+            verifier: None,
+            scratch: [0; SCRATCH_LEN],
+        }
+    }
+
+    /// Set the schema of the device, reported in response to a [`Handshake`]
+    pub fn with_schema(mut self, schema: Schema) -> Self {
+        self.schema = schema;
+        self
+    }
+
+    /// Refuse commands that are not authenticated by `verifier`
+    pub fn with_verifier(mut self, verifier: Verifier) -> Self {
+        self.verifier = Some(verifier);
+        self
+    }
+
+    /// The verifier authenticating commands, to save its counter from
+    pub fn verifier(&self) -> Option<&Verifier> {
+        self.verifier.as_ref()
+    }
+
+    /// Sequence number of the most recently executed command
+    pub fn last_executed(&self) -> Option<Sequence> {
+        self.last_executed
+    }
+
+    /// Forget all executed commands
+    ///
+    /// Must be called when the ground restarts its sequence numbers, otherwise
+    /// its new commands will be refused as [`Stale`](NackReason::Stale).
+    pub fn reset(&mut self) {
+        self.executed.clear();
+        self.last_executed = None;
+        self.horizon = None;
+    }
+
+    /// Execute a command received at `now` unless it is a duplicate
+    ///
+    /// `execute` is given the id and payload of the command and a buffer for
+    /// the encoded response, and returns the length of the response or the
+    /// reason the command was refused. Authenticated commands are given their
+    /// payload without its [trailer](crate::auth::TRAILER_LEN).
+    pub fn dispatch(
+        &mut self,
+        header: CommandHeader,
+        payload: &[u8],
+        now: Timestamp,
+        execute: impl FnOnce(CommandId, &[u8], &mut [u8]) -> Result<usize, NackReason>,
+    ) -> Reply<'_> {
+        let (payload, counter) = match &self.verifier {
+            Some(verifier) => match verifier.verify(&header, payload) {
+                Ok(verified) => (verified.payload, Some(verified.counter)),
+                Err(reason) => return Self::refuse(header, reason),
+            },
+            None => (payload, None),
+        };
+
+        // Built in commands are not remembered, so their counters are used up
+        // right away
+        if matches!(
+            header.command,
+            CommandId::RESYNC | CommandId::HANDSHAKE | CommandId::HEARTBEAT
+        ) {
+            if let (Some(verifier), Some(counter)) = (&mut self.verifier, counter) {
+                if !verifier.is_fresh(counter) {
+                    return Self::refuse(header, NackReason::Replayed);
+                }
+                verifier.accept(counter);
+            }
+        }
+
+        if header.command == CommandId::RESYNC {
+            return self.resync(header, payload);
+        }
+
+        if header.command == CommandId::HANDSHAKE {
+            return self.handshake(header, payload);
+        }
+
+        if header.command == CommandId::HEARTBEAT {
+            return self.heartbeat(header, payload, now);
+        }
+
+        if self.state(header.sequence) == CommandState::NotReceived {
+            if let (Some(verifier), Some(counter)) = (&self.verifier, counter) {
+                if !verifier.is_fresh(counter) {
+                    return Self::refuse(header, NackReason::Replayed);
+                }
+            }
+
+            let mut response = Vec::new();
+            response
+                .resize_default(RESPONSE)
+                .expect("capacity is RESPONSE");
+
+            let status = match execute(header.command, payload, &mut response) {
+                Ok(len) => {
+                    response.truncate(len);
+                    Status::Ack
+                }
+                Err(reason) => {
+                    response.clear();
+                    Status::Nack(reason)
+                }
+            };
+
+            // Busy commands were not executed and may be retried
+            if status == Status::Nack(NackReason::Busy) {
+                return Self::refuse(header, NackReason::Busy);
+            }
+
+            if let (Some(verifier), Some(counter)) = (&mut self.verifier, counter) {
+                verifier.accept(counter);
+            }
+
+            if self.executed.is_full() {
+                let evicted = self.executed.pop_front().expect("window is full").sequence;
+
+                self.horizon = match self.horizon {
+                    Some(horizon) if horizon.is_after(evicted) => Some(horizon),
+                    _ => Some(evicted),
+                };
+            }
+
+            self.last_executed = Some(header.sequence);
+            self.executed
+                .push_back(Executed {
+                    sequence: header.sequence,
+                    status,
+                    response,
+                })
+                .expect("space was made");
+
+            return Self::reply(self.executed.back().expect("just pushed"), false);
+        }
+
+        match self
+            .executed
+            .iter()
+            .find(|executed| executed.sequence == header.sequence)
+        {
+            Some(executed) => Self::reply(executed, true),
+            // Too old to tell, executing it again could be worse than refusing it
+            None => Reply {
+                header: ResponseHeader {
+                    sequence: header.sequence,
+                    status: Status::Nack(NackReason::Stale),
+                },
+                payload: &[],
+                duplicate: true,
+            },
+        }
+    }
+
+    /// What the dispatcher knows about a sequence number
+    pub fn state(&self, sequence: Sequence) -> CommandState {
+        if let Some(executed) = self
+            .executed
+            .iter()
+            .find(|executed| executed.sequence == sequence)
+        {
+            return CommandState::Executed(executed.status);
+        }
+
+        match self.horizon {
+            Some(horizon) if !sequence.is_after(horizon) => CommandState::Forgotten,
+            _ => CommandState::NotReceived,
+        }
+    }
+
+    fn resync(&mut self, header: CommandHeader, payload: &[u8]) -> Reply<'_> {
+        let reply = |status, payload| Reply {
+            header: ResponseHeader {
+                sequence: header.sequence,
+                status,
+            },
+            payload,
+            duplicate: false,
+        };
+
+        let request: Resync = match postcard::from_bytes(payload) {
+            Ok(request) => request,
+            Err(_) => return reply(Status::Nack(NackReason::Malformed), &[]),
+        };
+
+        let response = ResyncResponse {
+            last_executed: self.last_executed,
+            commands: request
+                .sequences
+                .iter()
+                .map(|&sequence| (sequence, self.state(sequence)))
+                .collect(),
+        };
+
+        match postcard::to_slice(&response, &mut self.scratch) {
+            Ok(encoded) => reply(Status::Ack, encoded),
+            Err(_) => reply(Status::Nack(NackReason::Failed(0)), &[]),
+        }
+    }
+
+    fn handshake(&mut self, header: CommandHeader, payload: &[u8]) -> Reply<'_> {
+        let reply = |status, payload| Reply {
+            header: ResponseHeader {
+                sequence: header.sequence,
+                status,
+            },
+            payload,
+            duplicate: false,
+        };
+
+        if postcard::from_bytes::<Handshake>(payload).is_err() {
+            return reply(Status::Nack(NackReason::Malformed), &[]);
+        }
+
+        // The ground numbers the commands of every session from scratch
+        self.reset();
+
+        match postcard::to_slice(&self.schema, &mut self.scratch) {
+            Ok(encoded) => reply(Status::Ack, encoded),
+            Err(_) => reply(Status::Nack(NackReason::ResponseTooLarge), &[]),
+        }
+    }
+
+    fn heartbeat(&mut self, header: CommandHeader, payload: &[u8], now: Timestamp) -> Reply<'_> {
+        let reply = |status, payload| Reply {
+            header: ResponseHeader {
+                sequence: header.sequence,
+                status,
+            },
+            payload,
+            duplicate: false,
+        };
+
+        let request: Heartbeat = match postcard::from_bytes(payload) {
+            Ok(request) => request,
+            Err(_) => return reply(Status::Nack(NackReason::Malformed), &[]),
+        };
+
+        let pulse = Pulse {
+            sent: request.sent,
+            device: now,
+        };
+
+        match postcard::to_slice(&pulse, &mut self.scratch) {
+            Ok(encoded) => reply(Status::Ack, encoded),
+            Err(_) => reply(Status::Nack(NackReason::ResponseTooLarge), &[]),
+        }
+    }
+
+    /// Refuse a command without remembering it
+    fn refuse(header: CommandHeader, reason: NackReason) -> Reply<'static> {
+        Reply {
+            header: ResponseHeader {
+                sequence: header.sequence,
+                status: Status::Nack(reason),
+            },
+            payload: &[],
+            duplicate: false,
+        }
+    }
+
+    fn reply(executed: &Executed<RESPONSE>, duplicate: bool) -> Reply<'_> {
+        Reply {
+            header: ResponseHeader {
+                sequence: executed.sequence,
+                status: executed.status,
+            },
+            payload: &executed.response,
+            duplicate,
+        }
+    }
+}
+
+impl<const WINDOW: usize, const RESPONSE: usize> Default for Dispatcher<WINDOW, RESPONSE> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,79 @@
+use std::collections::BTreeMap;
+
+use super::{
+    Arm, ArmError, Armed, CommandId, Interlock, Interlocked, Mode, ModeReport, NackReason,
+    Telecommand,
+};
+
+/// Ground side pre-check of the [`Interlock`]s of a device
+///
+/// Declared with the same commands as the
+/// [`Interlocks`](super::Interlocks) of the device, and kept up to date with
+/// the [`ModeReport`]s it sends as telemetry and in response to [`Arm`] and
+/// [`Disarm`](super::Disarm). Lets the ground refuse a command the device
+/// would refuse, before sending it. The device has the final say: the guard
+/// lets commands through while the mode of the device is unknown, and does
+/// not know when an arming expires.
+#[derive(Debug, Default)]
+pub struct Guard {
+    interlocks: BTreeMap<CommandId, Interlock>,
+    mode: Option<Mode>,
+    armed: Option<Armed>,
+}
+
+impl Guard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check the interlock of `C`
+    ///
+    /// # Panics
+    ///
+    /// If `C` is already interlocked.
+    pub fn command<C: Interlocked>(mut self) -> Self {
+        let previous = self.interlocks.insert(C::ID, C::INTERLOCK);
+        assert!(previous.is_none(), "duplicate interlock for {}", C::ID);
+
+        self
+    }
+
+    /// Account for a report of the device
+    pub fn update(&mut self, report: &ModeReport) {
+        self.mode = Some(report.mode);
+        self.armed = report.armed;
+    }
+
+    /// The mode of the device, as last reported
+    pub fn mode(&self) -> Option<Mode> {
+        self.mode
+    }
+
+    /// The command armed on the device, as last reported
+    pub fn armed(&self) -> Option<Armed> {
+        self.armed
+    }
+
+    /// Whether the device would execute `C` as far as the ground knows
+    pub fn check<C: Telecommand>(&self) -> Result<(), NackReason> {
+        let (Some(interlock), Some(mode)) = (self.interlocks.get(&C::ID), self.mode) else {
+            return Ok(());
+        };
+        let armed = self.armed.is_some_and(|armed| armed.command == C::ID);
+
+        interlock.check(mode, armed)
+    }
+
+    /// The command arming `C`, unless the device would refuse it
+    pub fn arm<C: Interlocked>(&self) -> Result<Arm, NackReason> {
+        if !C::INTERLOCK.arm {
+            return Err(ArmError::NotArmable.into());
+        }
+
+        if let Some(mode) = self.mode {
+            C::INTERLOCK.check(mode, true)?;
+        }
+
+        Ok(Arm { command: C::ID })
+    }
+}
@@ -0,0 +1,391 @@
+use core::{
+    fmt::{self, Display},
+    time::Duration,
+};
+
+use heapless::Vec;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    telemetry::{ChannelId, Telemetry},
+    time::Timestamp,
+};
+
+use super::{builtin, failure_codes, CommandHandler, CommandId, NackReason, Router, Telecommand};
+
+/// A mode of operation of a device, such as "armed" or "calibrating"
+///
+/// What the modes mean is up to the device, only their numbers are shared
+/// with the ground.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "u8")]
+pub struct Mode(u8);
+
+impl Mode {
+    /// Number of distinct modes a device can have
+    pub const COUNT: u8 = 32;
+
+    /// # Panics
+    ///
+    /// If `mode` is not below [`COUNT`](Self::COUNT).
+    pub const fn new(mode: u8) -> Self {
+        assert!(mode < Self::COUNT, "mode out of range");
+        Self(mode)
+    }
+
+    pub const fn get(self) -> u8 {
+        self.0
+    }
+}
+
+impl Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "mode#{}", self.0)
+    }
+}
+
+impl TryFrom<u8> for Mode {
+    type Error = InvalidMode;
+
+    fn try_from(mode: u8) -> Result<Self, InvalidMode> {
+        if mode < Self::COUNT {
+            Ok(Self(mode))
+        } else {
+            Err(InvalidMode(mode))
+        }
+    }
+}
+
+/// A mode number not below [`Mode::COUNT`], as received from a device that
+/// does not follow the protocol
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidMode(pub u8);
+
+impl Display for InvalidMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "mode {} out of range", self.0)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for InvalidMode {}
+
+/// A set of [`Mode`]s
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Modes(u32);
+
+impl Modes {
+    pub const ALL: Modes = Modes(u32::MAX);
+
+    pub const fn of(modes: &[Mode]) -> Self {
+        let mut set = 0;
+
+        let mut index = 0;
+        while index < modes.len() {
+            set |= 1 << modes[index].0;
+            index += 1;
+        }
+
+        Self(set)
+    }
+
+    pub const fn contains(self, mode: Mode) -> bool {
+        self.0 & (1 << mode.0) != 0
+    }
+}
+
+/// The conditions a command can only be executed under
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Interlock {
+    /// Modes the device has to be in
+    pub modes: Modes,
+    /// Whether the command has to be [`Arm`]ed right before it is sent
+    pub arm: bool,
+}
+
+impl Interlock {
+    /// Allow the command in `modes` only
+    pub const fn new(modes: Modes) -> Self {
+        Self { modes, arm: false }
+    }
+
+    /// Also require the command to be armed
+    pub const fn armed(mut self) -> Self {
+        self.arm = true;
+        self
+    }
+
+    /// Whether the command may be executed in `mode`, given whether it is armed
+    pub fn check(&self, mode: Mode, armed: bool) -> Result<(), NackReason> {
+        if !self.modes.contains(mode) {
+            return Err(NackReason::WrongMode);
+        }
+
+        if self.arm && !armed {
+            return Err(NackReason::NotArmed);
+        }
+
+        Ok(())
+    }
+}
+
+/// A command that can be dangerous, declaring when it may be executed
+///
+/// ```
+/// use micromanager_tele::telecommand::{Interlock, Interlocked, Mode, Modes, Telecommand};
+/// # use serde::{Deserialize, Serialize};
+///
+/// const DISARMED: Mode = Mode::new(0);
+///
+/// #[derive(Serialize, Deserialize, Telecommand)]
+/// #[telecommand(id = 7, response = ())]
+/// struct WriteCalibration([i16; 3]);
+///
+/// impl Interlocked for WriteCalibration {
+///     const INTERLOCK: Interlock = Interlock::new(Modes::of(&[DISARMED])).armed();
+/// }
+/// ```
+pub trait Interlocked: Telecommand {
+    const INTERLOCK: Interlock;
+}
+
+/// Why the device refused to arm a command
+///
+/// Commands refused by an interlock are refused as
+/// [`WrongMode`](NackReason::WrongMode) or [`NotArmed`](NackReason::NotArmed)
+/// instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArmError {
+    /// The command does not need to be armed, or is not known to the device
+    NotArmable,
+}
+
+failure_codes! {
+    /// Recover the error a device refused an [`Arm`] command with
+    ArmError {
+        NotArmable = 1,
+    }
+}
+
+impl Display for ArmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArmError::NotArmable => write!(f, "command can not be armed"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ArmError {}
+
+/// Allow a single execution of a command that has to be armed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Arm {
+    pub command: CommandId,
+}
+
+/// Withdraw the arming of a command
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Disarm;
+
+/// A command allowed to be executed once
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Armed {
+    pub command: CommandId,
+    /// Device time after which the command has to be armed again
+    pub expires: Timestamp,
+}
+
+/// The mode of a device and the command it armed, sent as telemetry when
+/// either changes and in response to [`Arm`] and [`Disarm`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModeReport {
+    pub mode: Mode,
+    pub armed: Option<Armed>,
+}
+
+builtin! {
+    Arm => ARM, ModeReport;
+    Disarm => DISARM, ModeReport;
+}
+
+impl Telemetry for ModeReport {
+    const CHANNEL: ChannelId = ChannelId::MODE;
+    const NAME: &'static str = "ModeReport";
+    const SCHEMA: u32 = 0;
+}
+
+/// Device side enforcement of the [`Interlock`]s of up to `N` commands
+///
+/// Every command has to be [`check`](Self::check)ed before it is executed,
+/// the [`Arm`] and [`Disarm`] commands handled by the [`router`](Self::router)
+/// included. An armed command may be executed once, within the arming
+/// [`window`](Self::window) counted from the time of the latest check or
+/// [`tick`](Self::tick), and changing mode disarms it.
+///
+/// Every change of the mode or of the armed command is queued as a
+/// [`ModeReport`] to be sent as telemetry.
+#[derive(Debug)]
+pub struct Interlocks<const N: usize> {
+    interlocks: Vec<(CommandId, Interlock), N>,
+    mode: Mode,
+    armed: Option<Armed>,
+    window: Duration,
+    now: Timestamp,
+    changed: bool,
+}
+
+impl<const N: usize> Interlocks<N> {
+    /// Start out in `mode`, with an arming window of ten seconds
+    pub const fn new(mode: Mode) -> Self {
+        Self {
+            interlocks: Vec::new(),
+            mode,
+            armed: None,
+            window: Duration::from_secs(10),
+            now: Timestamp::ZERO,
+            changed: true,
+        }
+    }
+
+    /// Time an armed command may be executed in
+    pub fn window(mut self, window: Duration) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// Enforce the interlock of `C`
+    ///
+    /// # Panics
+    ///
+    /// If the interlocks are full or `C` is already interlocked.
+    pub fn command<C: Interlocked>(mut self) -> Self {
+        assert!(
+            self.interlock(C::ID).is_none(),
+            "duplicate interlock for {}",
+            C::ID
+        );
+
+        if self.interlocks.push((C::ID, C::INTERLOCK)).is_err() {
+            panic!("interlocks are full, can not interlock {}", C::ID);
+        }
+
+        self
+    }
+
+    /// Routes the [`Arm`] and [`Disarm`] commands to the interlocks
+    pub fn router() -> Router<Self, 2> {
+        Router::new().route::<Arm>().route::<Disarm>()
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// Switch to another mode, disarming the armed command
+    pub fn set_mode(&mut self, mode: Mode) {
+        if mode != self.mode {
+            self.mode = mode;
+            self.armed = None;
+            self.changed = true;
+        }
+    }
+
+    /// The command that is armed, as of the latest check
+    pub fn armed(&self) -> Option<Armed> {
+        self.armed
+    }
+
+    /// The interlock of a command, if it has one
+    pub fn interlock(&self, command: CommandId) -> Option<Interlock> {
+        self.interlocks
+            .iter()
+            .find(|&&(id, _)| id == command)
+            .map(|&(_, interlock)| interlock)
+    }
+
+    /// Advance the device time to `now`, withdrawing an expired arming
+    ///
+    /// Done by every [`check`](Self::check). Ticking while no commands are
+    /// received reports expired armings as they expire.
+    pub fn tick(&mut self, now: Timestamp) {
+        self.now = now;
+
+        if self.armed.is_some_and(|armed| armed.expires < now) {
+            self.armed = None;
+            self.changed = true;
+        }
+    }
+
+    /// Whether a command received at `now` may be executed
+    ///
+    /// Uses up the arming of the command when it is allowed.
+    pub fn check(&mut self, command: CommandId, now: Timestamp) -> Result<(), NackReason> {
+        self.tick(now);
+
+        let Some(interlock) = self.interlock(command) else {
+            return Ok(());
+        };
+        let armed = self.armed.is_some_and(|armed| armed.command == command);
+
+        interlock.check(self.mode, armed)?;
+
+        if interlock.arm {
+            self.armed = None;
+            self.changed = true;
+        }
+
+        Ok(())
+    }
+
+    /// The report to send as telemetry, if the mode or armed command changed
+    pub fn next_report(&mut self) -> Option<ModeReport> {
+        if !self.changed {
+            return None;
+        }
+
+        self.changed = false;
+        Some(self.report())
+    }
+
+    fn report(&self) -> ModeReport {
+        ModeReport {
+            mode: self.mode,
+            armed: self.armed,
+        }
+    }
+}
+
+impl<const N: usize> CommandHandler<Arm> for Interlocks<N> {
+    type Error = NackReason;
+
+    fn handle(&mut self, Arm { command }: Arm) -> Result<ModeReport, NackReason> {
+        let interlock = self
+            .interlock(command)
+            .filter(|interlock| interlock.arm)
+            .ok_or(ArmError::NotArmable)?;
+
+        // Arming a command that would be refused anyway is refused right away
+        interlock.check(self.mode, true)?;
+
+        self.armed = Some(Armed {
+            command,
+            expires: self.now + self.window,
+        });
+        self.changed = true;
+
+        Ok(self.report())
+    }
+}
+
+impl<const N: usize> CommandHandler<Disarm> for Interlocks<N> {
+    type Error = core::convert::Infallible;
+
+    fn handle(&mut self, Disarm: Disarm) -> Result<ModeReport, Self::Error> {
+        if self.armed.take().is_some() {
+            self.changed = true;
+        }
+
+        Ok(self.report())
+    }
+}
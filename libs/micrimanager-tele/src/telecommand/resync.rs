@@ -0,0 +1,39 @@
+use heapless::Vec;
+use serde::{Deserialize, Serialize};
+
+use super::{builtin, Sequence, Status};
+
+/// Most commands whose state can be queried by a single [`Resync`]
+pub const MAX_RESYNC: usize = 16;
+
+/// Ask the device what became of commands the ground has not heard back about
+///
+/// Sent by the ground after a link outage, see [`Commander::resync`](super::Commander::resync).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Resync {
+    pub sequences: Vec<Sequence, MAX_RESYNC>,
+}
+
+builtin! {
+    Resync => RESYNC, ResyncResponse;
+}
+
+/// What the device knows about a single command
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CommandState {
+    /// The command was handled, with the given status
+    Executed(Status),
+    /// The command never reached the device
+    NotReceived,
+    /// The command is too old for the device to remember it
+    Forgotten,
+}
+
+/// The state of the device as reported in response to a [`Resync`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResyncResponse {
+    /// Sequence number of the most recently executed command
+    pub last_executed: Option<Sequence>,
+    /// The state of each command asked about, in the same order
+    pub commands: Vec<(Sequence, CommandState), MAX_RESYNC>,
+}
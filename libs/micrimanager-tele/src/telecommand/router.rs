@@ -0,0 +1,113 @@
+use core::convert::Infallible;
+
+use heapless::Vec;
+
+use super::{CommandId, NackReason, Telecommand};
+
+/// Device side implementation of a telecommand
+///
+/// Implemented on the state of the device, once for every command it accepts,
+/// and registered with a [`Router`].
+pub trait CommandHandler<C: Telecommand> {
+    /// Why the command failed, usually a command specific
+    /// [`Failed`](NackReason::Failed) code
+    type Error: Into<NackReason>;
+
+    fn handle(&mut self, command: C) -> Result<C::Response, Self::Error>;
+}
+
+impl From<Infallible> for NackReason {
+    fn from(infallible: Infallible) -> Self {
+        match infallible {}
+    }
+}
+
+type Handler<S> = fn(&mut S, &[u8], &mut [u8]) -> Result<usize, NackReason>;
+
+/// Maps command ids to the [`CommandHandler`]s of a device with state `S`
+///
+/// Holds up to `ROUTES` commands. Decoding of commands and encoding of their
+/// responses is taken care of, so handlers only ever see typed values. Meant
+/// to be used as the `execute` function of a
+/// [`Dispatcher`](super::Dispatcher).
+pub struct Router<S, const ROUTES: usize> {
+    routes: Vec<(CommandId, Handler<S>), ROUTES>,
+}
+
+impl<S, const ROUTES: usize> Router<S, ROUTES> {
+    pub const fn new() -> Self {
+        Self { routes: Vec::new() }
+    }
+
+    /// Route commands of type `C` to the state
+    ///
+    /// # Panics
+    ///
+    /// If the router is full or another command with the same id was routed.
+    pub fn route<C: Telecommand>(mut self) -> Self
+    where
+        S: CommandHandler<C>,
+    {
+        assert!(
+            self.handler(C::ID).is_none(),
+            "duplicate route for {}",
+            C::ID
+        );
+
+        if self.routes.push((C::ID, handle::<S, C>)).is_err() {
+            panic!("router is full, can not route {}", C::ID);
+        }
+
+        self
+    }
+
+    /// Ids of all routed commands
+    pub fn commands(&self) -> impl Iterator<Item = CommandId> + '_ {
+        self.routes.iter().map(|&(id, _)| id)
+    }
+
+    /// Decode a command, run its handler and encode the response into `response`
+    ///
+    /// Returns the length of the encoded response.
+    pub fn handle(
+        &self,
+        state: &mut S,
+        command: CommandId,
+        payload: &[u8],
+        response: &mut [u8],
+    ) -> Result<usize, NackReason> {
+        let handler = self.handler(command).ok_or(NackReason::UnknownCommand)?;
+
+        handler(state, payload, response)
+    }
+
+    fn handler(&self, command: CommandId) -> Option<Handler<S>> {
+        self.routes
+            .iter()
+            .find(|&&(id, _)| id == command)
+            .map(|&(_, handler)| handler)
+    }
+}
+
+impl<S, const ROUTES: usize> Default for Router<S, ROUTES> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn handle<S: CommandHandler<C>, C: Telecommand>(
+    state: &mut S,
+    payload: &[u8],
+    response: &mut [u8],
+) -> Result<usize, NackReason> {
+    let command = match postcard::take_from_bytes::<C>(payload) {
+        Ok((command, [])) => command,
+        _ => return Err(NackReason::Malformed),
+    };
+
+    let result = state.handle(command).map_err(Into::into)?;
+
+    postcard::to_slice(&result, response)
+        .map(|encoded| encoded.len())
+        .map_err(|_| NackReason::ResponseTooLarge)
+}
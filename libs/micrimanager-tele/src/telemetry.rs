@@ -0,0 +1,146 @@
+use core::fmt::{self, Display};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::time::Timestamp;
+
+pub use micromanager_tele_derive::Telemetry;
+
+/// Identifier of the logical channel a telemetry packet is sent over
+///
+/// Each telemetry packet type owns exactly one channel, which is how the
+/// receiving side knows what payload to decode. Channels from `0xff00` upwards
+/// are reserved for the protocol itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct ChannelId(u16);
+
+impl ChannelId {
+    /// Channel of [`ParameterChanged`](crate::param::ParameterChanged)
+    pub const PARAMETERS: ChannelId = ChannelId(0xff00);
+    /// Channel of [`LogRecord`](crate::log::LogRecord)
+    pub const LOG: ChannelId = ChannelId(0xff01);
+    /// Channel of [`Report`](crate::timeline::Report)
+    pub const TIMELINE: ChannelId = ChannelId(0xff02);
+    /// Channel of [`ModeReport`](crate::telecommand::ModeReport)
+    pub const MODE: ChannelId = ChannelId(0xff03);
+
+    pub const fn new(id: u16) -> Self {
+        Self(id)
+    }
+
+    pub const fn get(self) -> u16 {
+        self.0
+    }
+}
+
+impl Display for ChannelId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// A type that can be sent as telemetry
+///
+/// Shared between the device and the ground so both agree on the layout of
+/// every packet. Usually implemented with `#[derive(Telemetry)]`:
+///
+/// ```
+/// use micromanager_tele::telemetry::Telemetry;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize, Telemetry)]
+/// #[telemetry(channel = 1)]
+/// struct Magnetometer {
+///     x: f32,
+///     y: f32,
+///     z: f32,
+/// }
+/// ```
+pub trait Telemetry: Serialize + DeserializeOwned {
+    /// The channel this packet is sent over
+    const CHANNEL: ChannelId;
+
+    /// Name of the packet, used when reporting schema mismatches
+    const NAME: &'static str;
+
+    /// Hash of the packet definition, see [`schema_hash`](crate::handshake::schema_hash)
+    ///
+    /// Zero for the packets built into the protocol, which are covered by the
+    /// protocol version instead.
+    const SCHEMA: u32;
+}
+
+/// A telemetry payload stamped with the device time it was sampled at
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Packet<T> {
+    pub timestamp: Timestamp,
+    pub payload: T,
+}
+
+impl<T: Telemetry> Packet<T> {
+    pub fn new(timestamp: Timestamp, payload: T) -> Self {
+        Self { timestamp, payload }
+    }
+
+    pub fn channel(&self) -> ChannelId {
+        T::CHANNEL
+    }
+}
+
+/// Routing information sent ahead of every telemetry payload
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TelemetryHeader {
+    pub channel: ChannelId,
+    /// Wrapping count of packets sent on the channel, used to detect losses
+    pub sequence: u16,
+    pub timestamp: Timestamp,
+}
+
+/// Packets this far behind the one expected next are taken as a restart of the
+/// device, rather than as arriving late
+#[cfg(feature = "std")]
+const REORDER_WINDOW: u16 = 64;
+
+/// Packets sampled this long before the latest one are taken as a restart of
+/// the device, rather than as arriving late
+#[cfg(feature = "std")]
+const REORDER_DELAY: core::time::Duration = core::time::Duration::from_secs(60);
+
+/// Where a packet falls relative to the latest one received on its channel
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Gap {
+    /// The packet is the one expected next, or follows that many lost packets
+    Ahead(u16),
+    /// The packet arrived after a newer one, or is a duplicate
+    Late,
+    /// The device started counting over, its sequence numbers and clock
+    /// disagree with the latest packet
+    Restart,
+}
+
+#[cfg(feature = "std")]
+impl Gap {
+    pub(crate) fn of(header: &TelemetryHeader, latest: &TelemetryHeader) -> Self {
+        let gap = header
+            .sequence
+            .wrapping_sub(latest.sequence.wrapping_add(1));
+        let behind = latest.timestamp.saturating_duration_since(header.timestamp);
+
+        if gap < 0x8000 {
+            // Newer by its sequence number, but sampled earlier
+            if header.timestamp < latest.timestamp {
+                Gap::Restart
+            } else {
+                Gap::Ahead(gap)
+            }
+        } else if 0u16.wrapping_sub(gap) <= REORDER_WINDOW
+            && header.timestamp <= latest.timestamp
+            && behind <= REORDER_DELAY
+        {
+            Gap::Late
+        } else {
+            Gap::Restart
+        }
+    }
+}
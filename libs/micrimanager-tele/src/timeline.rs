@@ -0,0 +1,176 @@
+use core::{
+    fmt::{self, Display},
+    time::Duration,
+};
+
+use heapless::Vec;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    telecommand::{builtin, failure_codes, CommandId, Status},
+    telemetry::{ChannelId, Telemetry},
+    time::Timestamp,
+};
+
+pub use queue::Timeline;
+#[cfg(feature = "std")]
+pub use tracker::{Fate, Tracked, Tracker};
+
+mod queue;
+#[cfg(feature = "std")]
+mod tracker;
+
+/// Longest encoded command that can be scheduled
+pub const MAX_PAYLOAD: usize = 32;
+
+/// Scheduled items described by a single [`SchedulePage`]
+pub const PAGE_LEN: usize = 4;
+
+/// Identifier the device gave to a scheduled command
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct ItemId(u16);
+
+impl ItemId {
+    pub const fn new(id: u16) -> Self {
+        Self(id)
+    }
+
+    pub const fn get(self) -> u16 {
+        self.0
+    }
+}
+
+impl Display for ItemId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "item#{}", self.0)
+    }
+}
+
+/// When a scheduled command is due
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum When {
+    /// At a time of the device clock
+    At(Timestamp),
+    /// After a delay, counted from when the device stores the command
+    After(Duration),
+}
+
+/// What became of a scheduled command on the device
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Outcome {
+    /// The command was executed, and acknowledged or refused by its handler
+    Executed(Status),
+    /// The command was cancelled before it was due
+    Cancelled,
+}
+
+/// Why the device refused to schedule or cancel a command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// No more commands can be scheduled
+    Full,
+    /// The command would be due before the current time of the device
+    Past,
+    /// No such command is scheduled
+    UnknownItem,
+}
+
+failure_codes! {
+    /// Recover the error a device refused a timeline command with
+    Error {
+        Full = 1,
+        Past = 2,
+        UnknownItem = 3,
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Full => write!(f, "no room to schedule the command"),
+            Error::Past => write!(f, "command would be due in the past"),
+            Error::UnknownItem => write!(f, "no such scheduled command"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+/// Store a command on the device, to be executed when it is due
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduleCommand {
+    pub when: When,
+    pub command: CommandId,
+    /// The encoded command
+    pub payload: Vec<u8, MAX_PAYLOAD>,
+}
+
+/// A command stored on the device
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Scheduled {
+    pub id: ItemId,
+    pub command: CommandId,
+    /// Device time the command is due at
+    pub due: Timestamp,
+}
+
+/// Describe the scheduled commands, starting with the `start`th one due
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ListScheduled {
+    pub start: u16,
+}
+
+/// Up to [`PAGE_LEN`] scheduled commands, in response to [`ListScheduled`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SchedulePage {
+    /// Number of commands scheduled on the device
+    pub total: u16,
+    pub start: u16,
+    pub items: Vec<Scheduled, PAGE_LEN>,
+}
+
+/// Remove a command from the schedule before it is due
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CancelScheduled {
+    pub id: ItemId,
+}
+
+/// Ask what became of a scheduled command
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueryScheduled {
+    pub id: ItemId,
+}
+
+/// What the device knows about a scheduled command
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ItemState {
+    /// Waiting to be due
+    Pending(Scheduled),
+    /// Executed or cancelled
+    Finished(Report),
+    /// Never scheduled, or finished too long ago to be remembered
+    Unknown,
+}
+
+/// A scheduled command was executed or cancelled, sent as telemetry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Report {
+    pub item: Scheduled,
+    /// Device time the command was executed or cancelled at
+    pub finished: Timestamp,
+    pub outcome: Outcome,
+}
+
+builtin! {
+    ScheduleCommand => SCHEDULE_COMMAND, Scheduled;
+    ListScheduled => LIST_SCHEDULED, SchedulePage;
+    CancelScheduled => CANCEL_SCHEDULED, ();
+    QueryScheduled => QUERY_SCHEDULED, ItemState;
+}
+
+impl Telemetry for Report {
+    const CHANNEL: ChannelId = ChannelId::TIMELINE;
+    const NAME: &'static str = "Report";
+    const SCHEMA: u32 = 0;
+}
@@ -0,0 +1,230 @@
+use heapless::{Deque, Vec};
+
+use crate::{
+    telecommand::{CommandHandler, CommandId, NackReason, Router, Status},
+    time::Timestamp,
+};
+
+use super::{
+    CancelScheduled, Error, ItemId, ItemState, ListScheduled, Outcome, QueryScheduled, Report,
+    ScheduleCommand, SchedulePage, Scheduled, When, MAX_PAYLOAD, PAGE_LEN,
+};
+
+/// Largest response of a scheduled command, which is discarded
+const RESPONSE_LEN: usize = 256;
+
+#[derive(Debug)]
+struct Item {
+    scheduled: Scheduled,
+    payload: Vec<u8, MAX_PAYLOAD>,
+}
+
+/// Device side store of up to `N` commands waiting for their time
+///
+/// Handles the [`ScheduleCommand`], [`ListScheduled`], [`CancelScheduled`]
+/// and [`QueryScheduled`] commands, see [`router`](Self::router). Due commands
+/// are executed by [`poll`](Self::poll), and every executed or cancelled
+/// command is queued as a [`Report`] to be sent as telemetry. The last `N`
+/// reports are also remembered to answer [`QueryScheduled`] when the telemetry
+/// was lost.
+///
+/// Delays of [`When::After`] are counted from the time of the latest poll.
+#[derive(Debug)]
+pub struct Timeline<const N: usize> {
+    /// Ordered by due time, then by the order they were scheduled in
+    items: Vec<Item, N>,
+    finished: Deque<Report, N>,
+    reports: Deque<Report, N>,
+    next: ItemId,
+    now: Timestamp,
+}
+
+impl<const N: usize> Timeline<N> {
+    pub const fn new() -> Self {
+        Self {
+            items: Vec::new(),
+            finished: Deque::new(),
+            reports: Deque::new(),
+            next: ItemId::new(0),
+            now: Timestamp::ZERO,
+        }
+    }
+
+    /// Routes the timeline commands to a timeline
+    pub fn router() -> Router<Self, 4> {
+        Router::new()
+            .route::<ScheduleCommand>()
+            .route::<ListScheduled>()
+            .route::<CancelScheduled>()
+            .route::<QueryScheduled>()
+    }
+
+    /// Execute the commands due at `now`, returning how many were executed
+    ///
+    /// `execute` is called like the one given to a
+    /// [`Dispatcher`](crate::telecommand::Dispatcher), and usually routes to
+    /// the same handlers. Responses are discarded, only whether the command
+    /// was acknowledged is reported.
+    ///
+    /// Commands are executed as stored, without any
+    /// [`Interlocks`](crate::telecommand::Interlocks) checks: those have to be
+    /// made by `execute`, at the time the command is due.
+    pub fn poll(
+        &mut self,
+        now: Timestamp,
+        mut execute: impl FnMut(CommandId, &[u8], &mut [u8]) -> Result<usize, NackReason>,
+    ) -> usize {
+        self.now = now;
+
+        let mut executed = 0;
+        let mut response = [0; RESPONSE_LEN];
+
+        while self
+            .items
+            .first()
+            .is_some_and(|item| item.scheduled.due <= now)
+        {
+            let item = self.items.remove(0);
+
+            let status = match execute(item.scheduled.command, &item.payload, &mut response) {
+                Ok(_) => Status::Ack,
+                Err(reason) => Status::Nack(reason),
+            };
+
+            self.finish(item.scheduled, Outcome::Executed(status));
+            executed += 1;
+        }
+
+        executed
+    }
+
+    /// Device time the next command is due at
+    pub fn next_due(&self) -> Option<Timestamp> {
+        self.items.first().map(|item| item.scheduled.due)
+    }
+
+    /// The scheduled commands, in the order they are due
+    pub fn items(&self) -> impl Iterator<Item = &Scheduled> + '_ {
+        self.items.iter().map(|item| &item.scheduled)
+    }
+
+    /// Remove a command from the schedule
+    pub fn cancel(&mut self, id: ItemId) -> Result<(), Error> {
+        let index = self
+            .items
+            .iter()
+            .position(|item| item.scheduled.id == id)
+            .ok_or(Error::UnknownItem)?;
+
+        let item = self.items.remove(index);
+        self.finish(item.scheduled, Outcome::Cancelled);
+
+        Ok(())
+    }
+
+    /// What is known about a scheduled command
+    pub fn state(&self, id: ItemId) -> ItemState {
+        if let Some(item) = self.items().find(|item| item.id == id) {
+            return ItemState::Pending(*item);
+        }
+
+        match self.finished.iter().find(|report| report.item.id == id) {
+            Some(report) => ItemState::Finished(*report),
+            None => ItemState::Unknown,
+        }
+    }
+
+    /// The next report to send to the ground
+    pub fn next_report(&mut self) -> Option<Report> {
+        self.reports.pop_front()
+    }
+
+    fn finish(&mut self, item: Scheduled, outcome: Outcome) {
+        let report = Report {
+            item,
+            finished: self.now,
+            outcome,
+        };
+
+        // The oldest reports are dropped, the ground can still query for them
+        for queue in [&mut self.finished, &mut self.reports] {
+            if queue.is_full() {
+                queue.pop_front();
+            }
+            queue.push_back(report).expect("space was made");
+        }
+    }
+}
+
+impl<const N: usize> Default for Timeline<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> CommandHandler<ScheduleCommand> for Timeline<N> {
+    type Error = Error;
+
+    fn handle(&mut self, command: ScheduleCommand) -> Result<Scheduled, Error> {
+        let due = match command.when {
+            When::At(due) if due < self.now => return Err(Error::Past),
+            When::At(due) => due,
+            When::After(delay) => self.now + delay,
+        };
+
+        if self.items.is_full() {
+            return Err(Error::Full);
+        }
+
+        let scheduled = Scheduled {
+            id: self.next,
+            command: command.command,
+            due,
+        };
+        self.next = ItemId::new(self.next.get().wrapping_add(1));
+
+        let index = self.items.partition_point(|item| item.scheduled.due <= due);
+        let item = Item {
+            scheduled,
+            payload: command.payload,
+        };
+        if self.items.insert(index, item).is_err() {
+            unreachable!("checked for space");
+        }
+
+        Ok(scheduled)
+    }
+}
+
+impl<const N: usize> CommandHandler<ListScheduled> for Timeline<N> {
+    type Error = core::convert::Infallible;
+
+    fn handle(&mut self, command: ListScheduled) -> Result<SchedulePage, Self::Error> {
+        Ok(SchedulePage {
+            total: self.items.len() as u16,
+            start: command.start,
+            items: self
+                .items()
+                .skip(command.start as usize)
+                .take(PAGE_LEN)
+                .copied()
+                .collect(),
+        })
+    }
+}
+
+impl<const N: usize> CommandHandler<CancelScheduled> for Timeline<N> {
+    type Error = Error;
+
+    fn handle(&mut self, command: CancelScheduled) -> Result<(), Error> {
+        self.cancel(command.id)
+    }
+}
+
+impl<const N: usize> CommandHandler<QueryScheduled> for Timeline<N> {
+    type Error = core::convert::Infallible;
+
+    fn handle(&mut self, command: QueryScheduled) -> Result<ItemState, Self::Error> {
+        Ok(self.state(command.id))
+    }
+}
@@ -0,0 +1,259 @@
+use crate::{
+    telecommand::{self, Commander, NackReason, Sequence, Status, Telecommand},
+    time::Timestamp,
+    wire::{self, Frame, Header},
+};
+
+use super::{
+    CancelScheduled, ItemId, ItemState, Outcome, QueryScheduled, Report, ScheduleCommand,
+    Scheduled, When,
+};
+
+/// What the ground knows about a command it asked the device to schedule
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fate {
+    /// Waiting for the device to store the command
+    Requested,
+    /// The device refused to store the command, see
+    /// [`Error::from_reason`](super::Error::from_reason)
+    Refused(NackReason),
+    /// Stored on the device, waiting for its time
+    Scheduled,
+    /// Executed by the device at the given device time
+    Executed(Status, Timestamp),
+    /// Removed from the schedule before it was due
+    Cancelled,
+    /// The device did not answer, or no longer remembers the command. It may
+    /// or may not have been executed
+    Unknown,
+}
+
+impl Fate {
+    /// Whether the fate of the command can no longer change
+    pub fn is_final(&self) -> bool {
+        matches!(
+            self,
+            Fate::Refused(_) | Fate::Executed(..) | Fate::Cancelled
+        )
+    }
+}
+
+/// A command the ground asked the device to schedule
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tracked {
+    /// Sequence number of the [`ScheduleCommand`], identifying it on the ground
+    pub request: Sequence,
+    pub command: telecommand::CommandId,
+    pub when: When,
+    /// How the device stored the command, once it acknowledged it
+    pub scheduled: Option<Scheduled>,
+    pub fate: Fate,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Request {
+    Schedule,
+    Cancel(ItemId),
+    Query(ItemId),
+}
+
+/// Ground side record of the commands scheduled on a device
+///
+/// Like the [`Commander`] it issues commands with, the tracker does not
+/// perform any IO itself. Responses to its commands must be handed to
+/// [`receive`](Self::receive), the outcomes resolved by the commander to
+/// [`outcome`](Self::outcome) and [`Report`] telemetry to
+/// [`report`](Self::report). Since reports may be lost, the fate of a command
+/// that should have been executed can be asked for with
+/// [`query`](Self::query).
+#[derive(Debug, Default)]
+pub struct Tracker {
+    items: Vec<Tracked>,
+    requests: Vec<(Sequence, Request)>,
+}
+
+impl Tracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask the device to execute `command` when it is due
+    ///
+    /// Returns the sequence number the command is tracked by, and the encoded
+    /// frame to send. Fails with [`wire::Error::BufferTooSmall`] if the
+    /// command is longer than [`MAX_PAYLOAD`](super::MAX_PAYLOAD).
+    pub fn schedule<C: Telecommand>(
+        &mut self,
+        commander: &mut Commander<Vec<u8>>,
+        command: &C,
+        when: When,
+        now: Timestamp,
+    ) -> Result<(Sequence, Vec<u8>), wire::Error> {
+        let payload = postcard::to_stdvec(command)?;
+        let schedule = ScheduleCommand {
+            when,
+            command: C::ID,
+            payload: payload
+                .as_slice()
+                .try_into()
+                .map_err(|_| wire::Error::BufferTooSmall)?,
+        };
+
+        let frame = commander.issue(ScheduleCommand::ID, postcard::to_stdvec(&schedule)?, now);
+        let request = frame.header.sequence;
+
+        self.requests.push((request, Request::Schedule));
+        self.items.push(Tracked {
+            request,
+            command: C::ID,
+            when,
+            scheduled: None,
+            fate: Fate::Requested,
+        });
+
+        Ok((request, frame.to_wire()))
+    }
+
+    /// Ask the device to cancel a scheduled command
+    pub fn cancel(
+        &mut self,
+        commander: &mut Commander<Vec<u8>>,
+        id: ItemId,
+        now: Timestamp,
+    ) -> Result<Vec<u8>, wire::Error> {
+        self.issue(commander, &CancelScheduled { id }, Request::Cancel(id), now)
+    }
+
+    /// Ask the device what became of a scheduled command
+    pub fn query(
+        &mut self,
+        commander: &mut Commander<Vec<u8>>,
+        id: ItemId,
+        now: Timestamp,
+    ) -> Result<Vec<u8>, wire::Error> {
+        self.issue(commander, &QueryScheduled { id }, Request::Query(id), now)
+    }
+
+    /// Account for a frame received from the device
+    ///
+    /// Returns whether the frame answered one of the commands of the tracker.
+    pub fn receive(&mut self, frame: &Frame<'_>) -> bool {
+        let Header::Response(header) = frame.header else {
+            return false;
+        };
+        let Some(index) = self.request(header.sequence) else {
+            return false;
+        };
+        let (sequence, request) = self.requests.remove(index);
+
+        match (request, header.status) {
+            (Request::Schedule, Status::Ack) => {
+                let scheduled = frame.decode_payload::<Scheduled>().ok();
+                if let Some(item) = self.get_mut(sequence) {
+                    item.scheduled = scheduled;
+                    item.fate = match scheduled {
+                        Some(_) => Fate::Scheduled,
+                        None => Fate::Unknown,
+                    };
+                }
+            }
+            (Request::Schedule, Status::Nack(reason)) => {
+                if let Some(item) = self.get_mut(sequence) {
+                    item.fate = Fate::Refused(reason);
+                }
+            }
+            (Request::Cancel(id), Status::Ack) => self.settle(id, Fate::Cancelled),
+            (Request::Query(id), Status::Ack) => match frame.decode_payload::<ItemState>() {
+                Ok(ItemState::Pending(_)) => self.settle(id, Fate::Scheduled),
+                Ok(ItemState::Finished(report)) => self.report(&report),
+                Ok(ItemState::Unknown) | Err(_) => self.settle(id, Fate::Unknown),
+            },
+            (Request::Cancel(_) | Request::Query(_), Status::Nack(_)) => {}
+        }
+
+        true
+    }
+
+    /// Account for a command resolved by the [`Commander`]
+    ///
+    /// A command that timed out leaves the fate of what it scheduled unknown.
+    pub fn outcome(&mut self, outcome: &telecommand::Outcome) {
+        let (telecommand::Outcome::TimedOut(header) | telecommand::Outcome::Unknown(header)) =
+            outcome
+        else {
+            return;
+        };
+        let Some(index) = self.request(header.sequence) else {
+            return;
+        };
+
+        let (sequence, request) = self.requests.remove(index);
+        if request == Request::Schedule {
+            if let Some(item) = self.get_mut(sequence) {
+                item.fate = Fate::Unknown;
+            }
+        }
+    }
+
+    /// Account for a [`Report`] received as telemetry
+    pub fn report(&mut self, report: &Report) {
+        let fate = match report.outcome {
+            Outcome::Executed(status) => Fate::Executed(status, report.finished),
+            Outcome::Cancelled => Fate::Cancelled,
+        };
+
+        self.settle(report.item.id, fate);
+    }
+
+    /// The command scheduled by the request with the given sequence number
+    pub fn get(&self, request: Sequence) -> Option<&Tracked> {
+        self.items.iter().find(|item| item.request == request)
+    }
+
+    /// Every tracked command, in the order they were requested
+    pub fn iter(&self) -> impl Iterator<Item = &Tracked> + '_ {
+        self.items.iter()
+    }
+
+    /// Stop tracking the commands whose fate can no longer change
+    pub fn forget_final(&mut self) {
+        self.items.retain(|item| !item.fate.is_final());
+    }
+
+    fn issue<C: Telecommand>(
+        &mut self,
+        commander: &mut Commander<Vec<u8>>,
+        command: &C,
+        request: Request,
+        now: Timestamp,
+    ) -> Result<Vec<u8>, wire::Error> {
+        let frame = commander.issue(C::ID, postcard::to_stdvec(command)?, now);
+        self.requests.push((frame.header.sequence, request));
+
+        Ok(frame.to_wire())
+    }
+
+    fn request(&self, sequence: Sequence) -> Option<usize> {
+        self.requests
+            .iter()
+            .position(|&(request, _)| request == sequence)
+    }
+
+    fn get_mut(&mut self, request: Sequence) -> Option<&mut Tracked> {
+        self.items.iter_mut().find(|item| item.request == request)
+    }
+
+    /// Update the fate of a command the device scheduled, unless it is final
+    fn settle(&mut self, id: ItemId, fate: Fate) {
+        let item = self
+            .items
+            .iter_mut()
+            .find(|item| item.scheduled.is_some_and(|scheduled| scheduled.id == id));
+
+        if let Some(item) = item {
+            if !item.fate.is_final() {
+                item.fate = fate;
+            }
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use core::fmt::{self, Display};
+
+use crc::{Crc, CRC_32_ISO_HDLC};
+use heapless::Vec;
+use serde::{Deserialize, Serialize};
+
+use crate::telecommand::{builtin, failure_codes};
+
+#[cfg(feature = "std")]
+pub use ground::{Download, State, Upload};
+pub use handler::{BlobStorage, Transfers};
+
+#[cfg(feature = "std")]
+mod ground;
+mod handler;
+
+/// Most bytes of a blob carried by a single command or response
+pub const CHUNK_LEN: usize = 128;
+
+static CRC: Crc<u32> = Crc::<u32>::new(&CRC_32_ISO_HDLC);
+
+/// Checksum of a whole blob, CRC-32 as used by zlib
+pub fn checksum(data: &[u8]) -> u32 {
+    CRC.checksum(data)
+}
+
+/// Identifier of a blob stored on a device, such as a calibration table
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct BlobId(u16);
+
+impl BlobId {
+    pub const fn new(id: u16) -> Self {
+        Self(id)
+    }
+
+    pub const fn get(self) -> u16 {
+        self.0
+    }
+}
+
+impl Display for BlobId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "blob#{}", self.0)
+    }
+}
+
+/// How far along a transfer is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    /// Bytes acknowledged so far
+    pub transferred: u32,
+    /// Size of the whole blob, if already known
+    pub total: Option<u32>,
+}
+
+impl Progress {
+    /// Fraction of the blob transferred, between 0 and 1
+    pub fn fraction(&self) -> Option<f32> {
+        match self.total {
+            Some(0) => Some(1.0),
+            Some(total) => Some(self.transferred as f32 / total as f32),
+            None => None,
+        }
+    }
+}
+
+/// Why a device refused a transfer command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// The device has no such blob, or no upload of it is in progress
+    UnknownBlob,
+    /// The chunk does not continue the blob where the device expects it
+    BadOffset,
+    /// The blob does not match the checksum it was announced with
+    Checksum,
+    /// The blob could not be read from or written to the storage of the device
+    Storage,
+}
+
+failure_codes! {
+    /// Recover the error a device refused a transfer command with
+    Error {
+        UnknownBlob = 1,
+        BadOffset = 2,
+        Checksum = 3,
+        Storage = 4,
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::UnknownBlob => write!(f, "unknown blob"),
+            Error::BadOffset => write!(f, "chunk at unexpected offset"),
+            Error::Checksum => write!(f, "blob checksum mismatch"),
+            Error::Storage => write!(f, "blob storage failed"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+/// Announce a blob to be uploaded to the device
+///
+/// Responded to with the number of bytes the device already holds, non zero
+/// when resuming an interrupted upload of the same blob.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StartUpload {
+    pub blob: BlobId,
+    pub len: u32,
+    pub checksum: u32,
+}
+
+/// Part of a blob being uploaded, responded to with the bytes received so far
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UploadChunk {
+    pub blob: BlobId,
+    pub offset: u32,
+    pub data: Vec<u8, CHUNK_LEN>,
+}
+
+/// Verify a completely uploaded blob against its checksum
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FinishUpload {
+    pub blob: BlobId,
+}
+
+/// Ask for the size and checksum of a blob to download from the device
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StartDownload {
+    pub blob: BlobId,
+}
+
+/// Size and checksum of a blob, in response to [`StartDownload`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlobInfo {
+    pub len: u32,
+    pub checksum: u32,
+}
+
+/// Read up to [`CHUNK_LEN`] bytes of a blob, starting at `offset`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DownloadChunk {
+    pub blob: BlobId,
+    pub offset: u32,
+}
+
+builtin! {
+    StartUpload => START_UPLOAD, u32;
+    UploadChunk => UPLOAD_CHUNK, u32;
+    FinishUpload => FINISH_UPLOAD, ();
+    StartDownload => START_DOWNLOAD, BlobInfo;
+    DownloadChunk => DOWNLOAD_CHUNK, Vec<u8, CHUNK_LEN>;
+}
@@ -0,0 +1,427 @@
+use std::fmt;
+
+use crate::{
+    telecommand::{Commander, NackReason, Outcome, Sequence, Status, Telecommand},
+    time::Timestamp,
+    wire::{self, Frame, Header},
+};
+
+use super::{
+    checksum, BlobId, BlobInfo, DownloadChunk, Error, FinishUpload, Progress, StartDownload,
+    StartUpload, UploadChunk, CHUNK_LEN,
+};
+
+type Callback = Box<dyn FnMut(Progress) + Send>;
+
+/// Where a transfer stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+    /// Commands are being exchanged with the device
+    Active,
+    /// A command went unanswered, the transfer waits to be
+    /// [resumed](Upload::resume)
+    Interrupted,
+    /// The whole blob was transferred and matched its checksum
+    Complete,
+    /// The device refused the transfer, see [`Error::from_reason`]
+    Failed(NackReason),
+}
+
+/// The command a transfer sends next
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    Start,
+    Chunk(u32),
+    Finish,
+}
+
+/// Bookkeeping shared by uploads and downloads
+struct Exchange {
+    blob: BlobId,
+    step: Step,
+    outstanding: Option<Sequence>,
+    state: State,
+    callback: Option<Callback>,
+}
+
+impl Exchange {
+    fn new(blob: BlobId) -> Self {
+        Self {
+            blob,
+            step: Step::Start,
+            outstanding: None,
+            state: State::Active,
+            callback: None,
+        }
+    }
+
+    /// Whether the next command is due
+    fn is_due(&self) -> bool {
+        self.state == State::Active && self.outstanding.is_none()
+    }
+
+    fn send<C: Telecommand>(
+        &mut self,
+        commander: &mut Commander<Vec<u8>>,
+        command: &C,
+        now: Timestamp,
+    ) -> Result<Vec<u8>, wire::Error> {
+        let payload = postcard::to_stdvec(command)?;
+        let frame = commander.issue(C::ID, payload, now);
+
+        self.outstanding = Some(frame.header.sequence);
+
+        Ok(frame.to_wire())
+    }
+
+    /// The status of the response to the outstanding command, if `frame` is it
+    fn response(&mut self, frame: &Frame<'_>) -> Option<Status> {
+        let Header::Response(header) = frame.header else {
+            return None;
+        };
+        if self.outstanding != Some(header.sequence) {
+            return None;
+        }
+
+        self.outstanding = None;
+
+        Some(header.status)
+    }
+
+    fn outcome(&mut self, outcome: &Outcome) {
+        if self.outstanding != Some(outcome.header().sequence) {
+            return;
+        }
+
+        if let Outcome::TimedOut(_) | Outcome::Unknown(_) = outcome {
+            self.outstanding = None;
+            self.state = State::Interrupted;
+        }
+    }
+
+    fn resume(&mut self) {
+        if self.state != State::Complete {
+            self.step = Step::Start;
+            self.outstanding = None;
+            self.state = State::Active;
+        }
+    }
+
+    fn fail(&mut self, reason: NackReason) {
+        self.state = State::Failed(reason);
+    }
+
+    fn notify(&mut self, progress: Progress) {
+        if let Some(callback) = &mut self.callback {
+            callback(progress);
+        }
+    }
+}
+
+impl fmt::Debug for Exchange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Exchange")
+            .field("blob", &self.blob)
+            .field("step", &self.step)
+            .field("outstanding", &self.outstanding)
+            .field("state", &self.state)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Ground side of sending a blob to a device
+///
+/// Like the [`Commander`] it sends its commands with, an upload does not
+/// perform any IO itself. Frames returned from [`poll`](Self::poll) must be
+/// sent to the device, responses handed to [`receive`](Self::receive) and the
+/// outcomes resolved by the commander to [`outcome`](Self::outcome). One chunk
+/// is in flight at a time, each acknowledged before the next is sent.
+#[derive(Debug)]
+pub struct Upload {
+    exchange: Exchange,
+    data: Vec<u8>,
+    checksum: u32,
+    acknowledged: u32,
+}
+
+impl Upload {
+    /// Upload `data` as the new version of `blob`
+    ///
+    /// # Panics
+    ///
+    /// If `data` is longer than `u32::MAX` bytes.
+    pub fn new(blob: BlobId, data: Vec<u8>) -> Self {
+        assert!(data.len() <= u32::MAX as usize, "blob too large");
+
+        Self {
+            exchange: Exchange::new(blob),
+            checksum: checksum(&data),
+            data,
+            acknowledged: 0,
+        }
+    }
+
+    /// Call `callback` every time the device acknowledges part of the blob
+    pub fn on_progress(mut self, callback: impl FnMut(Progress) + Send + 'static) -> Self {
+        self.exchange.callback = Some(Box::new(callback));
+        self
+    }
+
+    pub fn blob(&self) -> BlobId {
+        self.exchange.blob
+    }
+
+    pub fn state(&self) -> State {
+        self.exchange.state
+    }
+
+    pub fn progress(&self) -> Progress {
+        Progress {
+            transferred: self.acknowledged,
+            total: Some(self.len()),
+        }
+    }
+
+    /// Return the next command to send, if the previous one was answered
+    pub fn poll(
+        &mut self,
+        commander: &mut Commander<Vec<u8>>,
+        now: Timestamp,
+    ) -> Result<Option<Vec<u8>>, wire::Error> {
+        if !self.exchange.is_due() {
+            return Ok(None);
+        }
+
+        let blob = self.exchange.blob;
+        let frame = match self.exchange.step {
+            Step::Start => {
+                let start = StartUpload {
+                    blob,
+                    len: self.len(),
+                    checksum: self.checksum,
+                };
+                self.exchange.send(commander, &start, now)?
+            }
+            Step::Chunk(offset) => {
+                let start = offset as usize;
+                let end = self.data.len().min(start + CHUNK_LEN);
+                let chunk = UploadChunk {
+                    blob,
+                    offset,
+                    data: self.data[start..end]
+                        .try_into()
+                        .expect("no longer than a chunk"),
+                };
+                self.exchange.send(commander, &chunk, now)?
+            }
+            Step::Finish => self.exchange.send(commander, &FinishUpload { blob }, now)?,
+        };
+
+        Ok(Some(frame))
+    }
+
+    /// Account for a frame received from the device
+    ///
+    /// Returns whether the frame answered the upload.
+    pub fn receive(&mut self, frame: &Frame<'_>) -> bool {
+        let Some(status) = self.exchange.response(frame) else {
+            return false;
+        };
+        let step = self.exchange.step;
+
+        match status {
+            Status::Ack if step == Step::Finish => {
+                self.exchange.state = State::Complete;
+            }
+            Status::Ack => match frame.decode_payload::<u32>() {
+                Ok(received) if received <= self.len() => {
+                    self.acknowledged = received;
+                    self.exchange.step = self.next_chunk();
+                    self.exchange.notify(self.progress());
+                }
+                _ => self.exchange.fail(NackReason::Malformed),
+            },
+            // The device lost track of the upload, announce it again to find
+            // out what it still holds
+            Status::Nack(reason)
+                if matches!(step, Step::Chunk(_))
+                    && matches!(
+                        Error::from_reason(reason),
+                        Some(Error::UnknownBlob | Error::BadOffset)
+                    ) =>
+            {
+                self.exchange.step = Step::Start;
+            }
+            Status::Nack(reason) => self.exchange.fail(reason),
+        }
+
+        true
+    }
+
+    /// Account for a command resolved by the [`Commander`]
+    ///
+    /// An upload command that timed out interrupts the upload.
+    pub fn outcome(&mut self, outcome: &Outcome) {
+        self.exchange.outcome(outcome);
+    }
+
+    /// Continue an interrupted or failed upload from where the device stopped
+    pub fn resume(&mut self) {
+        self.exchange.resume();
+    }
+
+    fn len(&self) -> u32 {
+        self.data.len() as u32
+    }
+
+    fn next_chunk(&self) -> Step {
+        if self.acknowledged < self.len() {
+            Step::Chunk(self.acknowledged)
+        } else {
+            Step::Finish
+        }
+    }
+}
+
+/// Ground side of fetching a blob from a device
+///
+/// Driven the same way as an [`Upload`]. The downloaded blob is checked
+/// against the checksum reported by the device before it is complete.
+#[derive(Debug)]
+pub struct Download {
+    exchange: Exchange,
+    info: Option<BlobInfo>,
+    data: Vec<u8>,
+}
+
+impl Download {
+    pub fn new(blob: BlobId) -> Self {
+        Self {
+            exchange: Exchange::new(blob),
+            info: None,
+            data: Vec::new(),
+        }
+    }
+
+    /// Call `callback` every time part of the blob is received
+    pub fn on_progress(mut self, callback: impl FnMut(Progress) + Send + 'static) -> Self {
+        self.exchange.callback = Some(Box::new(callback));
+        self
+    }
+
+    pub fn blob(&self) -> BlobId {
+        self.exchange.blob
+    }
+
+    pub fn state(&self) -> State {
+        self.exchange.state
+    }
+
+    pub fn progress(&self) -> Progress {
+        Progress {
+            transferred: self.data.len() as u32,
+            total: self.info.map(|info| info.len),
+        }
+    }
+
+    /// The blob received so far, complete once the [`state`](Self::state) is
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+
+    /// Return the next command to send, if the previous one was answered
+    pub fn poll(
+        &mut self,
+        commander: &mut Commander<Vec<u8>>,
+        now: Timestamp,
+    ) -> Result<Option<Vec<u8>>, wire::Error> {
+        if !self.exchange.is_due() {
+            return Ok(None);
+        }
+
+        let blob = self.exchange.blob;
+        let frame = match self.exchange.step {
+            Step::Start => self
+                .exchange
+                .send(commander, &StartDownload { blob }, now)?,
+            Step::Chunk(offset) => {
+                let chunk = DownloadChunk { blob, offset };
+                self.exchange.send(commander, &chunk, now)?
+            }
+            Step::Finish => unreachable!("downloads are verified on the ground"),
+        };
+
+        Ok(Some(frame))
+    }
+
+    /// Account for a frame received from the device
+    ///
+    /// Returns whether the frame answered the download.
+    pub fn receive(&mut self, frame: &Frame<'_>) -> bool {
+        let Some(status) = self.exchange.response(frame) else {
+            return false;
+        };
+
+        match (status, self.exchange.step) {
+            (Status::Ack, Step::Start) => match frame.decode_payload::<BlobInfo>() {
+                Ok(info) => {
+                    // Keep what was received of the same blob before an interruption
+                    if self.info != Some(info) {
+                        self.data.clear();
+                    }
+
+                    self.info = Some(info);
+                    self.next_chunk();
+                }
+                Err(_) => self.exchange.fail(NackReason::Malformed),
+            },
+            (Status::Ack, Step::Chunk(_)) => match frame.decode_payload::<Vec<u8>>() {
+                Ok(chunk) if !chunk.is_empty() => {
+                    self.data.extend_from_slice(&chunk);
+                    self.next_chunk();
+                }
+                _ => self.exchange.fail(NackReason::Malformed),
+            },
+            (Status::Ack, Step::Finish) => {}
+            (Status::Nack(reason), _) => self.exchange.fail(reason),
+        }
+
+        true
+    }
+
+    /// Account for a command resolved by the [`Commander`]
+    ///
+    /// A download command that timed out interrupts the download.
+    pub fn outcome(&mut self, outcome: &Outcome) {
+        self.exchange.outcome(outcome);
+    }
+
+    /// Continue an interrupted or failed download, keeping what was received if
+    /// the blob did not change on the device in the meantime
+    pub fn resume(&mut self) {
+        self.exchange.resume();
+    }
+
+    fn next_chunk(&mut self) {
+        let Some(info) = self.info else {
+            return;
+        };
+        let received = self.data.len() as u32;
+
+        if received < info.len {
+            self.exchange.step = Step::Chunk(received);
+        } else if received == info.len && checksum(&self.data) == info.checksum {
+            self.exchange.step = Step::Finish;
+            self.exchange.state = State::Complete;
+        } else {
+            self.data.clear();
+            self.exchange.fail(Error::Checksum.into());
+        }
+
+        self.exchange.notify(self.progress());
+    }
+}
@@ -0,0 +1,227 @@
+use heapless::Vec;
+
+use crate::telecommand::{CommandHandler, Router};
+
+use super::{
+    BlobId, BlobInfo, DownloadChunk, Error, FinishUpload, StartDownload, StartUpload, UploadChunk,
+    CHUNK_LEN, CRC,
+};
+
+/// Where a device keeps its blobs
+pub trait BlobStorage {
+    type Error;
+
+    /// Size of a stored blob, `None` if there is no such blob
+    fn len(&mut self, blob: BlobId) -> Option<u32>;
+
+    /// Read the blob from `offset` into `buffer`, returning the bytes read
+    ///
+    /// Reads the committed version of the blob, never one still being written.
+    fn read(&mut self, blob: BlobId, offset: u32, buffer: &mut [u8]) -> Result<usize, Self::Error>;
+
+    /// Read the new version of a blob, as [`read`](Self::read) does
+    ///
+    /// Used to verify what was written before it is committed.
+    fn read_pending(
+        &mut self,
+        blob: BlobId,
+        offset: u32,
+        buffer: &mut [u8],
+    ) -> Result<usize, Self::Error>;
+
+    /// Make room for a new version of a blob, `len` bytes long
+    ///
+    /// The blob being replaced should be kept until [`commit`](Self::commit),
+    /// and still be read by [`read`](Self::read).
+    fn create(&mut self, blob: BlobId, len: u32) -> Result<(), Self::Error>;
+
+    /// Write part of the new version of a blob
+    fn write(&mut self, blob: BlobId, offset: u32, data: &[u8]) -> Result<(), Self::Error>;
+
+    /// Replace a blob with its new version once it was verified
+    fn commit(&mut self, blob: BlobId) -> Result<(), Self::Error>;
+}
+
+/// Either [`BlobStorage::read`] or [`BlobStorage::read_pending`]
+type Read<S> = fn(&mut S, BlobId, u32, &mut [u8]) -> Result<usize, <S as BlobStorage>::Error>;
+
+#[derive(Debug, Clone, Copy)]
+struct Incoming {
+    blob: BlobId,
+    len: u32,
+    checksum: u32,
+    received: u32,
+}
+
+/// Device side of blob transfers
+///
+/// Handles [`StartUpload`], [`UploadChunk`], [`FinishUpload`],
+/// [`StartDownload`] and [`DownloadChunk`], see [`router`](Self::router). One
+/// upload can be in progress at a time, announcing another blob abandons it.
+#[derive(Debug)]
+pub struct Transfers<S> {
+    storage: S,
+    upload: Option<Incoming>,
+}
+
+impl<S: BlobStorage> Transfers<S> {
+    pub const fn new(storage: S) -> Self {
+        Self {
+            storage,
+            upload: None,
+        }
+    }
+
+    /// Routes the transfer commands to the transfers
+    pub fn router() -> Router<Self, 5> {
+        Router::new()
+            .route::<StartUpload>()
+            .route::<UploadChunk>()
+            .route::<FinishUpload>()
+            .route::<StartDownload>()
+            .route::<DownloadChunk>()
+    }
+
+    /// The blob being uploaded and the bytes received of it
+    pub fn upload(&self) -> Option<(BlobId, u32)> {
+        self.upload.map(|upload| (upload.blob, upload.received))
+    }
+
+    pub fn storage(&mut self) -> &mut S {
+        &mut self.storage
+    }
+
+    /// Checksum of the first `len` bytes of a blob, read back from the storage
+    fn checksum(&mut self, blob: BlobId, len: u32, read: Read<S>) -> Result<u32, Error> {
+        let mut digest = CRC.digest();
+        let mut buffer = [0; CHUNK_LEN];
+        let mut offset = 0;
+
+        while offset < len {
+            let want = CHUNK_LEN.min((len - offset) as usize);
+            let read = read(&mut self.storage, blob, offset, &mut buffer[..want])
+                .map_err(|_| Error::Storage)?;
+            if read == 0 {
+                return Err(Error::Storage);
+            }
+
+            digest.update(&buffer[..read]);
+            offset += read as u32;
+        }
+
+        Ok(digest.finalize())
+    }
+}
+
+impl<S: BlobStorage> CommandHandler<StartUpload> for Transfers<S> {
+    type Error = Error;
+
+    fn handle(&mut self, command: StartUpload) -> Result<u32, Error> {
+        if let Some(upload) = self.upload {
+            let resumed = upload.blob == command.blob
+                && upload.len == command.len
+                && upload.checksum == command.checksum;
+
+            if resumed {
+                return Ok(upload.received);
+            }
+        }
+
+        self.upload = None;
+        self.storage
+            .create(command.blob, command.len)
+            .map_err(|_| Error::Storage)?;
+        self.upload = Some(Incoming {
+            blob: command.blob,
+            len: command.len,
+            checksum: command.checksum,
+            received: 0,
+        });
+
+        Ok(0)
+    }
+}
+
+impl<S: BlobStorage> CommandHandler<UploadChunk> for Transfers<S> {
+    type Error = Error;
+
+    fn handle(&mut self, command: UploadChunk) -> Result<u32, Error> {
+        let upload = self
+            .upload
+            .as_mut()
+            .filter(|upload| upload.blob == command.blob)
+            .ok_or(Error::UnknownBlob)?;
+
+        let end = command.offset as u64 + command.data.len() as u64;
+        if command.offset != upload.received || end > upload.len as u64 {
+            return Err(Error::BadOffset);
+        }
+
+        self.storage
+            .write(command.blob, command.offset, &command.data)
+            .map_err(|_| Error::Storage)?;
+        upload.received = end as u32;
+
+        Ok(upload.received)
+    }
+}
+
+impl<S: BlobStorage> CommandHandler<FinishUpload> for Transfers<S> {
+    type Error = Error;
+
+    fn handle(&mut self, command: FinishUpload) -> Result<(), Error> {
+        let upload = self
+            .upload
+            .filter(|upload| upload.blob == command.blob)
+            .ok_or(Error::UnknownBlob)?;
+
+        if upload.received != upload.len {
+            return Err(Error::BadOffset);
+        }
+
+        // Whatever the outcome, the upload has to start over
+        self.upload = None;
+
+        if self.checksum(upload.blob, upload.len, S::read_pending)? != upload.checksum {
+            return Err(Error::Checksum);
+        }
+
+        self.storage.commit(upload.blob).map_err(|_| Error::Storage)
+    }
+}
+
+impl<S: BlobStorage> CommandHandler<StartDownload> for Transfers<S> {
+    type Error = Error;
+
+    fn handle(&mut self, command: StartDownload) -> Result<BlobInfo, Error> {
+        let len = self.storage.len(command.blob).ok_or(Error::UnknownBlob)?;
+
+        Ok(BlobInfo {
+            len,
+            checksum: self.checksum(command.blob, len, S::read)?,
+        })
+    }
+}
+
+impl<S: BlobStorage> CommandHandler<DownloadChunk> for Transfers<S> {
+    type Error = Error;
+
+    fn handle(&mut self, command: DownloadChunk) -> Result<Vec<u8, CHUNK_LEN>, Error> {
+        let len = self.storage.len(command.blob).ok_or(Error::UnknownBlob)?;
+        if command.offset > len {
+            return Err(Error::BadOffset);
+        }
+
+        let mut chunk = Vec::new();
+        let want = CHUNK_LEN.min((len - command.offset) as usize);
+        chunk.resize_default(want).expect("no longer than a chunk");
+
+        let read = self
+            .storage
+            .read(command.blob, command.offset, &mut chunk)
+            .map_err(|_| Error::Storage)?;
+        chunk.truncate(read);
+
+        Ok(chunk)
+    }
+}
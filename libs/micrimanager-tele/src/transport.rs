@@ -0,0 +1,76 @@
+use std::io;
+
+use crate::wire::DELIMITER;
+
+pub use loopback::Loopback;
+pub use lossy::{Lossy, LossyConfig, LossyStats};
+pub use replay::Replay;
+#[cfg(feature = "serial")]
+pub use serial::Serial;
+pub use tcp::Tcp;
+pub use udp::Udp;
+
+pub mod loopback;
+mod lossy;
+mod replay;
+#[cfg(feature = "serial")]
+mod serial;
+mod tcp;
+mod udp;
+
+/// A link that carries encoded frames
+///
+/// Frames are passed around exactly as produced by the [`wire`](crate::wire)
+/// encoder, including the trailing [`DELIMITER`]. Transports never block on
+/// receive, so a single thread can service several of them.
+pub trait Transport {
+    /// Send a single encoded frame
+    fn send(&mut self, frame: &[u8]) -> io::Result<()>;
+
+    /// Receive the next complete frame, or `None` if there is none available yet
+    fn recv(&mut self) -> io::Result<Option<Vec<u8>>>;
+}
+
+impl<T: Transport + ?Sized> Transport for &mut T {
+    fn send(&mut self, frame: &[u8]) -> io::Result<()> {
+        (**self).send(frame)
+    }
+
+    fn recv(&mut self) -> io::Result<Option<Vec<u8>>> {
+        (**self).recv()
+    }
+}
+
+impl<T: Transport + ?Sized> Transport for Box<T> {
+    fn send(&mut self, frame: &[u8]) -> io::Result<()> {
+        (**self).send(frame)
+    }
+
+    fn recv(&mut self) -> io::Result<Option<Vec<u8>>> {
+        (**self).recv()
+    }
+}
+
+/// Splits the bytes of a stream oriented transport into frames
+#[derive(Debug, Default)]
+struct Framer {
+    buffer: Vec<u8>,
+}
+
+impl Framer {
+    fn extend(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    fn next_frame(&mut self) -> Option<Vec<u8>> {
+        loop {
+            let end = self.buffer.iter().position(|&byte| byte == DELIMITER)?;
+            let frame: Vec<u8> = self.buffer.drain(..=end).collect();
+
+            // Skip the empty frames of a transmitter flushing the line
+            if frame.len() > 1 {
+                return Some(frame);
+            }
+        }
+    }
+}
@@ -0,0 +1,47 @@
+//! In process transport, for connecting the ground to a simulated device
+
+use std::{
+    io,
+    sync::mpsc::{self, Receiver, Sender, TryRecvError},
+};
+
+use super::Transport;
+
+/// One end of an in process link, created with [`pair`]
+#[derive(Debug)]
+pub struct Loopback {
+    tx: Sender<Vec<u8>>,
+    rx: Receiver<Vec<u8>>,
+}
+
+/// Create two connected ends of a link
+///
+/// Frames sent on one end are received on the other in order and without loss.
+pub fn pair() -> (Loopback, Loopback) {
+    let (a_tx, b_rx) = mpsc::channel();
+    let (b_tx, a_rx) = mpsc::channel();
+
+    (
+        Loopback { tx: a_tx, rx: a_rx },
+        Loopback { tx: b_tx, rx: b_rx },
+    )
+}
+
+impl Transport for Loopback {
+    fn send(&mut self, frame: &[u8]) -> io::Result<()> {
+        self.tx
+            .send(frame.to_vec())
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "other end was dropped"))
+    }
+
+    fn recv(&mut self) -> io::Result<Option<Vec<u8>>> {
+        match self.rx.try_recv() {
+            Ok(frame) => Ok(Some(frame)),
+            Err(TryRecvError::Empty) => Ok(None),
+            Err(TryRecvError::Disconnected) => Err(io::Error::new(
+                io::ErrorKind::ConnectionAborted,
+                "other end was dropped",
+            )),
+        }
+    }
+}
@@ -0,0 +1,225 @@
+use std::{collections::VecDeque, io, mem};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use super::Transport;
+
+/// Impairments applied by a [`Lossy`] transport
+///
+/// All probabilities are per frame and have to be in the range `0.0..=1.0`,
+/// [`Lossy::new`] refuses any other. The default configuration does not impair
+/// the link at all.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LossyConfig {
+    /// Seed of the random number generator, equal seeds give equal impairments
+    pub seed: u64,
+    /// Probability of a frame being lost
+    pub drop: f64,
+    /// Probability of a frame being delivered twice
+    pub duplicate: f64,
+    /// Probability of a frame being delivered after the frame following it, or
+    /// after [`max_delay`](Self::max_delay) calls to [`recv`](Transport::recv)
+    /// if no frame follows it by then
+    pub reorder: f64,
+    /// Probability of a frame being held back
+    pub delay: f64,
+    /// Maximum number of calls to [`recv`](Transport::recv) a frame is held back for
+    pub max_delay: u32,
+    /// Probability of a single bit of a frame being flipped
+    pub corrupt: f64,
+}
+
+impl Default for LossyConfig {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            drop: 0.0,
+            duplicate: 0.0,
+            reorder: 0.0,
+            delay: 0.0,
+            max_delay: 10,
+            corrupt: 0.0,
+        }
+    }
+}
+
+/// Count of the impairments applied by a [`Lossy`] transport
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LossyStats {
+    pub dropped: usize,
+    pub duplicated: usize,
+    pub reordered: usize,
+    pub delayed: usize,
+    pub corrupted: usize,
+}
+
+/// Wraps a transport, impairing the frames passing through it in both directions
+///
+/// Simulates a bad radio link to test that the protocol survives one. Time is
+/// measured in calls to [`recv`](Transport::recv), which keeps the simulation
+/// deterministic for a given [`seed`](LossyConfig::seed).
+#[derive(Debug)]
+pub struct Lossy<T> {
+    inner: T,
+    config: LossyConfig,
+    rng: StdRng,
+    stats: LossyStats,
+    tick: u64,
+
+    outbound: Direction,
+    inbound: Direction,
+    received: VecDeque<Vec<u8>>,
+}
+
+#[derive(Debug, Default)]
+struct Direction {
+    /// Frames held back, with the tick they are released at
+    delayed: Vec<(u64, Vec<u8>)>,
+    /// Frame waiting for the next one to overtake it, with the tick it is
+    /// released at if none does
+    reordered: Option<(u64, Vec<u8>)>,
+}
+
+impl<T: Transport> Lossy<T> {
+    /// # Panics
+    ///
+    /// If a probability of `config` is not in the range `0.0..=1.0`.
+    pub fn new(inner: T, config: LossyConfig) -> Self {
+        for (name, probability) in [
+            ("drop", config.drop),
+            ("duplicate", config.duplicate),
+            ("reorder", config.reorder),
+            ("delay", config.delay),
+            ("corrupt", config.corrupt),
+        ] {
+            assert!(
+                (0.0..=1.0).contains(&probability),
+                "{} probability {} is not in the range 0.0..=1.0",
+                name,
+                probability
+            );
+        }
+
+        Self {
+            inner,
+            rng: StdRng::seed_from_u64(config.seed),
+            config,
+            stats: LossyStats::default(),
+            tick: 0,
+
+            outbound: Direction::default(),
+            inbound: Direction::default(),
+            received: VecDeque::new(),
+        }
+    }
+
+    pub fn stats(&self) -> LossyStats {
+        self.stats
+    }
+
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Apply impairments to a frame, returning the frames to deliver right away
+    fn impair(&mut self, mut frame: Vec<u8>, outbound: bool) -> Vec<Vec<u8>> {
+        let config = self.config;
+
+        if self.rng.gen_bool(config.drop) {
+            self.stats.dropped += 1;
+
+            return Vec::new();
+        }
+
+        if !frame.is_empty() && self.rng.gen_bool(config.corrupt) {
+            let bit = self.rng.gen_range(0..frame.len() * 8);
+            frame[bit / 8] ^= 1 << (bit % 8);
+
+            self.stats.corrupted += 1;
+        }
+
+        let copies = if self.rng.gen_bool(config.duplicate) {
+            self.stats.duplicated += 1;
+
+            2
+        } else {
+            1
+        };
+
+        let mut deliver = Vec::new();
+        for frame in vec![frame; copies] {
+            let direction = match outbound {
+                true => &mut self.outbound,
+                false => &mut self.inbound,
+            };
+
+            if config.max_delay > 0 && self.rng.gen_bool(config.delay) {
+                let delay = self.rng.gen_range(1..=config.max_delay);
+                direction.delayed.push((self.tick + delay as u64, frame));
+
+                self.stats.delayed += 1;
+            } else if direction.reordered.is_none() && self.rng.gen_bool(config.reorder) {
+                let release = self.tick + config.max_delay.max(1) as u64;
+                direction.reordered = Some((release, frame));
+
+                self.stats.reordered += 1;
+            } else {
+                deliver.push(frame);
+                deliver.extend(direction.reordered.take().map(|(_, frame)| frame));
+            }
+        }
+
+        deliver
+    }
+
+    /// Take the delayed frames that are due, and the reordered frame if nothing
+    /// overtook it in time
+    fn release(direction: &mut Direction, tick: u64) -> Vec<Vec<u8>> {
+        let (mut due, delayed): (Vec<_>, _) = mem::take(&mut direction.delayed)
+            .into_iter()
+            .partition(|(release, _)| *release <= tick);
+        direction.delayed = delayed;
+
+        if direction
+            .reordered
+            .as_ref()
+            .is_some_and(|(release, _)| *release <= tick)
+        {
+            due.extend(direction.reordered.take());
+        }
+
+        due.into_iter().map(|(_, frame)| frame).collect()
+    }
+}
+
+impl<T: Transport> Transport for Lossy<T> {
+    fn send(&mut self, frame: &[u8]) -> io::Result<()> {
+        for frame in self.impair(frame.to_vec(), true) {
+            self.inner.send(&frame)?;
+        }
+
+        Ok(())
+    }
+
+    fn recv(&mut self) -> io::Result<Option<Vec<u8>>> {
+        self.tick += 1;
+
+        for frame in Self::release(&mut self.outbound, self.tick) {
+            self.inner.send(&frame)?;
+        }
+
+        self.received
+            .extend(Self::release(&mut self.inbound, self.tick));
+
+        while let Some(frame) = self.inner.recv()? {
+            let deliver = self.impair(frame, false);
+            self.received.extend(deliver);
+        }
+
+        Ok(self.received.pop_front())
+    }
+}
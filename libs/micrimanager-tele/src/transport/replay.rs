@@ -0,0 +1,206 @@
+use std::{
+    io::{self, Read},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use crate::recording::{Reader, Record};
+
+use super::Transport;
+
+/// Plays back a recording as if its frames were being received live
+///
+/// Frames are delivered with the same spacing they were received with,
+/// scaled by the playback speed. Frames sent to a replay go nowhere.
+///
+/// Playback is driven by [`poll`](Self::poll) and the other methods taking the
+/// current [`Instant`], which [`recv`](Transport::recv) calls with the real
+/// time.
+#[derive(Debug)]
+pub struct Replay {
+    /// Frames with their offset from the start of the recording
+    frames: Vec<(Duration, Vec<u8>)>,
+    next: usize,
+    speed: f64,
+    looping: bool,
+    paused: bool,
+    /// Position in the recording at the given instant
+    anchor: (Instant, Duration),
+}
+
+impl Replay {
+    /// Replay the records in the order given, at normal speed
+    ///
+    /// A record received before the one preceding it, as after the wall clock
+    /// was set back, is delivered right after that one.
+    pub fn new(records: impl IntoIterator<Item = Record>, now: Instant) -> Self {
+        let records: Vec<_> = records.into_iter().collect();
+        let start = records.first().map(|record| record.received);
+
+        let mut previous = Duration::ZERO;
+        let frames = records
+            .into_iter()
+            .map(|record| {
+                let offset = start
+                    .and_then(|start| record.received.duration_since(start).ok())
+                    .unwrap_or_default()
+                    .max(previous);
+                previous = offset;
+
+                (offset, record.frame)
+            })
+            .collect();
+
+        Self {
+            frames,
+            next: 0,
+            speed: 1.0,
+            looping: false,
+            paused: false,
+            anchor: (now, Duration::ZERO),
+        }
+    }
+
+    /// Replay every frame of a recording, starting now
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::from_reader(Reader::open(path)?, Instant::now())
+    }
+
+    pub fn from_reader<R: Read>(reader: Reader<R>, now: Instant) -> io::Result<Self> {
+        Ok(Self::new(reader.collect::<io::Result<Vec<_>>>()?, now))
+    }
+
+    /// Start over from the beginning once the end of the recording is reached
+    ///
+    /// A recording whose frames were all received at the same time is still
+    /// played once only.
+    pub fn looping(mut self, looping: bool) -> Self {
+        self.looping = looping;
+        self
+    }
+
+    /// Play back at a multiple of the original speed
+    ///
+    /// # Panics
+    ///
+    /// If `speed` is not a positive, finite number.
+    pub fn speed(mut self, speed: f64) -> Self {
+        self.speed = check_speed(speed);
+        self
+    }
+
+    /// Change the playback speed without jumping in the recording
+    ///
+    /// # Panics
+    ///
+    /// If `speed` is not a positive, finite number.
+    pub fn set_speed(&mut self, speed: f64, now: Instant) {
+        self.anchor = (now, self.position(now));
+        self.speed = check_speed(speed);
+    }
+
+    /// Time between the first and the last frame of the recording
+    pub fn duration(&self) -> Duration {
+        self.frames
+            .last()
+            .map(|&(offset, _)| offset)
+            .unwrap_or_default()
+    }
+
+    /// Position of playback in the recording
+    pub fn position(&self, now: Instant) -> Duration {
+        let (instant, position) = self.anchor;
+
+        if self.paused {
+            return position;
+        }
+
+        position + now.saturating_duration_since(instant).mul_f64(self.speed)
+    }
+
+    pub fn pause(&mut self, now: Instant) {
+        self.anchor = (now, self.position(now));
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self, now: Instant) {
+        self.anchor = (now, self.position(now));
+        self.paused = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Jump to a position in the recording
+    ///
+    /// Frames before the position are skipped, the first frame at or after it
+    /// is delivered once playback reaches it.
+    pub fn seek(&mut self, position: Duration, now: Instant) {
+        self.next = self
+            .frames
+            .partition_point(|&(offset, _)| offset < position);
+        self.anchor = (now, position);
+    }
+
+    /// Whether every frame has been delivered and the replay does not loop
+    pub fn is_finished(&self) -> bool {
+        !self.loops() && self.next == self.frames.len()
+    }
+
+    /// The next frame due for delivery
+    pub fn poll(&mut self, now: Instant) -> Option<Vec<u8>> {
+        if self.next == self.frames.len() && self.loops() {
+            let position = self.position(now);
+            let duration = self.duration();
+
+            if position >= duration {
+                self.next = 0;
+                self.anchor = (now, position - duration);
+            }
+        }
+
+        let (offset, frame) = self.frames.get(self.next)?;
+
+        if *offset > self.position(now) {
+            return None;
+        }
+
+        self.next += 1;
+
+        Some(frame.clone())
+    }
+
+    /// Whether playback starts over at the end, which would never end for a
+    /// recording without duration
+    fn loops(&self) -> bool {
+        self.looping && !self.duration().is_zero()
+    }
+}
+
+fn check_speed(speed: f64) -> f64 {
+    assert!(
+        speed.is_finite() && speed > 0.0,
+        "replay speed must be positive, got {}",
+        speed
+    );
+
+    speed
+}
+
+impl Transport for Replay {
+    fn send(&mut self, _frame: &[u8]) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn recv(&mut self) -> io::Result<Option<Vec<u8>>> {
+        if self.is_finished() {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "end of recording",
+            ));
+        }
+
+        Ok(self.poll(Instant::now()))
+    }
+}
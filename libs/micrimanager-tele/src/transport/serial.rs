@@ -0,0 +1,49 @@
+use std::{
+    io::{self, Read, Write},
+    time::Duration,
+};
+
+use serialport::SerialPort;
+
+use super::{Framer, Transport};
+
+/// Frames sent over a serial port, the usual link to a board on the bench
+pub struct Serial {
+    port: Box<dyn SerialPort>,
+    framer: Framer,
+}
+
+impl Serial {
+    pub fn open(path: &str, baud_rate: u32) -> io::Result<Self> {
+        let port = serialport::new(path, baud_rate)
+            .timeout(Duration::from_millis(100))
+            .open()?;
+
+        Ok(Self {
+            port,
+            framer: Framer::default(),
+        })
+    }
+}
+
+impl Transport for Serial {
+    fn send(&mut self, frame: &[u8]) -> io::Result<()> {
+        self.port.write_all(frame)?;
+        self.port.flush()
+    }
+
+    fn recv(&mut self) -> io::Result<Option<Vec<u8>>> {
+        if let Some(frame) = self.framer.next_frame() {
+            return Ok(Some(frame));
+        }
+
+        let available = self.port.bytes_to_read()? as usize;
+        if available > 0 {
+            let mut buffer = vec![0; available];
+            self.port.read_exact(&mut buffer)?;
+            self.framer.extend(&buffer);
+        }
+
+        Ok(self.framer.next_frame())
+    }
+}
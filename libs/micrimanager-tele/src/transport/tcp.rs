@@ -0,0 +1,71 @@
+use std::{
+    io::{self, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+};
+
+use super::{Framer, Transport};
+
+/// Frames sent over a TCP stream, for devices behind a network bridge or simulators
+#[derive(Debug)]
+pub struct Tcp {
+    stream: TcpStream,
+    framer: Framer,
+    closed: bool,
+}
+
+impl Tcp {
+    pub fn connect(address: impl ToSocketAddrs) -> io::Result<Self> {
+        Self::from_stream(TcpStream::connect(address)?)
+    }
+
+    pub fn from_stream(stream: TcpStream) -> io::Result<Self> {
+        stream.set_nodelay(true)?;
+
+        Ok(Self {
+            stream,
+            framer: Framer::default(),
+            closed: false,
+        })
+    }
+
+    fn read_available(&mut self) -> io::Result<()> {
+        let mut buffer = [0; 1024];
+
+        loop {
+            match self.stream.read(&mut buffer) {
+                Ok(0) => {
+                    self.closed = true;
+
+                    return Ok(());
+                }
+                Ok(len) => self.framer.extend(&buffer[..len]),
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+                Err(error) => return Err(error),
+            }
+        }
+    }
+}
+
+impl Transport for Tcp {
+    fn send(&mut self, frame: &[u8]) -> io::Result<()> {
+        self.stream.write_all(frame)
+    }
+
+    fn recv(&mut self) -> io::Result<Option<Vec<u8>>> {
+        if !self.closed {
+            self.stream.set_nonblocking(true)?;
+            let read = self.read_available();
+            self.stream.set_nonblocking(false)?;
+            read?;
+        }
+
+        match self.framer.next_frame() {
+            None if self.closed => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "connection closed",
+            )),
+            frame => Ok(frame),
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use std::{
+    io,
+    net::{ToSocketAddrs, UdpSocket},
+};
+
+use super::Transport;
+
+/// Frames sent as UDP datagrams, one frame per datagram
+#[derive(Debug)]
+pub struct Udp {
+    socket: UdpSocket,
+}
+
+impl Udp {
+    /// Bind to `local` and exchange frames with `remote` only
+    pub fn connect(local: impl ToSocketAddrs, remote: impl ToSocketAddrs) -> io::Result<Self> {
+        let socket = UdpSocket::bind(local)?;
+        socket.connect(remote)?;
+
+        Self::from_socket(socket)
+    }
+
+    /// Use an already connected socket
+    pub fn from_socket(socket: UdpSocket) -> io::Result<Self> {
+        socket.set_nonblocking(true)?;
+
+        Ok(Self { socket })
+    }
+}
+
+impl Transport for Udp {
+    fn send(&mut self, frame: &[u8]) -> io::Result<()> {
+        self.socket.send(frame).map(|_| ())
+    }
+
+    fn recv(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let mut buffer = [0; u16::MAX as usize];
+
+        match self.socket.recv(&mut buffer) {
+            Ok(len) => Ok(Some(buffer[..len].to_vec())),
+            Err(error) if error.kind() == io::ErrorKind::WouldBlock => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+}
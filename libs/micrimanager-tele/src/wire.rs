@@ -0,0 +1,471 @@
+use core::fmt::{self, Display};
+
+use crc::{Crc, CRC_16_IBM_3740};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    telecommand::{CommandHeader, CommandId, NackReason, ResponseHeader, Sequence, Status},
+    telemetry::{ChannelId, Packet, Telemetry, TelemetryHeader},
+    time::Timestamp,
+};
+
+/// Byte terminating every frame on the wire
+///
+/// COBS guarantees this byte never appears inside of a frame, so a receiver
+/// that lost sync can always resume at the next delimiter.
+pub const DELIMITER: u8 = 0x00;
+
+/// Length of the longest header of any frame kind
+pub const MAX_HEADER_LEN: usize = 13;
+
+const CRC: Crc<u16> = Crc::<u16>::new(&CRC_16_IBM_3740);
+const CRC_LEN: usize = 2;
+
+const KIND_TELEMETRY: u8 = 0x01;
+const KIND_COMMAND: u8 = 0x02;
+const KIND_RESPONSE: u8 = 0x03;
+
+const STATUS_ACK: u8 = 0x00;
+const STATUS_UNKNOWN_COMMAND: u8 = 0x01;
+const STATUS_MALFORMED: u8 = 0x02;
+const STATUS_BUSY: u8 = 0x03;
+const STATUS_FAILED: u8 = 0x04;
+const STATUS_STALE: u8 = 0x05;
+const STATUS_RESPONSE_TOO_LARGE: u8 = 0x06;
+const STATUS_UNAUTHENTICATED: u8 = 0x07;
+const STATUS_REPLAYED: u8 = 0x08;
+const STATUS_WRONG_MODE: u8 = 0x09;
+const STATUS_NOT_ARMED: u8 = 0x0a;
+
+/// Size of the buffer needed to encode a frame with a `payload_len` byte payload
+///
+/// Includes the header, checksum, COBS overhead and the trailing [`DELIMITER`].
+pub const fn max_encoded_len(payload_len: usize) -> usize {
+    let raw = MAX_HEADER_LEN + payload_len + CRC_LEN;
+
+    raw + raw / 254 + 1 + 1
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// The provided buffer can not hold the frame
+    BufferTooSmall,
+    /// The frame is not valid COBS
+    Framing,
+    /// The frame checksum does not match its contents, it was corrupted in transit
+    Checksum,
+    /// The frame ended in the middle of its header
+    Truncated,
+    /// The frame kind is not known to this version of the protocol
+    UnknownKind(u8),
+    /// The response status is not known to this version of the protocol
+    UnknownStatus(u8),
+    /// The payload could not be serialized or deserialized
+    Payload(postcard::Error),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::BufferTooSmall => write!(f, "buffer too small to hold frame"),
+            Error::Framing => write!(f, "frame is not valid COBS"),
+            Error::Checksum => write!(f, "frame checksum mismatch"),
+            Error::Truncated => write!(f, "frame ended before its header"),
+            Error::UnknownKind(kind) => write!(f, "unknown frame kind {:#04x}", kind),
+            Error::UnknownStatus(status) => write!(f, "unknown response status {:#04x}", status),
+            Error::Payload(error) => write!(f, "invalid payload: {}", error),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+impl From<postcard::Error> for Error {
+    fn from(error: postcard::Error) -> Self {
+        match error {
+            postcard::Error::SerializeBufferFull => Error::BufferTooSmall,
+            error => Error::Payload(error),
+        }
+    }
+}
+
+/// The routing information of any frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Header {
+    Telemetry(TelemetryHeader),
+    Command(CommandHeader),
+    Response(ResponseHeader),
+}
+
+/// A decoded frame, borrowing its payload from the receive buffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Frame<'a> {
+    pub header: Header,
+    pub payload: &'a [u8],
+}
+
+impl<'a> Frame<'a> {
+    /// Deserialize the payload of the frame
+    pub fn decode_payload<T: Deserialize<'a>>(&self) -> Result<T, Error> {
+        Ok(postcard::from_bytes(self.payload)?)
+    }
+}
+
+/// Encode a frame, serializing `payload` in the process
+///
+/// `scratch` holds the frame before COBS encoding and `out` the finished frame,
+/// see [`max_encoded_len`] for the size they need.
+pub fn encode<'o, T: Serialize + ?Sized>(
+    header: &Header,
+    payload: &T,
+    scratch: &mut [u8],
+    out: &'o mut [u8],
+) -> Result<&'o [u8], Error> {
+    encode_with(header, scratch, out, |buffer| {
+        Ok(postcard::to_slice(payload, buffer)?.len())
+    })
+}
+
+/// Encode a frame with an already serialized payload
+pub fn encode_raw<'o>(
+    header: &Header,
+    payload: &[u8],
+    scratch: &mut [u8],
+    out: &'o mut [u8],
+) -> Result<&'o [u8], Error> {
+    encode_with(header, scratch, out, |buffer| {
+        buffer
+            .get_mut(..payload.len())
+            .ok_or(Error::BufferTooSmall)?
+            .copy_from_slice(payload);
+
+        Ok(payload.len())
+    })
+}
+
+/// Encode a frame into a newly allocated buffer
+#[cfg(feature = "std")]
+pub fn to_vec<T: Serialize + ?Sized>(header: &Header, payload: &T) -> Result<Vec<u8>, Error> {
+    let payload = postcard::to_stdvec(payload)?;
+
+    Ok(to_vec_raw(header, &payload))
+}
+
+/// Encode a frame with an already serialized payload into a newly allocated buffer
+#[cfg(feature = "std")]
+pub fn to_vec_raw(header: &Header, payload: &[u8]) -> Vec<u8> {
+    let mut scratch = vec![0; MAX_HEADER_LEN + payload.len() + CRC_LEN];
+    let mut out = vec![0; max_encoded_len(payload.len())];
+
+    let len = encode_raw(header, payload, &mut scratch, &mut out)
+        .expect("buffers are sized for the frame")
+        .len();
+    out.truncate(len);
+
+    out
+}
+
+fn encode_with<'o>(
+    header: &Header,
+    scratch: &mut [u8],
+    out: &'o mut [u8],
+    write_payload: impl FnOnce(&mut [u8]) -> Result<usize, Error>,
+) -> Result<&'o [u8], Error> {
+    let mut writer = Writer {
+        buffer: scratch,
+        position: 0,
+    };
+    write_header(&mut writer, header)?;
+
+    let Writer { buffer, position } = writer;
+
+    let payload_len = write_payload(buffer.get_mut(position..).ok_or(Error::BufferTooSmall)?)?;
+    let mut len = position + payload_len;
+
+    let crc = CRC.checksum(&buffer[..len]);
+    buffer
+        .get_mut(len..len + CRC_LEN)
+        .ok_or(Error::BufferTooSmall)?
+        .copy_from_slice(&crc.to_le_bytes());
+    len += CRC_LEN;
+
+    if out.len() < cobs::max_encoding_length(len) + 1 {
+        return Err(Error::BufferTooSmall);
+    }
+
+    let encoded = cobs::encode(&buffer[..len], out);
+    out[encoded] = DELIMITER;
+
+    Ok(&out[..encoded + 1])
+}
+
+/// Decode a single frame in place
+///
+/// The trailing [`DELIMITER`] may or may not be included.
+pub fn decode(frame: &mut [u8]) -> Result<Frame<'_>, Error> {
+    let len = match frame.last() {
+        Some(&DELIMITER) => frame.len() - 1,
+        _ => frame.len(),
+    };
+    let frame = &mut frame[..len];
+
+    let len = cobs::decode_in_place(frame).map_err(|()| Error::Framing)?;
+    let frame = &frame[..len];
+
+    if len < CRC_LEN {
+        return Err(Error::Truncated);
+    }
+
+    let (body, crc) = frame.split_at(len - CRC_LEN);
+    if CRC.checksum(body).to_le_bytes() != crc {
+        return Err(Error::Checksum);
+    }
+
+    let mut reader = Reader { buffer: body };
+    let header = read_header(&mut reader)?;
+
+    Ok(Frame {
+        header,
+        payload: reader.buffer,
+    })
+}
+
+fn write_header(writer: &mut Writer, header: &Header) -> Result<(), Error> {
+    match header {
+        Header::Telemetry(header) => {
+            writer.write(&[KIND_TELEMETRY])?;
+            writer.write(&header.channel.get().to_le_bytes())?;
+            writer.write(&header.sequence.to_le_bytes())?;
+            writer.write(&header.timestamp.as_micros().to_le_bytes())?;
+        }
+        Header::Command(header) => {
+            writer.write(&[KIND_COMMAND])?;
+            writer.write(&header.sequence.get().to_le_bytes())?;
+            writer.write(&header.command.get().to_le_bytes())?;
+        }
+        Header::Response(header) => {
+            writer.write(&[KIND_RESPONSE])?;
+            writer.write(&header.sequence.get().to_le_bytes())?;
+
+            match header.status {
+                Status::Ack => writer.write(&[STATUS_ACK])?,
+                Status::Nack(NackReason::UnknownCommand) => {
+                    writer.write(&[STATUS_UNKNOWN_COMMAND])?
+                }
+                Status::Nack(NackReason::Malformed) => writer.write(&[STATUS_MALFORMED])?,
+                Status::Nack(NackReason::Busy) => writer.write(&[STATUS_BUSY])?,
+                Status::Nack(NackReason::Failed(code)) => writer.write(&[STATUS_FAILED, code])?,
+                Status::Nack(NackReason::Stale) => writer.write(&[STATUS_STALE])?,
+                Status::Nack(NackReason::ResponseTooLarge) => {
+                    writer.write(&[STATUS_RESPONSE_TOO_LARGE])?
+                }
+                Status::Nack(NackReason::Unauthenticated) => {
+                    writer.write(&[STATUS_UNAUTHENTICATED])?
+                }
+                Status::Nack(NackReason::Replayed) => writer.write(&[STATUS_REPLAYED])?,
+                Status::Nack(NackReason::WrongMode) => writer.write(&[STATUS_WRONG_MODE])?,
+                Status::Nack(NackReason::NotArmed) => writer.write(&[STATUS_NOT_ARMED])?,
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn read_header(reader: &mut Reader) -> Result<Header, Error> {
+    Ok(match reader.u8()? {
+        KIND_TELEMETRY => Header::Telemetry(TelemetryHeader {
+            channel: ChannelId::new(reader.u16()?),
+            sequence: reader.u16()?,
+            timestamp: Timestamp::from_micros(reader.u64()?),
+        }),
+        KIND_COMMAND => Header::Command(CommandHeader {
+            sequence: Sequence::new(reader.u16()?),
+            command: CommandId::new(reader.u16()?),
+        }),
+        KIND_RESPONSE => Header::Response(ResponseHeader {
+            sequence: Sequence::new(reader.u16()?),
+            status: match reader.u8()? {
+                STATUS_ACK => Status::Ack,
+                STATUS_UNKNOWN_COMMAND => Status::Nack(NackReason::UnknownCommand),
+                STATUS_MALFORMED => Status::Nack(NackReason::Malformed),
+                STATUS_BUSY => Status::Nack(NackReason::Busy),
+                STATUS_FAILED => Status::Nack(NackReason::Failed(reader.u8()?)),
+                STATUS_STALE => Status::Nack(NackReason::Stale),
+                STATUS_RESPONSE_TOO_LARGE => Status::Nack(NackReason::ResponseTooLarge),
+                STATUS_UNAUTHENTICATED => Status::Nack(NackReason::Unauthenticated),
+                STATUS_REPLAYED => Status::Nack(NackReason::Replayed),
+                STATUS_WRONG_MODE => Status::Nack(NackReason::WrongMode),
+                STATUS_NOT_ARMED => Status::Nack(NackReason::NotArmed),
+                status => return Err(Error::UnknownStatus(status)),
+            },
+        }),
+        kind => return Err(Error::UnknownKind(kind)),
+    })
+}
+
+struct Writer<'b> {
+    buffer: &'b mut [u8],
+    position: usize,
+}
+
+impl Writer<'_> {
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        let end = self.position + bytes.len();
+
+        self.buffer
+            .get_mut(self.position..end)
+            .ok_or(Error::BufferTooSmall)?
+            .copy_from_slice(bytes);
+        self.position = end;
+
+        Ok(())
+    }
+}
+
+struct Reader<'b> {
+    buffer: &'b [u8],
+}
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], Error> {
+        if self.buffer.len() < N {
+            return Err(Error::Truncated);
+        }
+
+        let (bytes, rest) = self.buffer.split_at(N);
+        self.buffer = rest;
+
+        Ok(bytes.try_into().expect("split at N"))
+    }
+
+    fn u8(&mut self) -> Result<u8, Error> {
+        Ok(u8::from_le_bytes(self.take()?))
+    }
+
+    fn u16(&mut self) -> Result<u16, Error> {
+        Ok(u16::from_le_bytes(self.take()?))
+    }
+
+    fn u64(&mut self) -> Result<u64, Error> {
+        Ok(u64::from_le_bytes(self.take()?))
+    }
+}
+
+/// Fixed capacity buffers for encoding frames without an allocator
+///
+/// `N` is the longest encoded frame that can be produced, see [`max_encoded_len`].
+#[derive(Debug, Clone)]
+pub struct Encoder<const N: usize> {
+    scratch: [u8; N],
+    out: [u8; N],
+}
+
+impl<const N: usize> Encoder<N> {
+    pub const fn new() -> Self {
+        Self {
+            scratch: [0; N],
+            out: [0; N],
+        }
+    }
+
+    /// Encode a frame, returning the bytes to put on the wire
+    pub fn encode<T: Serialize + ?Sized>(
+        &mut self,
+        header: &Header,
+        payload: &T,
+    ) -> Result<&[u8], Error> {
+        encode(header, payload, &mut self.scratch, &mut self.out)
+    }
+
+    /// Encode a frame with an already serialized payload
+    pub fn encode_raw(&mut self, header: &Header, payload: &[u8]) -> Result<&[u8], Error> {
+        encode_raw(header, payload, &mut self.scratch, &mut self.out)
+    }
+
+    /// Encode a telemetry packet
+    pub fn telemetry<T: Telemetry>(
+        &mut self,
+        sequence: u16,
+        packet: &Packet<T>,
+    ) -> Result<&[u8], Error> {
+        let header = Header::Telemetry(TelemetryHeader {
+            channel: T::CHANNEL,
+            sequence,
+            timestamp: packet.timestamp,
+        });
+
+        self.encode(&header, &packet.payload)
+    }
+}
+
+impl<const N: usize> Default for Encoder<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Fixed capacity buffer splitting a byte stream into frames
+///
+/// Bytes are pushed in as they are received, and a frame is produced every
+/// time a [`DELIMITER`] completes one. `N` is the longest encoded frame that
+/// can be received, longer frames are discarded.
+#[derive(Debug, Clone)]
+pub struct Decoder<const N: usize> {
+    buffer: [u8; N],
+    len: usize,
+    overflowed: bool,
+}
+
+impl<const N: usize> Decoder<N> {
+    pub const fn new() -> Self {
+        Self {
+            buffer: [0; N],
+            len: 0,
+            overflowed: false,
+        }
+    }
+
+    /// Push a received byte, returning the frame it completes if any
+    ///
+    /// Empty frames, as produced by a transmitter flushing the line with
+    /// delimiters, are skipped.
+    pub fn push(&mut self, byte: u8) -> Option<Result<Frame<'_>, Error>> {
+        if byte != DELIMITER {
+            match self.buffer.get_mut(self.len) {
+                Some(slot) => {
+                    *slot = byte;
+                    self.len += 1;
+                }
+                None => self.overflowed = true,
+            }
+
+            return None;
+        }
+
+        let len = core::mem::take(&mut self.len);
+
+        if core::mem::take(&mut self.overflowed) {
+            return Some(Err(Error::BufferTooSmall));
+        }
+
+        if len == 0 {
+            return None;
+        }
+
+        Some(decode(&mut self.buffer[..len]))
+    }
+
+    /// Discard any partially received frame
+    pub fn reset(&mut self) {
+        self.len = 0;
+        self.overflowed = false;
+    }
+}
+
+impl<const N: usize> Default for Decoder<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
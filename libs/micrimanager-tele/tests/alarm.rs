@@ -0,0 +1,241 @@
+#![cfg(feature = "std")]
+
+use std::time::{Duration, Instant};
+
+use micromanager_tele::{
+    alarm::{Alarms, Direction, Kind, Limits, Rule, Severity, Transition},
+    store::Store,
+    telemetry::{Telemetry, TelemetryHeader},
+    time::Timestamp,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Telemetry)]
+#[telemetry(channel = 1)]
+struct Battery {
+    volts: f32,
+    amps: f32,
+}
+
+const MS: Duration = Duration::from_millis(1);
+
+/// A packet sent every 100ms of device time
+fn header(sequence: u16) -> TelemetryHeader {
+    TelemetryHeader {
+        channel: Battery::CHANNEL,
+        sequence,
+        timestamp: Timestamp::from_micros(sequence as u64 * 100_000),
+    }
+}
+
+fn update(alarms: &mut Alarms, sequence: u16, volts: f32, now: Instant) {
+    let payload = postcard::to_allocvec(&Battery { volts, amps: 0.5 }).unwrap();
+
+    alarms.update(&header(sequence), &payload, now);
+}
+
+fn volts() -> Alarms {
+    Alarms::new().watch(
+        "volts",
+        |battery: &Battery| battery.volts as f64,
+        Rule::new().limits(
+            Limits::new()
+                .low(11.0, 10.5)
+                .high(13.0, 13.5)
+                .hysteresis(0.25),
+        ),
+    )
+}
+
+fn transitions(alarms: &Alarms) -> Vec<Transition> {
+    alarms.history().map(|entry| entry.transition).collect()
+}
+
+#[test]
+fn limits_raise_and_clear_with_hysteresis() {
+    let now = Instant::now();
+    let mut alarms = volts();
+
+    update(&mut alarms, 0, 12.0, now);
+    assert_eq!(alarms.alarms().count(), 0);
+
+    update(&mut alarms, 1, 13.0, now);
+    let alarm = *alarms.alarms().next().unwrap();
+    assert_eq!(alarm.kind, Kind::Limit);
+    assert_eq!(alarm.severity, Severity::Yellow);
+    assert_eq!(alarm.direction, Some(Direction::High));
+    assert!(alarm.active);
+
+    // Back under the threshold, but not by the hysteresis
+    update(&mut alarms, 2, 12.9, now);
+    assert_eq!(alarms.worst(), Some(Severity::Yellow));
+
+    update(&mut alarms, 3, 13.6, now);
+    update(&mut alarms, 4, 13.4, now);
+    assert_eq!(alarms.worst(), Some(Severity::Red));
+
+    update(&mut alarms, 5, 13.1, now);
+    assert_eq!(alarms.worst(), Some(Severity::Yellow));
+
+    update(&mut alarms, 6, 12.7, now);
+    assert_eq!(alarms.worst(), None);
+
+    assert_eq!(
+        transitions(&alarms),
+        [
+            Transition::Raised(Severity::Yellow),
+            Transition::Escalated(Severity::Red),
+            Transition::Downgraded(Severity::Yellow),
+            Transition::Cleared,
+        ]
+    );
+}
+
+#[test]
+fn cleared_alarms_are_shown_until_acknowledged() {
+    let now = Instant::now();
+    let mut alarms = volts();
+
+    update(&mut alarms, 0, 10.0, now);
+    update(&mut alarms, 1, 12.0, now + MS * 100);
+
+    let alarm = alarms.alarms().next().unwrap();
+    assert!(!alarm.active);
+    assert_eq!(alarm.severity, Severity::Red);
+    assert_eq!(alarm.direction, Some(Direction::Low));
+    assert_eq!(alarm.raised, now);
+
+    assert!(alarms.acknowledge("volts", Kind::Limit, now + MS * 200));
+    assert_eq!(alarms.alarms().count(), 0);
+    assert!(!alarms.acknowledge("volts", Kind::Limit, now + MS * 200));
+
+    // Acknowledged alarms stay shown while active, until escalated
+    update(&mut alarms, 2, 10.9, now);
+    alarms.acknowledge_all(now);
+    assert!(alarms.alarms().next().unwrap().acknowledged);
+
+    update(&mut alarms, 3, 10.4, now);
+    assert!(!alarms.alarms().next().unwrap().acknowledged);
+
+    alarms.acknowledge_all(now);
+    update(&mut alarms, 4, 12.0, now);
+    assert_eq!(alarms.alarms().count(), 0);
+
+    let last = alarms.history().last().unwrap();
+    assert_eq!(last.to_string(), "volts limit alarm cleared at 12");
+}
+
+#[test]
+fn history_can_be_turned_off() {
+    let now = Instant::now();
+    let mut alarms = volts().history_len(0);
+
+    for sequence in 0..10 {
+        let volts = if sequence % 2 == 0 { 12.0 } else { 10.0 };
+        update(&mut alarms, sequence, volts, now);
+    }
+
+    assert_eq!(alarms.history().count(), 0);
+    assert_eq!(alarms.worst(), Some(Severity::Red));
+}
+
+#[test]
+fn rate_of_change_uses_device_time() {
+    let now = Instant::now();
+    let mut alarms = Alarms::new().watch(
+        "volts",
+        |battery: &Battery| battery.volts as f64,
+        Rule::new().rate(Limits::new().low(-2.0, -5.0)),
+    );
+
+    update(&mut alarms, 0, 12.0, now);
+    // A tenth of a volt in a tenth of a second, received in a burst
+    update(&mut alarms, 1, 11.9, now);
+    assert_eq!(alarms.worst(), None);
+
+    update(&mut alarms, 2, 11.6, now);
+    let alarm = *alarms.alarms().next().unwrap();
+    assert_eq!(alarm.kind, Kind::Rate);
+    assert_eq!(alarm.severity, Severity::Yellow);
+    assert!((alarm.value.unwrap() + 3.0).abs() < 1e-3);
+
+    // Stale packets arriving late are ignored by the rate
+    update(&mut alarms, 1, 11.9, now);
+    assert_eq!(alarms.worst(), Some(Severity::Yellow));
+
+    update(&mut alarms, 4, 10.5, now);
+    assert_eq!(alarms.worst(), Some(Severity::Red));
+}
+
+#[test]
+fn rate_of_change_restarts_with_the_device() {
+    let now = Instant::now();
+    let mut alarms = Alarms::new().watch(
+        "volts",
+        |battery: &Battery| battery.volts as f64,
+        Rule::new().rate(Limits::new().low(-2.0, -5.0)),
+    );
+
+    update(&mut alarms, 100, 12.0, now);
+    update(&mut alarms, 101, 11.9, now);
+
+    // The device restarted, its clock and sequence numbers start over
+    update(&mut alarms, 0, 11.0, now);
+    assert_eq!(alarms.worst(), None);
+
+    update(&mut alarms, 1, 10.9, now);
+    assert_eq!(alarms.worst(), None);
+
+    update(&mut alarms, 2, 10.0, now);
+    assert_eq!(alarms.worst(), Some(Severity::Red));
+}
+
+#[test]
+fn silent_channels_raise_staleness_alarms() {
+    let start = Instant::now();
+    let mut store = Store::new().expect::<Battery>(MS * 100).tolerance(3);
+    let mut alarms = Alarms::new()
+        .watch(
+            "amps",
+            |battery: &Battery| battery.amps as f64,
+            Rule::new().stale(Severity::Red),
+        )
+        .history_len(2);
+
+    // Nothing received yet, nothing is known to be stale
+    alarms.poll(&store, start);
+    assert_eq!(alarms.worst(), None);
+
+    let payload = postcard::to_allocvec(&Battery {
+        volts: 12.0,
+        amps: 0.5,
+    })
+    .unwrap();
+    store.update(&header(0), &payload, start);
+    alarms.update(&header(0), &payload, start);
+
+    alarms.poll(&store, start + MS * 200);
+    assert_eq!(alarms.worst(), None);
+
+    alarms.poll(&store, start + MS * 400);
+    let alarm = alarms.alarms().next().unwrap();
+    assert_eq!(alarm.kind, Kind::Stale);
+    assert_eq!(alarm.value, None);
+
+    store.update(&header(5), &payload, start + MS * 500);
+    alarms.poll(&store, start + MS * 500);
+    alarms.acknowledge("amps", Kind::Stale, start + MS * 600);
+    assert_eq!(alarms.worst(), None);
+
+    // The oldest transition fell out of the history
+    assert_eq!(
+        transitions(&alarms),
+        [Transition::Cleared, Transition::Acknowledged]
+    );
+}
+
+#[test]
+#[should_panic(expected = "duplicate watch volts")]
+fn names_are_unique() {
+    let _ = volts().watch("volts", |_: &Battery| 0.0, Rule::new());
+}
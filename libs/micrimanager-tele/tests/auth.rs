@@ -0,0 +1,233 @@
+#![cfg(feature = "std")]
+
+use micromanager_tele::{
+    auth::{Key, Signer, Verifier, TRAILER_LEN},
+    handshake::{Handshake, Schema},
+    telecommand::{
+        CommandFrame, CommandHeader, Commander, Dispatcher, NackReason, Outcome, RetryPolicy,
+        Status, Telecommand,
+    },
+    time::Timestamp,
+    wire::{self, Header},
+};
+use serde::{Deserialize, Serialize};
+
+const SECRET: &[u8] = b"correct horse battery staple";
+
+#[derive(Serialize, Deserialize, Telecommand)]
+#[telecommand(id = 1, response = u8)]
+struct Deploy(u8);
+
+struct Device {
+    dispatcher: Dispatcher<4, 8>,
+    deployed: Vec<u8>,
+}
+
+impl Device {
+    fn new(verifier: Verifier) -> Self {
+        Self {
+            dispatcher: Dispatcher::new().with_verifier(verifier),
+            deployed: Vec::new(),
+        }
+    }
+
+    /// Answer a frame sent by the ground, returning the response header
+    fn answer(&mut self, mut frame: Vec<u8>) -> Header {
+        let frame = wire::decode(&mut frame).unwrap();
+        let Header::Command(header) = frame.header else {
+            panic!("expected a command, got {:?}", frame.header);
+        };
+
+        let deployed = &mut self.deployed;
+        let reply = self.dispatcher.dispatch(
+            header,
+            frame.payload,
+            Timestamp::ZERO,
+            |_, payload, response| {
+                let Deploy(panel) = postcard::from_bytes(payload).unwrap();
+                deployed.push(panel);
+                response[0] = panel;
+
+                Ok(1)
+            },
+        );
+
+        // Through the wire and back, as the ground would see it
+        let mut response = wire::to_vec_raw(&Header::Response(reply.header), reply.payload);
+        wire::decode(&mut response).unwrap().header
+    }
+}
+
+fn commander(key: &[u8]) -> Commander<Vec<u8>> {
+    Commander::new(RetryPolicy::default()).with_signer(Signer::new(Key::new(key), 0))
+}
+
+fn device() -> Device {
+    Device::new(Verifier::new(Key::new(SECRET)))
+}
+
+fn status(header: Header) -> Status {
+    let Header::Response(header) = header else {
+        panic!("expected a response, got {:?}", header);
+    };
+
+    header.status
+}
+
+#[test]
+fn signed_commands_are_executed() {
+    let mut commander = commander(SECRET);
+    let mut device = device();
+
+    let frame = commander
+        .issue_command(&Deploy(1), Timestamp::ZERO)
+        .unwrap();
+    let Header::Response(response) = device.answer(frame) else {
+        panic!("expected a response");
+    };
+
+    assert_eq!(response.status, Status::Ack);
+    assert!(matches!(
+        commander.receive(&response),
+        Some(Outcome::Acknowledged(_))
+    ));
+    // The handler never sees the trailer
+    assert_eq!(device.deployed, [1]);
+    assert_eq!(commander.signer().unwrap().counter(), 1);
+    assert_eq!(device.dispatcher.verifier().unwrap().last(), Some(0));
+}
+
+#[test]
+fn forged_commands_are_rejected() {
+    let mut device = device();
+
+    let mut unsigned = Commander::new(RetryPolicy::default());
+    let frame = unsigned.issue_command(&Deploy(1), Timestamp::ZERO).unwrap();
+    assert_eq!(
+        status(device.answer(frame)),
+        Status::Nack(NackReason::Unauthenticated)
+    );
+
+    let mut impostor = commander(b"guessed");
+    let frame = impostor.issue_command(&Deploy(2), Timestamp::ZERO).unwrap();
+    assert_eq!(
+        status(device.answer(frame)),
+        Status::Nack(NackReason::Unauthenticated)
+    );
+
+    // A genuine command whose payload was changed in flight
+    let mut commander = commander(SECRET);
+    let mut frame = commander.issue(Deploy::ID, vec![3], Timestamp::ZERO);
+    assert_eq!(frame.payload.len(), 1 + TRAILER_LEN);
+    frame.payload[0] = 4;
+    assert_eq!(
+        status(device.answer(frame.to_wire())),
+        Status::Nack(NackReason::Unauthenticated)
+    );
+
+    assert!(device.deployed.is_empty());
+    assert_eq!(device.dispatcher.last_executed(), None);
+}
+
+#[test]
+fn recorded_commands_can_not_be_replayed() {
+    let mut commander = commander(SECRET);
+    let mut device = device();
+
+    let recorded = commander
+        .issue_command(&Deploy(1), Timestamp::ZERO)
+        .unwrap();
+    assert_eq!(status(device.answer(recorded.clone())), Status::Ack);
+
+    // A retransmission is answered from memory, not executed again
+    assert_eq!(status(device.answer(recorded.clone())), Status::Ack);
+    assert_eq!(device.deployed, [1]);
+
+    // Not even once a handshake made the dispatcher forget what it executed
+    let handshake = commander
+        .issue_command(&Handshake(Schema::new()), Timestamp::ZERO)
+        .unwrap();
+    assert_eq!(status(device.answer(handshake)), Status::Ack);
+
+    assert_eq!(
+        status(device.answer(recorded.clone())),
+        Status::Nack(NackReason::Replayed)
+    );
+
+    // Nor after a restart of the device that kept its counter
+    let last = device.dispatcher.verifier().unwrap().last().unwrap();
+    let mut device = Device::new(Verifier::new(Key::new(SECRET)).with_last(last));
+    assert_eq!(
+        status(device.answer(recorded)),
+        Status::Nack(NackReason::Replayed)
+    );
+    assert!(device.deployed.is_empty());
+}
+
+#[test]
+fn recorded_built_in_commands_can_not_be_replayed() {
+    let mut commander = commander(SECRET);
+    let mut device = device();
+
+    let handshake = commander
+        .issue_command(&Handshake(Schema::new()), Timestamp::ZERO)
+        .unwrap();
+    assert_eq!(status(device.answer(handshake.clone())), Status::Ack);
+
+    let deploy = commander
+        .issue_command(&Deploy(1), Timestamp::ZERO)
+        .unwrap();
+    assert_eq!(status(device.answer(deploy)), Status::Ack);
+    let executed = device.dispatcher.last_executed();
+
+    let resync = commander.resync(Timestamp::ZERO).unwrap();
+    assert_eq!(status(device.answer(resync.clone())), Status::Ack);
+
+    for recorded in [handshake, resync] {
+        assert_eq!(
+            status(device.answer(recorded)),
+            Status::Nack(NackReason::Replayed)
+        );
+    }
+    // The replayed handshake did not start a new session
+    assert_eq!(device.dispatcher.last_executed(), executed);
+    assert!(executed.is_some());
+}
+
+#[test]
+fn commands_lost_before_newer_ones_still_get_through() {
+    let mut commander = commander(SECRET);
+    let mut device = device();
+
+    let lost = commander.issue(
+        Deploy::ID,
+        postcard::to_stdvec(&Deploy(1)).unwrap(),
+        Timestamp::ZERO,
+    );
+    let newer = commander
+        .issue_command(&Deploy(2), Timestamp::ZERO)
+        .unwrap();
+    assert_eq!(status(device.answer(newer)), Status::Ack);
+
+    // Retransmitted as it was first sent
+    assert_eq!(status(device.answer(lost.to_wire())), Status::Ack);
+    assert_eq!(device.deployed, [2, 1]);
+}
+
+#[test]
+fn untracked_commands_are_signed_on_request() {
+    let mut commander = commander(SECRET);
+    let mut device = device();
+
+    let mut frame = CommandFrame {
+        header: CommandHeader {
+            sequence: commander.untracked(),
+            command: Deploy::ID,
+        },
+        payload: postcard::to_stdvec(&Deploy(5)).unwrap(),
+    };
+    commander.seal(&frame.header, &mut frame.payload);
+
+    assert_eq!(status(device.answer(frame.to_wire())), Status::Ack);
+    assert_eq!(device.deployed, [5]);
+}
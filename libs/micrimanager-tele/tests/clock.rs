@@ -0,0 +1,170 @@
+#![cfg(feature = "std")]
+
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use micromanager_tele::{clock::ClockSync, link::Pulse, time::Timestamp};
+
+const MS: Duration = Duration::from_millis(1);
+
+/// A device that booted 1000s before the epoch, with a clock running 50ppm fast
+fn device_time(since_epoch: Duration) -> Timestamp {
+    Timestamp::from(Duration::from_secs_f64(
+        1000.0 + since_epoch.as_secs_f64() * 1.000_050,
+    ))
+}
+
+/// Exchange a heartbeat sent at `sent`, delayed `up` and `down` on the way
+fn exchange(sync: &mut ClockSync, epoch: Instant, sent: Duration, up: Duration, down: Duration) {
+    let pulse = Pulse {
+        sent: Timestamp::from(sent),
+        device: device_time(sent + up),
+    };
+
+    sync.update(&pulse, epoch + sent + up + down);
+}
+
+fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+    assert!(
+        (actual - expected).abs() <= tolerance,
+        "{} is not within {} of {}",
+        actual,
+        tolerance,
+        expected
+    );
+}
+
+#[test]
+fn estimates_offset_and_drift() {
+    let epoch = Instant::now();
+    let wall = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    let mut sync = ClockSync::new(epoch, wall);
+
+    assert_eq!(sync.estimate(), None);
+    assert_eq!(sync.to_wall(Timestamp::ZERO), None);
+
+    for second in 0..16 {
+        let sent = Duration::from_secs(second * 10);
+
+        // Every other heartbeat is stuck in a queue on the way down
+        let down = if second % 2 == 0 { MS * 20 } else { MS * 300 };
+        exchange(&mut sync, epoch, sent, MS * 20, down);
+    }
+
+    let estimate = sync.estimate().unwrap();
+    assert_close(estimate.offset, 1000.0, 1e-4);
+    assert_close(estimate.drift, 50.0, 0.5);
+    assert_eq!(estimate.uncertainty, MS * 20);
+    assert_eq!(estimate.samples, 8);
+
+    // A packet sampled 100s after the epoch, by the ground clock
+    let timestamp = device_time(Duration::from_secs(100));
+    let since_epoch = sync
+        .to_ground(timestamp)
+        .unwrap()
+        .duration_since(epoch)
+        .as_secs_f64();
+    assert_close(since_epoch, 100.0, 1e-4);
+
+    let since_wall = sync
+        .to_wall(timestamp)
+        .unwrap()
+        .duration_since(wall)
+        .unwrap()
+        .as_secs_f64();
+    assert_close(since_wall, 100.0, 1e-4);
+
+    let device = sync.to_device(epoch + Duration::from_secs(100)).unwrap();
+    assert_close(
+        device.as_micros() as f64,
+        timestamp.as_micros() as f64,
+        100.0,
+    );
+}
+
+#[test]
+fn boards_line_up() {
+    let epoch = Instant::now();
+    let wall = SystemTime::now();
+
+    let mut first = ClockSync::new(epoch, wall);
+    let mut second = ClockSync::new(epoch, wall);
+
+    first.update(
+        &Pulse {
+            sent: Timestamp::ZERO,
+            device: Timestamp::from_micros(5_000_000),
+        },
+        epoch + MS * 10,
+    );
+    second.update(
+        &Pulse {
+            sent: Timestamp::ZERO,
+            device: Timestamp::from_micros(60_000_000),
+        },
+        epoch + MS * 10,
+    );
+
+    // The same instant as seen by two boards that booted 55 seconds apart
+    let first = first.to_wall(Timestamp::from_micros(7_000_000)).unwrap();
+    let second = second.to_wall(Timestamp::from_micros(62_000_000)).unwrap();
+
+    assert_eq!(first, second);
+    assert_eq!(
+        first.duration_since(wall).unwrap(),
+        Duration::from_secs(2) + MS * 5
+    );
+}
+
+#[test]
+fn device_restarts_start_over() {
+    let epoch = Instant::now();
+    let mut sync = ClockSync::new(epoch, SystemTime::now());
+
+    for second in 0..4 {
+        exchange(&mut sync, epoch, Duration::from_secs(second * 10), MS, MS);
+    }
+    assert_eq!(sync.estimate().unwrap().samples, 4);
+
+    // The device restarted 2 seconds into the fifth heartbeat
+    sync.update(
+        &Pulse {
+            sent: Timestamp::from(Duration::from_secs(40)),
+            device: Timestamp::from_micros(1_000_000),
+        },
+        epoch + Duration::from_secs(40) + MS * 2,
+    );
+
+    let estimate = sync.estimate().unwrap();
+    assert_eq!(estimate.samples, 1);
+    assert_close(estimate.offset, 1.0 - 40.001, 1e-6);
+    assert_eq!(estimate.drift, 0.0);
+
+    // Before the restart, as far as the device clock can tell
+    assert_eq!(sync.to_device(epoch), None);
+}
+
+#[test]
+fn implausible_fits_are_discarded() {
+    let epoch = Instant::now();
+    let mut sync = ClockSync::new(epoch, SystemTime::now());
+
+    for second in 0..4 {
+        exchange(&mut sync, epoch, Duration::from_secs(second * 10), MS, MS);
+    }
+    let estimate = sync.estimate().unwrap();
+
+    // The device clock jumped an hour ahead
+    sync.update(
+        &Pulse {
+            sent: Timestamp::from(Duration::from_secs(40)),
+            device: device_time(Duration::from_secs(3640)),
+        },
+        epoch + Duration::from_secs(40) + MS * 2,
+    );
+    let kept = sync.estimate().unwrap();
+    assert_eq!((kept.offset, kept.drift), (estimate.offset, estimate.drift));
+
+    assert!(sync
+        .to_ground(device_time(Duration::from_secs(50)))
+        .is_some());
+}
@@ -0,0 +1,242 @@
+use micromanager_tele::{
+    telecommand::{
+        CommandHandler, CommandHeader, CommandId, CommandState, Dispatcher, NackReason, Resync,
+        ResyncResponse, Router, Sequence, Status, Telecommand,
+    },
+    time::Timestamp,
+};
+use serde::{Deserialize, Serialize};
+
+fn header(sequence: u16) -> CommandHeader {
+    CommandHeader {
+        sequence: Sequence::new(sequence),
+        command: CommandId::new(1),
+    }
+}
+
+#[test]
+fn duplicates_replay_cached_response() {
+    let mut dispatcher = Dispatcher::<4, 8>::new();
+    let mut executions = 0;
+
+    let mut execute = |_: CommandId, payload: &[u8], response: &mut [u8]| {
+        executions += 1;
+        response[..payload.len()].copy_from_slice(payload);
+
+        Ok(payload.len())
+    };
+
+    let reply = dispatcher.dispatch(header(0), &[1, 2, 3], Timestamp::ZERO, &mut execute);
+    assert_eq!(reply.header.status, Status::Ack);
+    assert_eq!(reply.payload, [1, 2, 3]);
+    assert!(!reply.duplicate);
+
+    let reply = dispatcher.dispatch(header(0), &[9, 9, 9], Timestamp::ZERO, &mut execute);
+    assert_eq!(reply.header.status, Status::Ack);
+    assert_eq!(reply.payload, [1, 2, 3]);
+    assert!(reply.duplicate);
+
+    assert_eq!(executions, 1);
+    assert_eq!(dispatcher.last_executed(), Some(Sequence::new(0)));
+}
+
+#[test]
+fn refusals_are_cached_except_busy() {
+    let mut dispatcher = Dispatcher::<4, 8>::new();
+
+    let reply = dispatcher.dispatch(header(0), &[], Timestamp::ZERO, |_, _, _| {
+        Err(NackReason::Busy)
+    });
+    assert_eq!(reply.header.status, Status::Nack(NackReason::Busy));
+
+    let reply = dispatcher.dispatch(header(0), &[], Timestamp::ZERO, |_, _, _| {
+        Err(NackReason::Failed(3))
+    });
+    assert_eq!(reply.header.status, Status::Nack(NackReason::Failed(3)));
+    assert!(!reply.duplicate);
+
+    let reply = dispatcher.dispatch(header(0), &[], Timestamp::ZERO, |_, _, _| Ok(0));
+    assert_eq!(reply.header.status, Status::Nack(NackReason::Failed(3)));
+    assert!(reply.duplicate);
+}
+
+#[test]
+fn commands_older_than_window_are_stale() {
+    let mut dispatcher = Dispatcher::<2, 8>::new();
+
+    // Out of order arrival within the window is fine
+    for sequence in [1, 0, 3, 2] {
+        let reply = dispatcher.dispatch(header(sequence), &[], Timestamp::ZERO, |_, _, _| Ok(0));
+        assert_eq!(reply.header.status, Status::Ack);
+    }
+
+    assert_eq!(dispatcher.state(Sequence::new(1)), CommandState::Forgotten);
+    assert_eq!(
+        dispatcher.state(Sequence::new(2)),
+        CommandState::Executed(Status::Ack)
+    );
+    assert_eq!(
+        dispatcher.state(Sequence::new(4)),
+        CommandState::NotReceived
+    );
+
+    let reply = dispatcher.dispatch(header(1), &[], Timestamp::ZERO, |_, _, _| {
+        panic!("executed twice")
+    });
+    assert_eq!(reply.header.status, Status::Nack(NackReason::Stale));
+
+    dispatcher.reset();
+    let reply = dispatcher.dispatch(header(1), &[], Timestamp::ZERO, |_, _, _| Ok(0));
+    assert_eq!(reply.header.status, Status::Ack);
+}
+
+#[test]
+fn resync_is_answered_from_window() {
+    let mut dispatcher = Dispatcher::<4, 8>::new();
+    dispatcher.dispatch(header(0), &[], Timestamp::ZERO, |_, _, _| Ok(0));
+    dispatcher.dispatch(header(1), &[], Timestamp::ZERO, |_, _, _| {
+        Err(NackReason::Malformed)
+    });
+
+    let request = Resync {
+        sequences: [0, 1, 2].into_iter().map(Sequence::new).collect(),
+    };
+    let mut buffer = [0; 64];
+    let payload = postcard::to_slice(&request, &mut buffer).unwrap();
+
+    let resync = CommandHeader {
+        sequence: Sequence::new(3),
+        command: CommandId::RESYNC,
+    };
+    let reply = dispatcher.dispatch(resync, payload, Timestamp::ZERO, |_, _, _| {
+        panic!("resync is built in")
+    });
+    assert_eq!(reply.header.status, Status::Ack);
+
+    let response: ResyncResponse = postcard::from_bytes(reply.payload).unwrap();
+    assert_eq!(
+        response,
+        ResyncResponse {
+            last_executed: Some(Sequence::new(1)),
+            commands: [
+                (Sequence::new(0), CommandState::Executed(Status::Ack)),
+                (
+                    Sequence::new(1),
+                    CommandState::Executed(Status::Nack(NackReason::Malformed))
+                ),
+                (Sequence::new(2), CommandState::NotReceived),
+            ]
+            .into_iter()
+            .collect(),
+        }
+    );
+}
+
+#[derive(Default)]
+struct Device {
+    gain: u8,
+}
+
+#[derive(Serialize, Deserialize, Telecommand)]
+#[telecommand(id = 10, response = u8)]
+struct SetGain(u8);
+
+enum GainError {
+    TooHigh = 1,
+}
+
+impl From<GainError> for NackReason {
+    fn from(error: GainError) -> Self {
+        NackReason::Failed(error as u8)
+    }
+}
+
+impl CommandHandler<SetGain> for Device {
+    type Error = GainError;
+
+    fn handle(&mut self, SetGain(gain): SetGain) -> Result<u8, GainError> {
+        if gain > 100 {
+            return Err(GainError::TooHigh);
+        }
+
+        Ok(core::mem::replace(&mut self.gain, gain))
+    }
+}
+
+#[derive(Serialize, Deserialize, Telecommand)]
+#[telecommand(id = 11, response = [u8; 32])]
+struct Dump;
+
+impl CommandHandler<Dump> for Device {
+    type Error = core::convert::Infallible;
+
+    fn handle(&mut self, Dump: Dump) -> Result<[u8; 32], Self::Error> {
+        Ok([self.gain; 32])
+    }
+}
+
+#[test]
+fn router_decodes_and_encodes() {
+    let router = Router::<Device, 2>::new()
+        .route::<SetGain>()
+        .route::<Dump>();
+    let mut device = Device::default();
+    let mut response = [0; 8];
+
+    assert_eq!(
+        router.commands().collect::<Vec<_>>(),
+        [SetGain::ID, Dump::ID]
+    );
+
+    let len = router.handle(&mut device, SetGain::ID, &[42], &mut response);
+    assert_eq!(len, Ok(1));
+    assert_eq!(response[0], 0);
+    assert_eq!(device.gain, 42);
+
+    let error = router.handle(&mut device, SetGain::ID, &[200], &mut response);
+    assert_eq!(error, Err(NackReason::Failed(GainError::TooHigh as u8)));
+
+    let error = router.handle(&mut device, SetGain::ID, &[1, 2], &mut response);
+    assert_eq!(error, Err(NackReason::Malformed));
+
+    let error = router.handle(&mut device, SetGain::ID, &[], &mut response);
+    assert_eq!(error, Err(NackReason::Malformed));
+
+    let error = router.handle(&mut device, Dump::ID, &[], &mut response);
+    assert_eq!(error, Err(NackReason::ResponseTooLarge));
+
+    let error = router.handle(&mut device, CommandId::new(12), &[], &mut response);
+    assert_eq!(error, Err(NackReason::UnknownCommand));
+
+    assert_eq!(device.gain, 42);
+}
+
+#[test]
+fn router_executes_dispatched_commands() {
+    let router = Router::<Device, 1>::new().route::<SetGain>();
+    let mut dispatcher = Dispatcher::<4, 8>::new();
+    let mut device = Device::default();
+
+    for gain in [10, 20] {
+        let header = CommandHeader {
+            sequence: Sequence::new(gain as u16),
+            command: SetGain::ID,
+        };
+
+        let reply =
+            dispatcher.dispatch(header, &[gain], Timestamp::ZERO, |id, payload, response| {
+                router.handle(&mut device, id, payload, response)
+            });
+        assert_eq!(reply.header.status, Status::Ack);
+    }
+
+    assert_eq!(device.gain, 20);
+}
+
+#[test]
+#[should_panic(expected = "duplicate route")]
+fn router_refuses_duplicate_routes() {
+    let _ = Router::<Device, 2>::new()
+        .route::<SetGain>()
+        .route::<SetGain>();
+}
@@ -0,0 +1,205 @@
+#![cfg(feature = "std")]
+
+use micromanager_tele::{
+    handshake::{self, Difference, Handshake, Schema, PROTOCOL_VERSION},
+    telecommand::{
+        CommandHeader, CommandId, Dispatcher, NackReason, Sequence, Status, Telecommand,
+    },
+    telemetry::{ChannelId, Telemetry},
+    time::Timestamp,
+};
+use serde::{Deserialize, Serialize};
+
+/// Packet definitions as built into the device firmware
+mod device {
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Telemetry)]
+    #[telemetry(channel = 1)]
+    pub struct Attitude {
+        pub roll: f32,
+        pub pitch: f32,
+    }
+
+    #[derive(Serialize, Deserialize, Telemetry)]
+    #[telemetry(channel = 2)]
+    pub struct Battery {
+        pub millivolts: u16,
+    }
+
+    #[derive(Serialize, Deserialize, Telecommand)]
+    #[telecommand(id = 1, response = ())]
+    pub struct Arm;
+
+    #[derive(Serialize, Deserialize, Telecommand)]
+    #[telecommand(id = 3, response = ())]
+    pub struct Reboot;
+}
+
+/// Packet definitions as built into a newer ground station
+mod ground {
+    use super::*;
+
+    /// Documentation does not change the schema
+    #[derive(Serialize, Deserialize, Telemetry)]
+    #[telemetry(channel = 1)]
+    pub struct Attitude {
+        /// Degrees
+        pub roll: f32,
+        pub pitch: f32,
+    }
+
+    #[derive(Serialize, Deserialize, Telemetry)]
+    #[telemetry(channel = 2)]
+    pub struct Battery {
+        pub millivolts: u16,
+        pub milliamps: i16,
+    }
+
+    #[derive(Serialize, Deserialize, Telecommand)]
+    #[telecommand(id = 1, response = ())]
+    pub struct Arm;
+
+    #[derive(Serialize, Deserialize, Telecommand)]
+    #[telecommand(id = 2, response = bool)]
+    pub struct Launch;
+}
+
+fn device_schema() -> Schema {
+    Schema::new()
+        .telemetry::<device::Attitude>()
+        .telemetry::<device::Battery>()
+        .command::<device::Arm>()
+        .command::<device::Reboot>()
+}
+
+fn ground_schema() -> Schema {
+    Schema::new()
+        .telemetry::<ground::Attitude>()
+        .telemetry::<ground::Battery>()
+        .command::<ground::Arm>()
+        .command::<ground::Launch>()
+}
+
+#[test]
+fn schema_hashes_follow_definitions() {
+    assert_eq!(device::Attitude::SCHEMA, ground::Attitude::SCHEMA);
+    assert_ne!(device::Battery::SCHEMA, ground::Battery::SCHEMA);
+    assert_eq!(device::Arm::SCHEMA, ground::Arm::SCHEMA);
+    assert_eq!(ground::Launch::NAME, "Launch");
+
+    assert_eq!(handshake::schema_hash(""), 0x811c_9dc5);
+    assert_eq!(handshake::schema_hash("a"), 0xe40c_292c);
+}
+
+#[test]
+fn dispatcher_answers_handshake_and_starts_a_session() {
+    let mut dispatcher = Dispatcher::<4, 8>::new().with_schema(device_schema());
+    let command = |sequence| CommandHeader {
+        sequence: Sequence::new(sequence),
+        command: CommandId::new(1),
+    };
+
+    dispatcher.dispatch(command(0), &[], Timestamp::ZERO, |_, _, _| Ok(0));
+    assert_eq!(dispatcher.last_executed(), Some(Sequence::new(0)));
+
+    let request = postcard::to_allocvec(&Handshake(ground_schema())).unwrap();
+    let reply = dispatcher.dispatch(
+        CommandHeader {
+            sequence: Sequence::new(0),
+            command: CommandId::HANDSHAKE,
+        },
+        &request,
+        Timestamp::ZERO,
+        |_, _, _| unreachable!("handshakes are handled by the dispatcher"),
+    );
+
+    assert_eq!(reply.header.status, Status::Ack);
+    let remote: <Handshake as Telecommand>::Response = postcard::from_bytes(reply.payload).unwrap();
+    assert_eq!(remote.version, PROTOCOL_VERSION);
+    assert_eq!(remote.telemetry.len(), 2);
+    assert_eq!(remote.commands.len(), 2);
+    assert_eq!(dispatcher.last_executed(), None);
+
+    // The sequence numbers of the new session start over
+    let reply = dispatcher.dispatch(command(0), &[], Timestamp::ZERO, |_, _, _| Ok(0));
+    assert!(!reply.duplicate);
+}
+
+#[test]
+fn malformed_handshake_is_refused() {
+    let mut dispatcher = Dispatcher::<4, 8>::new();
+
+    let reply = dispatcher.dispatch(
+        CommandHeader {
+            sequence: Sequence::new(0),
+            command: CommandId::HANDSHAKE,
+        },
+        &[0xff],
+        Timestamp::ZERO,
+        |_, _, _| unreachable!(),
+    );
+
+    assert_eq!(reply.header.status, Status::Nack(NackReason::Malformed));
+}
+
+#[test]
+fn mismatched_schemas_degrade() {
+    let local = ground_schema();
+    let encoded = postcard::to_allocvec(&device_schema()).unwrap();
+    let remote: Schema = postcard::from_bytes(&encoded).unwrap();
+
+    let agreement = handshake::negotiate(&local, &remote).unwrap();
+
+    assert!(agreement.accepts_telemetry(ChannelId::new(1)));
+    assert!(!agreement.accepts_telemetry(ChannelId::new(2)));
+    assert!(agreement.accepts_command(CommandId::new(1)));
+    assert!(!agreement.accepts_command(CommandId::new(2)));
+    assert!(!agreement.accepts_command(CommandId::new(3)));
+
+    let report = agreement.report();
+    assert!(!report.is_identical());
+    assert_eq!(
+        report.telemetry,
+        [Difference::Changed {
+            id: ChannelId::new(2),
+            name: "Battery"
+        }]
+    );
+    assert_eq!(
+        report.commands,
+        [
+            Difference::LocalOnly {
+                id: CommandId::new(2),
+                name: "Launch"
+            },
+            Difference::RemoteOnly {
+                id: CommandId::new(3)
+            },
+        ]
+    );
+    assert_eq!(
+        report.to_string(),
+        "protocol version 1, telemetry Battery (#2) differs, \
+         telecommand Launch (cmd#2) unknown to remote, telecommand cmd#3 unknown to local"
+    );
+}
+
+#[test]
+fn identical_schemas_agree() {
+    let agreement = handshake::negotiate(&device_schema(), &device_schema()).unwrap();
+
+    assert!(agreement.report().is_identical());
+}
+
+#[test]
+fn version_mismatch_is_refused() {
+    let mut remote = device_schema();
+    remote.version = PROTOCOL_VERSION + 1;
+
+    let report = handshake::negotiate(&device_schema(), &remote).unwrap_err();
+
+    assert!(!report.is_identical());
+    assert!(report.telemetry.is_empty());
+    assert!(report.commands.is_empty());
+}
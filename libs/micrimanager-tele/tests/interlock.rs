@@ -0,0 +1,367 @@
+#![cfg(feature = "std")]
+
+use std::time::Duration;
+
+use micromanager_tele::{
+    telecommand::{
+        Arm, ArmError, Armed, CommandHandler, CommandHeader, CommandId, Disarm, Dispatcher, Guard,
+        Interlock, Interlocked, Interlocks, InvalidMode, Mode, ModeReport, Modes, NackReason,
+        Router, Sequence, Status, Telecommand,
+    },
+    time::Timestamp,
+};
+use serde::{Deserialize, Serialize};
+
+const SAFE: Mode = Mode::new(0);
+const FLIGHT: Mode = Mode::new(1);
+const MAINTENANCE: Mode = Mode::new(2);
+
+#[derive(Serialize, Deserialize, Telecommand)]
+#[telecommand(id = 1, response = ())]
+struct WriteCalibration(i16);
+
+impl Interlocked for WriteCalibration {
+    const INTERLOCK: Interlock = Interlock::new(Modes::of(&[SAFE, MAINTENANCE])).armed();
+}
+
+#[derive(Serialize, Deserialize, Telecommand)]
+#[telecommand(id = 2, response = ())]
+struct FireThruster;
+
+impl Interlocked for FireThruster {
+    const INTERLOCK: Interlock = Interlock::new(Modes::of(&[FLIGHT]));
+}
+
+#[derive(Serialize, Deserialize, Telecommand)]
+#[telecommand(id = 3, response = ())]
+struct Ping;
+
+#[derive(Default)]
+struct Spacecraft {
+    calibration: Option<i16>,
+    burns: u32,
+}
+
+impl CommandHandler<WriteCalibration> for Spacecraft {
+    type Error = core::convert::Infallible;
+
+    fn handle(&mut self, WriteCalibration(offset): WriteCalibration) -> Result<(), Self::Error> {
+        self.calibration = Some(offset);
+        Ok(())
+    }
+}
+
+impl CommandHandler<FireThruster> for Spacecraft {
+    type Error = core::convert::Infallible;
+
+    fn handle(&mut self, FireThruster: FireThruster) -> Result<(), Self::Error> {
+        self.burns += 1;
+        Ok(())
+    }
+}
+
+impl CommandHandler<Ping> for Spacecraft {
+    type Error = core::convert::Infallible;
+
+    fn handle(&mut self, Ping: Ping) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+struct Device {
+    dispatcher: Dispatcher<8, 16>,
+    interlocks: Interlocks<2>,
+    spacecraft: Spacecraft,
+    routes: Router<Spacecraft, 3>,
+    next: u16,
+}
+
+impl Device {
+    fn new(mode: Mode) -> Self {
+        Self {
+            dispatcher: Dispatcher::new(),
+            interlocks: Interlocks::new(mode)
+                .window(Duration::from_secs(5))
+                .command::<WriteCalibration>()
+                .command::<FireThruster>(),
+            spacecraft: Spacecraft::default(),
+            routes: Router::new()
+                .route::<WriteCalibration>()
+                .route::<FireThruster>()
+                .route::<Ping>(),
+            next: 0,
+        }
+    }
+
+    /// Execute a command received at `now`, returning its status and response
+    fn send<C: Telecommand>(&mut self, command: &C, now: Timestamp) -> (Status, Vec<u8>) {
+        let header = CommandHeader {
+            sequence: Sequence::new(self.next),
+            command: C::ID,
+        };
+        self.next += 1;
+        let payload = postcard::to_allocvec(command).unwrap();
+
+        let (interlocks, routes, spacecraft) =
+            (&mut self.interlocks, &self.routes, &mut self.spacecraft);
+        let reply = self
+            .dispatcher
+            .dispatch(header, &payload, now, |id, payload, response| {
+                interlocks.check(id, now)?;
+
+                match Interlocks::router().handle(interlocks, id, payload, response) {
+                    Err(NackReason::UnknownCommand) => {
+                        routes.handle(spacecraft, id, payload, response)
+                    }
+                    result => result,
+                }
+            });
+
+        (reply.header.status, reply.payload.to_vec())
+    }
+
+    fn status<C: Telecommand>(&mut self, command: &C, now: Timestamp) -> Status {
+        self.send(command, now).0
+    }
+}
+
+fn at(seconds: u64) -> Timestamp {
+    Timestamp::from_micros(seconds * 1_000_000)
+}
+
+#[test]
+fn commands_run_only_in_their_modes() {
+    let mut device = Device::new(SAFE);
+
+    assert_eq!(
+        device.status(&FireThruster, at(0)),
+        Status::Nack(NackReason::WrongMode)
+    );
+    assert_eq!(device.status(&Ping, at(0)), Status::Ack);
+
+    device.interlocks.set_mode(FLIGHT);
+    assert_eq!(device.status(&FireThruster, at(1)), Status::Ack);
+    assert_eq!(device.status(&FireThruster, at(1)), Status::Ack);
+    assert_eq!(device.spacecraft.burns, 2);
+
+    // Calibration is refused in flight, armed or not
+    assert_eq!(
+        device.status(&WriteCalibration(3), at(2)),
+        Status::Nack(NackReason::WrongMode)
+    );
+    let arm = Arm {
+        command: WriteCalibration::ID,
+    };
+    assert_eq!(
+        device.status(&arm, at(2)),
+        Status::Nack(NackReason::WrongMode)
+    );
+    assert_eq!(device.spacecraft.calibration, None);
+}
+
+#[test]
+fn armed_commands_run_once() {
+    let mut device = Device::new(MAINTENANCE);
+    let arm = Arm {
+        command: WriteCalibration::ID,
+    };
+
+    assert_eq!(
+        device.status(&WriteCalibration(3), at(0)),
+        Status::Nack(NackReason::NotArmed)
+    );
+
+    let (status, response) = device.send(&arm, at(0));
+    assert_eq!(status, Status::Ack);
+    let report: ModeReport = postcard::from_bytes(&response).unwrap();
+    assert_eq!(report.mode, MAINTENANCE);
+    assert_eq!(report.armed.unwrap().expires, at(5));
+
+    // Other commands do not use up the arming
+    assert_eq!(device.status(&Ping, at(1)), Status::Ack);
+    assert_eq!(device.status(&WriteCalibration(3), at(2)), Status::Ack);
+    assert_eq!(device.spacecraft.calibration, Some(3));
+    assert_eq!(device.interlocks.armed(), None);
+
+    assert_eq!(
+        device.status(&WriteCalibration(4), at(2)),
+        Status::Nack(NackReason::NotArmed)
+    );
+    assert_eq!(device.spacecraft.calibration, Some(3));
+}
+
+#[test]
+fn armings_are_withdrawn() {
+    let mut device = Device::new(SAFE);
+    let arm = Arm {
+        command: WriteCalibration::ID,
+    };
+
+    // By expiring
+    device.send(&arm, at(0));
+    assert_eq!(
+        device.status(&WriteCalibration(1), at(6)),
+        Status::Nack(NackReason::NotArmed)
+    );
+
+    // By changing mode, even to one the command is allowed in
+    device.send(&arm, at(10));
+    device.interlocks.set_mode(MAINTENANCE);
+    assert_eq!(
+        device.status(&WriteCalibration(1), at(10)),
+        Status::Nack(NackReason::NotArmed)
+    );
+
+    // On request
+    device.send(&arm, at(20));
+    let (status, response) = device.send(&Disarm, at(20));
+    assert_eq!(status, Status::Ack);
+    let report: ModeReport = postcard::from_bytes(&response).unwrap();
+    assert_eq!(report.armed, None);
+    assert_eq!(
+        device.status(&WriteCalibration(1), at(20)),
+        Status::Nack(NackReason::NotArmed)
+    );
+
+    // Commands without an arming requirement can not be armed
+    let Status::Nack(reason) = device.status(
+        &Arm {
+            command: FireThruster::ID,
+        },
+        at(30),
+    ) else {
+        panic!("arming a command without arming requirement was accepted");
+    };
+    assert_eq!(ArmError::from_reason(reason), Some(ArmError::NotArmable));
+    assert_eq!(
+        device.status(
+            &Arm {
+                command: CommandId::new(99)
+            },
+            at(30)
+        ),
+        Status::Nack(ArmError::NotArmable.into())
+    );
+}
+
+#[test]
+fn armings_expire_with_device_time() {
+    let mut device = Device::new(SAFE);
+    let arm = Arm {
+        command: WriteCalibration::ID,
+    };
+
+    device.send(&arm, at(0));
+    device.interlocks.next_report();
+
+    // Reported as soon as it expires, without waiting for a command
+    device.interlocks.tick(at(6));
+    assert_eq!(device.interlocks.armed(), None);
+    assert_eq!(device.interlocks.next_report().unwrap().armed, None);
+
+    // Armed from the time of the latest tick
+    device.interlocks.tick(at(10));
+    let mut response = [0; 32];
+    let len = Interlocks::router()
+        .handle(
+            &mut device.interlocks,
+            Arm::ID,
+            &postcard::to_allocvec(&arm).unwrap(),
+            &mut response,
+        )
+        .unwrap();
+    let report: ModeReport = postcard::from_bytes(&response[..len]).unwrap();
+    assert_eq!(report.armed.unwrap().expires, at(15));
+}
+
+#[test]
+fn reports_follow_mode_and_arming() {
+    let mut device = Device::new(SAFE);
+
+    // The initial mode is reported
+    assert_eq!(
+        device.interlocks.next_report(),
+        Some(ModeReport {
+            mode: SAFE,
+            armed: None
+        })
+    );
+    assert_eq!(device.interlocks.next_report(), None);
+
+    device.interlocks.set_mode(SAFE);
+    assert_eq!(device.interlocks.next_report(), None);
+
+    device.send(
+        &Arm {
+            command: WriteCalibration::ID,
+        },
+        at(0),
+    );
+    assert!(device.interlocks.next_report().unwrap().armed.is_some());
+
+    device.status(&WriteCalibration(1), at(1));
+    assert_eq!(device.interlocks.next_report().unwrap().armed, None);
+
+    device.interlocks.set_mode(FLIGHT);
+    assert_eq!(device.interlocks.next_report().unwrap().mode, FLIGHT);
+}
+
+#[test]
+fn ground_pre_checks_commands() {
+    let mut device = Device::new(SAFE);
+    let mut guard = Guard::new()
+        .command::<WriteCalibration>()
+        .command::<FireThruster>();
+
+    // Nothing is known about the device yet, it will decide
+    assert_eq!(guard.check::<FireThruster>(), Ok(()));
+    assert_eq!(guard.mode(), None);
+
+    guard.update(&device.interlocks.next_report().unwrap());
+    assert_eq!(guard.mode(), Some(SAFE));
+    assert_eq!(guard.check::<FireThruster>(), Err(NackReason::WrongMode));
+    assert_eq!(guard.check::<Ping>(), Ok(()));
+    assert_eq!(guard.check::<WriteCalibration>(), Err(NackReason::NotArmed));
+    assert_eq!(
+        guard.arm::<FireThruster>(),
+        Err(ArmError::NotArmable.into())
+    );
+
+    let arm = guard.arm::<WriteCalibration>().unwrap();
+    let (status, response) = device.send(&arm, at(0));
+    assert_eq!(status, Status::Ack);
+    guard.update(&postcard::from_bytes(&response).unwrap());
+
+    assert_eq!(guard.armed().unwrap().command, WriteCalibration::ID);
+    assert_eq!(guard.check::<WriteCalibration>(), Ok(()));
+    assert_eq!(device.status(&WriteCalibration(7), at(1)), Status::Ack);
+
+    guard.update(&device.interlocks.next_report().unwrap());
+    assert_eq!(guard.check::<WriteCalibration>(), Err(NackReason::NotArmed));
+
+    device.interlocks.set_mode(FLIGHT);
+    guard.update(&device.interlocks.next_report().unwrap());
+    assert_eq!(guard.arm::<WriteCalibration>(), Err(NackReason::WrongMode));
+}
+
+#[test]
+fn reports_of_unknown_modes_are_refused() {
+    let mut guard = Guard::new().command::<FireThruster>();
+    let report = |mode: u8| postcard::to_allocvec(&(mode, None::<Armed>)).unwrap();
+
+    assert!(postcard::from_bytes::<ModeReport>(&report(40)).is_err());
+    assert_eq!(Mode::try_from(40), Err(InvalidMode(40)));
+    assert_eq!(guard.check::<FireThruster>(), Ok(()));
+
+    guard.update(&postcard::from_bytes(&report(1)).unwrap());
+    assert_eq!(guard.mode(), Some(FLIGHT));
+    assert_eq!(guard.check::<FireThruster>(), Ok(()));
+}
+
+#[test]
+#[should_panic(expected = "duplicate interlock for cmd#1")]
+fn interlocks_are_declared_once() {
+    let _ = Interlocks::<2>::new(SAFE)
+        .command::<WriteCalibration>()
+        .command::<WriteCalibration>();
+}
@@ -0,0 +1,178 @@
+#![cfg(feature = "std")]
+
+use std::time::{Duration, Instant};
+
+use micromanager_tele::{
+    link::{LinkState, Monitor},
+    telecommand::{Commander, Dispatcher, RetryPolicy},
+    telemetry::ChannelId,
+    telemetry::TelemetryHeader,
+    time::Timestamp,
+    wire::{self, Header},
+};
+
+const MS: Duration = Duration::from_millis(1);
+
+/// Answer a frame sent by the ground the way a device would
+fn answer(dispatcher: &mut Dispatcher<4, 8>, mut frame: Vec<u8>, now: Timestamp) -> Vec<u8> {
+    let frame = wire::decode(&mut frame).unwrap();
+    let header = match frame.header {
+        Header::Command(header) => header,
+        header => panic!("expected a command, got {:?}", header),
+    };
+
+    let reply = dispatcher.dispatch(header, frame.payload, now, |_, _, _| {
+        panic!("heartbeats are built in")
+    });
+
+    wire::to_vec_raw(&Header::Response(reply.header), reply.payload)
+}
+
+fn telemetry(channel: u16, sequence: u16) -> Vec<u8> {
+    sampled(channel, sequence, Timestamp::ZERO)
+}
+
+fn sampled(channel: u16, sequence: u16, timestamp: Timestamp) -> Vec<u8> {
+    let header = Header::Telemetry(TelemetryHeader {
+        channel: ChannelId::new(channel),
+        sequence,
+        timestamp,
+    });
+
+    wire::to_vec(&header, &()).unwrap()
+}
+
+fn observe(monitor: &mut Monitor, mut frame: Vec<u8>, now: Instant) {
+    monitor.observe(&wire::decode(&mut frame), now);
+}
+
+#[test]
+fn heartbeats_measure_round_trip_time() {
+    let start = Instant::now();
+    let mut commander = Commander::new(RetryPolicy::default());
+    let mut dispatcher = Dispatcher::<4, 8>::new();
+    let mut monitor = Monitor::new(start).interval(MS * 100);
+
+    assert_eq!(monitor.quality(start).state, LinkState::Unknown);
+
+    let heartbeat = monitor.poll(&mut commander, start).unwrap().unwrap();
+    assert_eq!(monitor.poll(&mut commander, start + MS * 50).unwrap(), None);
+
+    let device_time = Timestamp::from_micros(123_456);
+    let mut response = answer(&mut dispatcher, heartbeat, device_time);
+    let pulse = monitor
+        .observe(&wire::decode(&mut response), start + MS * 40)
+        .unwrap();
+
+    assert_eq!(pulse.sent, Timestamp::ZERO);
+    assert_eq!(pulse.device, device_time);
+    assert_eq!(dispatcher.last_executed(), None);
+    assert!(commander.is_idle());
+
+    let quality = monitor.quality(start + MS * 40);
+    assert_eq!(quality.rtt, Some(MS * 40));
+    assert_eq!(quality.state, LinkState::Quiet);
+    assert_eq!(quality.heartbeat_loss, 0.0);
+
+    let heartbeat = monitor
+        .poll(&mut commander, start + MS * 100)
+        .unwrap()
+        .unwrap();
+    let response = answer(&mut dispatcher, heartbeat, device_time);
+    observe(&mut monitor, response, start + MS * 180);
+    observe(&mut monitor, telemetry(1, 0), start + MS * 180);
+
+    let quality = monitor.quality(start + MS * 180);
+    assert_eq!(quality.last_rtt, Some(MS * 80));
+    assert_eq!(quality.rtt, Some(MS * 45));
+    assert_eq!(quality.state, LinkState::Up);
+    assert_eq!(quality.frames, 3);
+}
+
+#[test]
+fn unanswered_heartbeats_take_the_link_down() {
+    let start = Instant::now();
+    let mut commander = Commander::new(RetryPolicy::default());
+    let mut monitor = Monitor::new(start).interval(MS * 100).timeout(MS * 250);
+
+    observe(&mut monitor, telemetry(1, 0), start);
+
+    for n in 0..6 {
+        monitor
+            .poll(&mut commander, start + MS * 100 * n)
+            .unwrap()
+            .unwrap();
+    }
+
+    let quality = monitor.quality(start + MS * 500);
+    assert_eq!(quality.state, LinkState::Down);
+    assert_eq!(quality.heartbeat_loss, 1.0);
+    assert_eq!(quality.silence, Some(MS * 500));
+}
+
+#[test]
+fn losses_and_corruption_are_counted() {
+    let start = Instant::now();
+    let mut monitor = Monitor::new(start);
+
+    for sequence in [0, 1, 4, 3, 5] {
+        observe(&mut monitor, telemetry(1, sequence), start);
+    }
+    for sequence in [10, 11, 12] {
+        observe(&mut monitor, telemetry(2, sequence), start);
+    }
+
+    let mut corrupt = telemetry(1, 6);
+    corrupt[4] ^= 0x01;
+    observe(&mut monitor, corrupt, start);
+    observe(&mut monitor, vec![0x02, 0x07, 0x00], start);
+
+    let quality = monitor.quality(start);
+    assert_eq!(quality.frames, 8);
+    assert_eq!(quality.telemetry_loss, 2.0 / 10.0);
+    assert_eq!(quality.checksum_failures, 1);
+    assert_eq!(quality.malformed, 1);
+    assert_eq!(quality.state, LinkState::Up);
+    assert_eq!(
+        quality.to_string(),
+        "link up, 20.0% telemetry lost, 0.0% heartbeats lost, 2 of 10 frames corrupt"
+    );
+}
+
+#[test]
+fn device_restarts_keep_counting_losses() {
+    let start = Instant::now();
+    let mut monitor = Monitor::new(start);
+
+    for sequence in [5000, 5001, 0, 1, 3] {
+        observe(&mut monitor, telemetry(1, sequence), start);
+    }
+
+    // Only the packet missing after the restart is lost
+    assert_eq!(monitor.quality(start).telemetry_loss, 1.0 / 6.0);
+}
+
+#[test]
+fn device_restarts_are_told_by_device_time() {
+    let start = Instant::now();
+    let uptime = Timestamp::from_micros(3_600_000_000);
+
+    // Restarted early, and half way through the sequence numbers
+    for before in [vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9], vec![40_000, 40_001]] {
+        let mut monitor = Monitor::new(start);
+
+        for &sequence in &before {
+            observe(&mut monitor, sampled(1, sequence, uptime), start);
+        }
+        for sequence in [0, 1, 3] {
+            let timestamp = Timestamp::from_micros(sequence as u64 * 100_000);
+            observe(&mut monitor, sampled(1, sequence, timestamp), start);
+        }
+
+        let received = before.len() + 3;
+        assert_eq!(
+            monitor.quality(start).telemetry_loss,
+            1.0 / (received + 1) as f64
+        );
+    }
+}
@@ -0,0 +1,141 @@
+#![cfg(feature = "std")]
+
+use std::{
+    collections::BTreeMap,
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use micromanager_tele::{
+    log::{self, Bridge, FieldValue, Level, LogRecord, MAX_FIELDS, MAX_MESSAGE_LEN},
+    mux::{Demultiplexer, Multiplexer},
+    telemetry::Packet,
+    time::Timestamp,
+};
+use tracing::{
+    field::{Field, Visit},
+    span, Event, Metadata, Subscriber,
+};
+
+#[test]
+fn records_are_built_from_format_arguments() {
+    let record = LogRecord::new(Level::Warn, "imu", format_args!("sampling at {} Hz", 400))
+        .with("overruns", 3u32)
+        .with("axis", "z")
+        .with("ok", false);
+
+    assert_eq!(record.message, "sampling at 400 Hz");
+    assert_eq!(record.target, "imu");
+    assert_eq!(record.fields.len(), 3);
+    assert_eq!(record.fields[0].1, FieldValue::U64(3));
+    assert_eq!(record.fields[1].1.to_string(), "\"z\"");
+    assert!(!record.truncated);
+}
+
+#[test]
+fn oversized_records_are_truncated() {
+    let long = "é".repeat(MAX_MESSAGE_LEN);
+    let record = LogRecord::new(Level::Info, "imu", format_args!("{}", long));
+
+    assert!(record.truncated);
+    assert_eq!(record.message.len(), MAX_MESSAGE_LEN);
+    assert!(record.message.chars().all(|c| c == 'é'));
+
+    // Nothing is written after the cut, even what would still fit
+    let record = LogRecord::new(
+        Level::Info,
+        "imu",
+        format_args!("{}{}{}", "a".repeat(MAX_MESSAGE_LEN - 2), "bcd", "e"),
+    );
+    assert!(record.truncated);
+    assert_eq!(
+        record.message.as_str(),
+        format!("{}bc", "a".repeat(MAX_MESSAGE_LEN - 2))
+    );
+
+    let mut record = LogRecord::new(Level::Info, "imu", format_args!("fields"));
+    for n in 0..=MAX_FIELDS {
+        record = record.with("n", n as u32);
+    }
+
+    assert!(record.truncated);
+    assert_eq!(record.fields.len(), MAX_FIELDS);
+}
+
+/// Level, target and formatted fields of an event
+type Emitted = (tracing::Level, String, BTreeMap<String, String>);
+
+/// The events emitted to it
+#[derive(Default, Clone)]
+struct Collector(Arc<Mutex<Vec<Emitted>>>);
+
+struct Fields<'a>(&'a mut BTreeMap<String, String>);
+
+impl Visit for Fields<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{:?}", value));
+    }
+}
+
+impl Subscriber for Collector {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, _: &span::Attributes<'_>) -> span::Id {
+        span::Id::from_u64(1)
+    }
+
+    fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+
+    fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut fields = BTreeMap::new();
+        event.record(&mut Fields(&mut fields));
+
+        let metadata = event.metadata();
+        self.0
+            .lock()
+            .unwrap()
+            .push((*metadata.level(), metadata.target().to_string(), fields));
+    }
+
+    fn enter(&self, _: &span::Id) {}
+
+    fn exit(&self, _: &span::Id) {}
+}
+
+#[test]
+fn bridge_emits_tracing_events() {
+    let collector = Collector::default();
+
+    let mut multiplexer = Multiplexer::<256, 1>::new().channel::<LogRecord>();
+    let mut demultiplexer = Demultiplexer::new();
+    let bridge = Bridge::new("imu-board");
+    demultiplexer.subscribe::<LogRecord>(move |packet| bridge.emit(&packet));
+
+    let record = LogRecord::new(Level::Error, "imu::gyro", format_args!("saturated"))
+        .with("axis", "x")
+        .with("rate", -2000i32);
+    let packet = Packet::new(Timestamp::from_micros(1_500), record);
+    let mut frame = multiplexer.telemetry(&packet).unwrap().to_vec();
+
+    tracing::subscriber::with_default(collector.clone(), || {
+        assert_eq!(demultiplexer.receive(&mut frame), Ok(None));
+    });
+
+    let events = collector.0.lock().unwrap();
+    assert_eq!(events.len(), 1);
+
+    let (level, target, fields) = &events[0];
+    assert_eq!(*level, tracing::Level::ERROR);
+    assert_eq!(target, log::TARGET);
+    assert_eq!(fields["message"], "saturated");
+    assert_eq!(fields["device"], "imu-board");
+    assert_eq!(fields["device.target"], "imu::gyro");
+    assert_eq!(fields["device.time"], "1500");
+    assert_eq!(fields["fields"], "axis=\"x\" rate=-2000");
+    assert_eq!(fields["truncated"], "false");
+}
@@ -0,0 +1,233 @@
+#![cfg(feature = "std")]
+
+use std::time::Duration;
+
+use micromanager_tele::{
+    telecommand::{Commander, Dispatcher, Event, Outcome, RetryPolicy, Telecommand},
+    telemetry::{Packet, Telemetry},
+    time::Timestamp,
+    transport::{loopback, Lossy, LossyConfig, Transport},
+    wire::{self, Encoder, Header},
+};
+use serde::{Deserialize, Serialize};
+
+fn bad_link(seed: u64) -> LossyConfig {
+    LossyConfig {
+        seed,
+        drop: 0.2,
+        duplicate: 0.1,
+        reorder: 0.1,
+        delay: 0.1,
+        max_delay: 5,
+        corrupt: 0.1,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Telemetry)]
+#[telemetry(channel = 3)]
+struct Battery {
+    millivolts: u16,
+    milliamps: i16,
+}
+
+#[derive(Debug, Serialize, Deserialize, Telecommand)]
+#[telecommand(id = 7, response = u16)]
+struct Toggle(u16);
+
+#[test]
+fn impairments_are_deterministic() {
+    let run = |seed| {
+        let (device, mut ground) = loopback::pair();
+        let mut device = Lossy::new(device, bad_link(seed));
+
+        for index in 0..200u8 {
+            device.send(&[index, 0x00]).unwrap();
+        }
+
+        let mut received = Vec::new();
+        for _ in 0..20 {
+            while let Some(frame) = ground.recv().unwrap() {
+                received.push(frame);
+            }
+            device.recv().unwrap();
+        }
+
+        (received, device.stats())
+    };
+
+    assert_eq!(run(1), run(1));
+    assert_ne!(run(1), run(2));
+}
+
+#[test]
+fn reordered_frames_are_released_on_a_quiet_link() {
+    let (device, mut ground) = loopback::pair();
+    let mut device = Lossy::new(
+        device,
+        LossyConfig {
+            reorder: 1.0,
+            max_delay: 3,
+            ..LossyConfig::default()
+        },
+    );
+
+    device.send(&[1, 0x00]).unwrap();
+    assert_eq!(device.stats().reordered, 1);
+
+    for _ in 0..2 {
+        device.recv().unwrap();
+        assert_eq!(ground.recv().unwrap(), None);
+    }
+
+    device.recv().unwrap();
+    assert_eq!(ground.recv().unwrap(), Some(vec![1, 0x00]));
+    assert_eq!(ground.recv().unwrap(), None);
+}
+
+#[test]
+#[should_panic(expected = "drop probability 1.5 is not in the range 0.0..=1.0")]
+fn probabilities_are_checked_up_front() {
+    let (device, _ground) = loopback::pair();
+    let _ = Lossy::new(
+        device,
+        LossyConfig {
+            drop: 1.5,
+            ..LossyConfig::default()
+        },
+    );
+}
+
+#[test]
+fn telemetry_survives_bad_link() {
+    let (device, mut ground) = loopback::pair();
+    let mut device = Lossy::new(device, bad_link(42));
+    let mut encoder = Encoder::<64>::new();
+
+    let sent: Vec<_> = (0..1000u16)
+        .map(|sequence| {
+            let packet = Packet::new(
+                Timestamp::from_micros(sequence as u64 * 1000),
+                Battery {
+                    millivolts: 3700 + sequence,
+                    milliamps: -(sequence as i16),
+                },
+            );
+
+            device
+                .send(encoder.telemetry(sequence, &packet).unwrap())
+                .unwrap();
+
+            packet
+        })
+        .collect();
+
+    let mut received = Vec::new();
+    let mut rejected = 0;
+    for _ in 0..10 {
+        device.recv().unwrap();
+
+        while let Some(mut frame) = ground.recv().unwrap() {
+            let frame = match wire::decode(&mut frame) {
+                Ok(frame) => frame,
+                Err(_) => {
+                    rejected += 1;
+                    continue;
+                }
+            };
+
+            let header = match frame.header {
+                Header::Telemetry(header) => header,
+                header => panic!("expected telemetry, got {:?}", header),
+            };
+            assert_eq!(header.channel, Battery::CHANNEL);
+
+            let packet = Packet::new(header.timestamp, frame.decode_payload().unwrap());
+            assert_eq!(packet, sent[header.sequence as usize], "decoded garbage");
+
+            received.push(header.sequence);
+        }
+    }
+
+    let stats = device.stats();
+    assert!(stats.dropped > 0 && stats.corrupted > 0 && stats.reordered > 0);
+    assert!(rejected >= stats.corrupted);
+    assert!(received.len() + rejected <= 1000 - stats.dropped + stats.duplicated);
+    assert!(received.windows(2).any(|pair| pair[0] > pair[1]));
+}
+
+#[test]
+fn telecommands_survive_bad_link() {
+    let (ground, mut device) = loopback::pair();
+    let mut ground = Lossy::new(ground, bad_link(7));
+    let mut commander = Commander::new(RetryPolicy {
+        timeout: Duration::from_millis(50),
+        retries: 10,
+    });
+
+    let mut dispatcher = Dispatcher::<64, 8>::new();
+    let mut executions = [0; 50];
+
+    let mut now = Timestamp::ZERO;
+    let mut outcomes = Vec::new();
+
+    for argument in 0..50 {
+        let frame = commander.issue_command(&Toggle(argument), now).unwrap();
+        ground.send(&frame).unwrap();
+    }
+
+    while !commander.is_idle() {
+        now = now + Duration::from_millis(10);
+
+        while let Some(mut frame) = device.recv().unwrap() {
+            let frame = match wire::decode(&mut frame) {
+                Ok(frame) => frame,
+                Err(_) => continue,
+            };
+
+            if let Header::Command(header) = frame.header {
+                let reply =
+                    dispatcher.dispatch(header, frame.payload, now, |_, payload, response| {
+                        let Toggle(argument) = postcard::from_bytes(payload).unwrap();
+                        executions[argument as usize] += 1;
+
+                        Ok(postcard::to_slice(&!argument, response).unwrap().len())
+                    });
+
+                device
+                    .send(&wire::to_vec_raw(
+                        &Header::Response(reply.header),
+                        reply.payload,
+                    ))
+                    .unwrap();
+            }
+        }
+
+        while let Some(mut frame) = ground.recv().unwrap() {
+            let frame = match wire::decode(&mut frame) {
+                Ok(frame) => frame,
+                Err(_) => continue,
+            };
+
+            if let Header::Response(header) = frame.header {
+                if let Some(outcome) = commander.receive(&header) {
+                    outcomes.push(outcome);
+                }
+            }
+        }
+
+        while let Some(event) = commander.poll(now) {
+            match event {
+                Event::Retransmit(frame) => ground.send(&frame.to_wire()).unwrap(),
+                Event::Resolved(outcome) => outcomes.push(outcome),
+                Event::Resynchronised(_) => unreachable!("no resync was requested"),
+            }
+        }
+    }
+
+    assert_eq!(outcomes.len(), 50);
+    assert!(outcomes
+        .iter()
+        .all(|outcome| matches!(outcome, Outcome::Acknowledged(_))));
+    assert!(ground.stats().dropped > 0 && ground.stats().duplicated > 0);
+    assert_eq!(executions, [1; 50], "commands executed more than once");
+}
@@ -0,0 +1,220 @@
+#![cfg(feature = "std")]
+
+use std::sync::{Arc, Mutex};
+
+use micromanager_tele::{
+    mux::{ChannelStats, Demultiplexer, Error, Multiplexer},
+    telecommand::{ResponseHeader, Sequence, Status},
+    telemetry::{ChannelId, Packet, Telemetry},
+    time::Timestamp,
+    wire::{self, Header},
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Telemetry)]
+#[telemetry(channel = 1)]
+struct Imu {
+    gyro: [i16; 3],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Telemetry)]
+#[telemetry(channel = 2)]
+struct Temperature(f32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Telemetry)]
+#[telemetry(channel = 3)]
+struct Unopened;
+
+/// A newer definition of [`Imu`] the device does not know about yet
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Telemetry)]
+#[telemetry(channel = 1)]
+struct ImuV2 {
+    gyro: [i16; 3],
+    accel: [i16; 3],
+}
+
+fn imu(n: i16) -> Packet<Imu> {
+    Packet::new(Timestamp::from_micros(n as u64), Imu { gyro: [n; 3] })
+}
+
+fn temperature(n: i16) -> Packet<Temperature> {
+    Packet::new(Timestamp::from_micros(n as u64), Temperature(n as f32))
+}
+
+#[test]
+fn channels_have_independent_sequences() {
+    let mut mux = Multiplexer::<64, 2>::new()
+        .channel::<Imu>()
+        .channel::<Temperature>();
+    let mut demux = Demultiplexer::new();
+
+    let imus = demux.stream::<Imu>();
+    let temperatures = Arc::new(Mutex::new(Vec::new()));
+    demux.subscribe::<Temperature>({
+        let temperatures = temperatures.clone();
+        move |packet| temperatures.lock().unwrap().push(packet)
+    });
+
+    let raw = Arc::new(Mutex::new(Vec::new()));
+    demux.subscribe_raw(Temperature::CHANNEL, {
+        let raw = raw.clone();
+        move |header, _| raw.lock().unwrap().push(header.sequence)
+    });
+
+    for n in 0..10 {
+        let mut frame = mux.telemetry(&imu(n)).unwrap().to_vec();
+
+        // Every other IMU packet is lost, without affecting temperatures
+        if n % 2 == 0 {
+            assert_eq!(demux.receive(&mut frame).unwrap(), None);
+        }
+
+        let mut frame = mux.telemetry(&temperature(n)).unwrap().to_vec();
+        assert_eq!(demux.receive(&mut frame).unwrap(), None);
+    }
+
+    assert_eq!(
+        imus.try_iter().collect::<Vec<_>>(),
+        [imu(0), imu(2), imu(4), imu(6), imu(8)]
+    );
+    assert_eq!(temperatures.lock().unwrap().len(), 10);
+    assert_eq!(*raw.lock().unwrap(), (0..10).collect::<Vec<_>>());
+
+    assert_eq!(
+        demux.stats(Imu::CHANNEL),
+        ChannelStats {
+            received: 5,
+            lost: 4,
+            ..ChannelStats::default()
+        }
+    );
+    assert_eq!(demux.stats(Temperature::CHANNEL).lost, 0);
+}
+
+#[test]
+fn unopened_channels_are_refused() {
+    let mut mux = Multiplexer::<64, 1>::new().channel::<Imu>();
+
+    assert_eq!(
+        mux.telemetry(&Packet::new(Timestamp::ZERO, Unopened)),
+        Err(Error::UnknownChannel(Unopened::CHANNEL))
+    );
+    assert_eq!(mux.channels().collect::<Vec<_>>(), [Imu::CHANNEL]);
+}
+
+#[test]
+#[should_panic]
+fn channels_open_once() {
+    let _ = Multiplexer::<64, 2>::new()
+        .channel::<Imu>()
+        .channel::<Imu>();
+}
+
+#[test]
+fn late_and_malformed_packets_are_counted() {
+    let mut mux = Multiplexer::<64, 1>::new().channel::<Imu>();
+    let mut demux = Demultiplexer::new();
+
+    let _imus = demux.stream::<ImuV2>();
+    let frames: Vec<_> = (0..3)
+        .map(|n| mux.telemetry(&imu(n)).unwrap().to_vec())
+        .collect();
+
+    for index in [0, 2, 1] {
+        demux.receive(&mut frames[index].clone()).unwrap();
+    }
+
+    let stats = demux.stats(ChannelId::new(1));
+    assert_eq!(stats.received, 3);
+    assert_eq!(stats.lost, 1);
+    assert_eq!(stats.late, 1);
+    assert_eq!(stats.malformed, 3);
+
+    demux.reset();
+    assert_eq!(demux.stats(Imu::CHANNEL), ChannelStats::default());
+}
+
+#[test]
+fn device_restarts_are_not_late() {
+    let mut mux = Multiplexer::<64, 1>::new().channel::<Imu>();
+    let mut demux = Demultiplexer::new();
+
+    for n in 0..100 {
+        demux
+            .receive(&mut mux.telemetry(&imu(n)).unwrap().to_vec())
+            .unwrap();
+    }
+
+    let mut restarted = Multiplexer::<64, 1>::new().channel::<Imu>();
+    for n in 0..3 {
+        let mut frame = restarted.telemetry(&imu(n)).unwrap().to_vec();
+        if n != 1 {
+            demux.receive(&mut frame).unwrap();
+        }
+    }
+
+    let stats = demux.stats(Imu::CHANNEL);
+    assert_eq!(stats.received, 102);
+    assert_eq!(stats.late, 0);
+    assert_eq!(stats.lost, 1);
+}
+
+#[test]
+fn early_device_restarts_are_not_late() {
+    let mut mux = Multiplexer::<64, 1>::new().channel::<Imu>();
+    let mut demux = Demultiplexer::new();
+    let imus = demux.stream::<Imu>();
+
+    // A device that was up for an hour
+    for n in 0..10 {
+        let packet = Packet::new(
+            Timestamp::from_micros(3_600_000_000 + n as u64),
+            imu(n).payload,
+        );
+        demux
+            .receive(&mut mux.telemetry(&packet).unwrap().to_vec())
+            .unwrap();
+    }
+
+    let mut restarted = Multiplexer::<64, 1>::new().channel::<Imu>();
+    for n in 0..3 {
+        demux
+            .receive(&mut restarted.telemetry(&imu(n)).unwrap().to_vec())
+            .unwrap();
+    }
+
+    let stats = demux.stats(Imu::CHANNEL);
+    assert_eq!(stats.received, 13);
+    assert_eq!(stats.late, 0);
+    assert_eq!(stats.lost, 0);
+    assert_eq!(imus.try_iter().count(), 13);
+}
+
+#[test]
+fn dropped_streams_unsubscribe() {
+    let mut mux = Multiplexer::<64, 1>::new().channel::<Imu>();
+    let mut demux = Demultiplexer::new();
+
+    drop(demux.stream::<Imu>());
+    demux
+        .receive(&mut mux.telemetry(&imu(0)).unwrap().to_vec())
+        .unwrap();
+
+    assert_eq!(demux.stats(Imu::CHANNEL).received, 1);
+}
+
+#[test]
+fn other_frames_are_handed_back() {
+    let mut demux = Demultiplexer::new();
+    let header = Header::Response(ResponseHeader {
+        sequence: Sequence::new(4),
+        status: Status::Ack,
+    });
+    let mut frame = wire::to_vec(&header, &7u8).unwrap();
+
+    let frame = demux.receive(&mut frame).unwrap().unwrap();
+
+    assert_eq!(frame.header, header);
+    assert_eq!(frame.decode_payload::<u8>().unwrap(), 7);
+    assert_eq!(demux.channels().count(), 0);
+}
@@ -0,0 +1,269 @@
+#![cfg(feature = "std")]
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use micromanager_tele::{
+    param::{
+        Error, GetParameter, ListParameters, ParamId, Parameter, ParameterChanged, ParameterPage,
+        PersistParameters, Registry, SetParameter, Storage, Table, Value,
+    },
+    telecommand::{NackReason, Telecommand},
+};
+
+const GYRO_GAIN: Parameter<f32> = Parameter {
+    id: ParamId::new(1),
+    name: "gyro.gain",
+    default: 1.0,
+    min: 0.0,
+    max: 8.0,
+};
+
+const RATE: Parameter<u32> = Parameter {
+    id: ParamId::new(2),
+    name: "imu.rate",
+    default: 100,
+    min: 1,
+    max: 1000,
+};
+
+const OFFSET: Parameter<i32> = Parameter {
+    id: ParamId::new(3),
+    name: "mag.offset",
+    default: 0,
+    min: -500,
+    max: 500,
+};
+
+const LOGGING: Parameter<bool> = Parameter {
+    id: ParamId::new(4),
+    name: "log.enabled",
+    default: false,
+    min: false,
+    max: true,
+};
+
+const FILTER: Parameter<bool> = Parameter {
+    id: ParamId::new(5),
+    name: "imu.filter",
+    default: true,
+    min: false,
+    max: true,
+};
+
+/// Non-volatile memory of a device, kept across resets
+#[derive(Default)]
+struct Flash(HashMap<ParamId, Value>);
+
+impl Storage for &mut Flash {
+    type Error = ();
+
+    fn load(&mut self, id: ParamId) -> Option<Value> {
+        self.0.get(&id).copied()
+    }
+
+    fn store(&mut self, id: ParamId, value: Value) -> Result<(), ()> {
+        self.0.insert(id, value);
+
+        Ok(())
+    }
+}
+
+fn registry<S: Storage>(storage: S) -> Registry<S, 8> {
+    Registry::new(storage)
+        .declare(&LOGGING)
+        .declare(&GYRO_GAIN)
+        .declare(&RATE)
+        .declare(&OFFSET)
+        .declare(&FILTER)
+}
+
+/// Run a command through the parameter router as the dispatcher would
+fn command<S: Storage, C: Telecommand>(
+    registry: &mut Registry<S, 8>,
+    command: &C,
+) -> Result<C::Response, NackReason> {
+    let payload = postcard::to_allocvec(command).unwrap();
+    let mut response = [0; 256];
+
+    let len = Registry::router().handle(registry, C::ID, &payload, &mut response)?;
+
+    Ok(postcard::from_bytes(&response[..len]).unwrap())
+}
+
+#[test]
+fn typed_access_on_the_device() {
+    let mut registry = registry(());
+
+    assert_eq!(registry.get(&GYRO_GAIN), 1.0);
+    assert_eq!(registry.get(&RATE), 100);
+
+    registry.set(&RATE, 250).unwrap();
+    assert_eq!(registry.get(&RATE), 250);
+    assert_eq!(registry.set(&RATE, 0), Err(Error::OutOfBounds));
+    assert_eq!(registry.get(&RATE), 250);
+
+    assert_eq!(
+        registry
+            .descriptors()
+            .map(|d| d.id.get())
+            .collect::<Vec<_>>(),
+        [1, 2, 3, 4, 5]
+    );
+}
+
+#[test]
+#[should_panic(expected = "duplicate parameter param#1")]
+fn duplicate_declarations_panic() {
+    Registry::<(), 4>::new(())
+        .declare(&GYRO_GAIN)
+        .declare(&GYRO_GAIN);
+}
+
+#[test]
+fn commands_check_kind_and_bounds() {
+    let mut registry = registry(());
+
+    let get = GetParameter { id: OFFSET.id };
+    assert_eq!(command(&mut registry, &get), Ok(Value::I32(0)));
+
+    let set = |value| SetParameter {
+        id: OFFSET.id,
+        value,
+    };
+    assert_eq!(
+        command(&mut registry, &set(Value::I32(-20))),
+        Ok(Value::I32(-20))
+    );
+    assert_eq!(
+        command(&mut registry, &set(Value::I32(501))),
+        Err(Error::OutOfBounds.into())
+    );
+    assert_eq!(
+        command(&mut registry, &set(Value::F32(1.0))),
+        Err(Error::WrongKind.into())
+    );
+    assert_eq!(registry.get(&OFFSET), -20);
+
+    let unknown = GetParameter {
+        id: ParamId::new(9),
+    };
+    let reason = command(&mut registry, &unknown).unwrap_err();
+    assert_eq!(Error::from_reason(reason), Some(Error::UnknownParameter));
+}
+
+#[test]
+fn persisted_values_survive_resets() {
+    let mut flash = Flash::default();
+
+    let mut device = registry(&mut flash);
+    device.set(&GYRO_GAIN, 2.5).unwrap();
+    command(&mut device, &PersistParameters).unwrap();
+    device.set(&GYRO_GAIN, 3.0).unwrap();
+    drop(device);
+
+    let device = registry(&mut flash);
+    assert_eq!(device.get(&GYRO_GAIN), 2.5);
+    assert_eq!(device.get(&RATE), 100);
+
+    // Values the bounds no longer allow are reset to their default
+    flash.0.insert(RATE.id, Value::U32(5000));
+    assert_eq!(registry(&mut flash).get(&RATE), 100);
+}
+
+#[test]
+fn persisting_without_storage_fails() {
+    let mut registry = registry(());
+
+    assert_eq!(
+        command(&mut registry, &PersistParameters),
+        Err(Error::Storage.into())
+    );
+}
+
+#[test]
+fn changes_are_notified_once() {
+    let mut registry = registry(());
+
+    registry.set(&RATE, 200).unwrap();
+    registry.set(&OFFSET, 7).unwrap();
+    registry.set(&RATE, 300).unwrap();
+    // Writing the current value is not a change
+    registry.set(&LOGGING, false).unwrap();
+
+    let changes = std::iter::from_fn(|| registry.next_change()).collect::<Vec<_>>();
+    assert_eq!(
+        changes,
+        [
+            ParameterChanged {
+                id: RATE.id,
+                value: Value::U32(300)
+            },
+            ParameterChanged {
+                id: OFFSET.id,
+                value: Value::I32(7)
+            },
+        ]
+    );
+}
+
+#[test]
+fn ground_lists_and_tracks_parameters() {
+    let mut device = registry(());
+    let mut table = Table::new();
+
+    let changes = Arc::new(Mutex::new(Vec::new()));
+    table.subscribe({
+        let changes = changes.clone();
+        move |descriptor, value| {
+            changes
+                .lock()
+                .unwrap()
+                .push((descriptor.name.to_string(), value))
+        }
+    });
+
+    let mut request = Some(ListParameters { start: 0 });
+    let mut pages = 0;
+    while let Some(list) = request {
+        let page: ParameterPage = command(&mut device, &list).unwrap();
+        request = table.insert_page(&page);
+        pages += 1;
+    }
+
+    assert_eq!(pages, 2);
+    assert!(table.is_complete());
+    assert_eq!(table.iter().count(), 5);
+    assert_eq!(changes.lock().unwrap().len(), 5);
+
+    let (descriptor, value) = table.find("gyro.gain").unwrap();
+    assert_eq!(descriptor.id, GYRO_GAIN.id);
+    assert_eq!(descriptor.max, Value::F32(8.0));
+    assert_eq!(value, Value::F32(1.0));
+
+    // Writes are checked before they are sent
+    assert_eq!(
+        table.set("gyro.gain", Value::F32(9.0)),
+        Err(Error::OutOfBounds)
+    );
+    assert_eq!(table.set("gyro.gain", Value::U32(1)), Err(Error::WrongKind));
+    assert_eq!(
+        table.set("gyro.rate", Value::F32(1.0)),
+        Err(Error::UnknownParameter)
+    );
+
+    let set = table.set("gyro.gain", Value::F32(4.0)).unwrap();
+    let value = command(&mut device, &set).unwrap();
+    table.update(set.id, value);
+    assert_eq!(table.get(GYRO_GAIN.id).unwrap().1, Value::F32(4.0));
+
+    // Notifications for values the ground already knows are ignored
+    let change = device.next_change().unwrap();
+    table.update(change.id, change.value);
+
+    let changes = changes.lock().unwrap();
+    assert_eq!(changes.len(), 6);
+    assert_eq!(changes[5], ("gyro.gain".to_string(), Value::F32(4.0)));
+}
@@ -0,0 +1,251 @@
+#![cfg(feature = "std")]
+
+use std::{
+    fs,
+    io::ErrorKind,
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use micromanager_tele::{
+    recording::{FrameStatus, LinkId, Reader, Recorder},
+    telemetry::{Packet, Telemetry},
+    time::Timestamp,
+    wire::Encoder,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Telemetry)]
+#[telemetry(channel = 4)]
+struct Pressure {
+    pascal: f32,
+}
+
+fn frame(sequence: u16) -> Vec<u8> {
+    let packet = Packet::new(
+        Timestamp::from_micros(sequence as u64 * 1000),
+        Pressure { pascal: 101_325.0 },
+    );
+
+    Encoder::<64>::new()
+        .telemetry(sequence, &packet)
+        .unwrap()
+        .to_vec()
+}
+
+fn at(millis: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_millis(1_600_000_000_000 + millis)
+}
+
+/// A file in the temporary directory, removed when dropped
+struct TempFile(PathBuf);
+
+impl TempFile {
+    fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!(
+            "micromanager-tele-{}-{}.mmtlog",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+
+        Self(path)
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+fn record_session() -> Vec<u8> {
+    let mut recorder = Recorder::new(Vec::new()).unwrap();
+    let radio = recorder.link("radio 433MHz").unwrap();
+    let usb = recorder.link("serial /dev/ttyACM0").unwrap();
+
+    let mut corrupt = frame(1);
+    let payload = corrupt.iter().position(|&byte| byte == 0xc5).unwrap();
+    corrupt[payload] ^= 0x10;
+
+    assert_eq!(
+        recorder.record(radio, &frame(0), at(0)).unwrap(),
+        FrameStatus::Valid
+    );
+    assert_eq!(
+        recorder.record(radio, &corrupt, at(10)).unwrap(),
+        FrameStatus::Checksum
+    );
+    assert_eq!(
+        recorder.record(usb, &frame(2), at(20)).unwrap(),
+        FrameStatus::Valid
+    );
+
+    recorder.into_inner()
+}
+
+/// Offset of the last record of a recording
+fn last_record(recording: &[u8]) -> usize {
+    let mut offset = 8;
+
+    loop {
+        let len = u32::from_le_bytes(recording[offset..offset + 4].try_into().unwrap());
+        let next = offset + 4 + len as usize + 4;
+        if next == recording.len() {
+            return offset;
+        }
+
+        offset = next;
+    }
+}
+
+#[test]
+fn records_round_trip() {
+    let recording = record_session();
+    let mut reader = Reader::new(&recording[..]).unwrap();
+
+    let records: Vec<_> = (&mut reader).collect::<Result<_, _>>().unwrap();
+
+    assert_eq!(records.len(), 3);
+    assert_eq!(records[0].frame, frame(0));
+    assert_eq!(records[0].received, at(0));
+    assert_eq!(records[1].status, FrameStatus::Checksum);
+    assert_eq!(records[2].link, LinkId::new(1));
+    assert_eq!(records[2].received, at(20));
+
+    assert_eq!(reader.link_name(LinkId::new(0)), Some("radio 433MHz"));
+    assert_eq!(
+        reader.link_name(LinkId::new(1)),
+        Some("serial /dev/ttyACM0")
+    );
+    assert!(!reader.is_torn());
+}
+
+#[test]
+fn torn_record_ends_recording() {
+    let recording = record_session();
+
+    for cut in 1..12 {
+        let mut reader = Reader::new(&recording[..recording.len() - cut]).unwrap();
+        let records: Vec<_> = (&mut reader).collect::<Result<_, _>>().unwrap();
+
+        assert_eq!(records.len(), 2, "{} bytes cut", cut);
+        assert!(reader.is_torn());
+    }
+}
+
+#[test]
+fn corrupt_record_is_an_error() {
+    let mut recording = record_session();
+    let middle = recording.len() / 2;
+    recording[middle] ^= 0x01;
+
+    let error = Reader::new(&recording[..])
+        .unwrap()
+        .find_map(Result::err)
+        .unwrap();
+
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+}
+
+#[test]
+fn corrupt_last_record_ends_recording() {
+    let recording = record_session();
+
+    // A garbled checksum, then a garbled length
+    for offset in [recording.len() - 1, last_record(&recording) + 3] {
+        let mut recording = recording.clone();
+        recording[offset] ^= 0x80;
+
+        let mut reader = Reader::new(&recording[..]).unwrap();
+        let records: Vec<_> = (&mut reader).collect::<Result<_, _>>().unwrap();
+
+        assert_eq!(records.len(), 2, "byte {} corrupt", offset);
+        assert!(reader.is_torn());
+    }
+}
+
+#[test]
+fn foreign_files_are_refused() {
+    let error = Reader::new(&b"PK\x03\x04 not a recording"[..]).unwrap_err();
+
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+}
+
+#[test]
+fn append_recovers_from_crash() {
+    let file = TempFile::new("append");
+
+    let mut recorder = Recorder::create(&file.0).unwrap();
+    let radio = recorder.link("radio").unwrap();
+    recorder.record(radio, &frame(0), at(0)).unwrap();
+    recorder.record(radio, &frame(1), at(10)).unwrap();
+    recorder.sync().unwrap();
+    drop(recorder);
+
+    assert_eq!(
+        Recorder::create(&file.0).unwrap_err().kind(),
+        ErrorKind::AlreadyExists
+    );
+
+    // Crash half way through writing the second frame
+    let len = fs::metadata(&file.0).unwrap().len();
+    fs::OpenOptions::new()
+        .write(true)
+        .open(&file.0)
+        .unwrap()
+        .set_len(len - 5)
+        .unwrap();
+
+    let mut recorder = Recorder::append(&file.0).unwrap();
+    let usb = recorder.link("usb").unwrap();
+    recorder.record(usb, &frame(2), at(20)).unwrap();
+    drop(recorder);
+
+    let mut reader = Reader::open(&file.0).unwrap();
+    let records: Vec<_> = (&mut reader).collect::<Result<_, _>>().unwrap();
+
+    assert_eq!(
+        records
+            .iter()
+            .map(|record| (record.link, record.received))
+            .collect::<Vec<_>>(),
+        [(radio, at(0)), (usb, at(20))]
+    );
+    assert_eq!(usb, LinkId::new(1));
+    assert!(!reader.is_torn());
+}
+
+#[test]
+fn append_recovers_from_corrupt_last_record() {
+    let file = TempFile::new("append-corrupt");
+
+    let mut recorder = Recorder::create(&file.0).unwrap();
+    let radio = recorder.link("radio").unwrap();
+    recorder.record(radio, &frame(0), at(0)).unwrap();
+    recorder.record(radio, &frame(1), at(10)).unwrap();
+    drop(recorder);
+
+    // Crash before the checksum of the last record reached the disk
+    let mut recording = fs::read(&file.0).unwrap();
+    let last = recording.len() - 1;
+    recording[last] = 0;
+    fs::write(&file.0, recording).unwrap();
+
+    let mut recorder = Recorder::append(&file.0).unwrap();
+    recorder.record(radio, &frame(2), at(20)).unwrap();
+    drop(recorder);
+
+    let records: Vec<_> = Reader::open(&file.0)
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+
+    assert_eq!(
+        records
+            .iter()
+            .map(|record| record.received)
+            .collect::<Vec<_>>(),
+        [at(0), at(20)]
+    );
+}
@@ -0,0 +1,164 @@
+#![cfg(feature = "std")]
+
+use std::{
+    io::ErrorKind,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use micromanager_tele::{
+    recording::{FrameStatus, LinkId, Reader, Record, Recorder},
+    transport::{Replay, Transport},
+};
+
+const MS: Duration = Duration::from_millis(1);
+
+/// Frames `[n]` received `n * 100` milliseconds into the recording
+fn records(count: u8) -> Vec<Record> {
+    (0..count)
+        .map(|n| Record {
+            received: UNIX_EPOCH + Duration::from_secs(1_600_000_000) + MS * 100 * n as u32,
+            link: LinkId::new(0),
+            status: FrameStatus::Malformed,
+            frame: vec![n, 0],
+        })
+        .collect()
+}
+
+/// The frames delivered at each of the given milliseconds after `start`
+fn deliveries(replay: &mut Replay, start: Instant, millis: &[u32]) -> Vec<Vec<u8>> {
+    millis
+        .iter()
+        .map(|&ms| {
+            std::iter::from_fn(|| replay.poll(start + MS * ms))
+                .map(|frame| frame[0])
+                .collect()
+        })
+        .collect()
+}
+
+#[test]
+fn original_timing() {
+    let start = Instant::now();
+    let mut replay = Replay::new(records(4), start);
+
+    assert_eq!(replay.duration(), MS * 300);
+    assert_eq!(
+        deliveries(&mut replay, start, &[0, 99, 100, 250, 400]),
+        [vec![0], vec![], vec![1], vec![2], vec![3]]
+    );
+    assert!(replay.is_finished());
+}
+
+#[test]
+fn speed_multiplier() {
+    let start = Instant::now();
+    let mut replay = Replay::new(records(4), start).speed(4.0);
+
+    assert_eq!(
+        deliveries(&mut replay, start, &[0, 25, 50]),
+        [vec![0], vec![1], vec![2]]
+    );
+
+    // Slowing down does not jump back in the recording
+    replay.set_speed(0.5, start + MS * 50);
+    assert_eq!(replay.position(start + MS * 50), MS * 200);
+    assert_eq!(
+        deliveries(&mut replay, start, &[249, 250]),
+        [vec![], vec![3]]
+    );
+}
+
+#[test]
+fn pause_and_seek() {
+    let start = Instant::now();
+    let mut replay = Replay::new(records(4), start);
+
+    assert_eq!(deliveries(&mut replay, start, &[0]), [vec![0]]);
+
+    replay.pause(start + MS * 50);
+    assert!(replay.is_paused());
+    assert_eq!(deliveries(&mut replay, start, &[1000]), [vec![]]);
+
+    replay.resume(start + MS * 1000);
+    assert_eq!(
+        deliveries(&mut replay, start, &[1049, 1050]),
+        [vec![], vec![1]]
+    );
+
+    replay.seek(MS * 250, start + MS * 2000);
+    assert_eq!(
+        deliveries(&mut replay, start, &[2000, 2050]),
+        [vec![], vec![3]]
+    );
+
+    replay.seek(Duration::ZERO, start + MS * 3000);
+    assert!(!replay.is_finished());
+    assert_eq!(deliveries(&mut replay, start, &[3100]), [vec![0, 1]]);
+}
+
+#[test]
+fn looping() {
+    let start = Instant::now();
+    let mut replay = Replay::new(records(3), start).looping(true);
+
+    assert_eq!(
+        deliveries(&mut replay, start, &[200, 250, 300, 400]),
+        [vec![0, 1, 2, 0], vec![], vec![1], vec![2, 0]]
+    );
+    assert!(!replay.is_finished());
+}
+
+#[test]
+fn recordings_without_duration_play_once() {
+    let start = Instant::now();
+    let mut records = records(2);
+    records[1].received = records[0].received;
+
+    let mut replay = Replay::new(records, start).looping(true);
+    assert_eq!(
+        deliveries(&mut replay, start, &[0, 100]),
+        [vec![0, 1], vec![]]
+    );
+    assert!(replay.is_finished());
+
+    let mut replay = Replay::new(Vec::new(), start).looping(true);
+    assert_eq!(replay.poll(start), None);
+    assert!(replay.is_finished());
+}
+
+#[test]
+fn records_out_of_order_keep_their_place() {
+    let start = Instant::now();
+    let mut records = records(4);
+    // The wall clock was set back by a second before the third record
+    records[2].received -= Duration::from_secs(1);
+    records[3].received -= Duration::from_secs(1);
+
+    let mut replay = Replay::new(records, start);
+    assert_eq!(replay.duration(), MS * 100);
+    assert_eq!(
+        deliveries(&mut replay, start, &[0, 100]),
+        [vec![0], vec![1, 2, 3]]
+    );
+
+    replay.seek(MS * 50, start + MS * 1000);
+    assert_eq!(deliveries(&mut replay, start, &[1050]), [vec![1, 2, 3]]);
+}
+
+#[test]
+fn replays_a_recording() {
+    let mut recorder = Recorder::new(Vec::new()).unwrap();
+    let link = recorder.link("radio").unwrap();
+    let now = SystemTime::now();
+    recorder.record(link, &[1, 2, 0], now).unwrap();
+    recorder.record(link, &[3, 4, 0], now).unwrap();
+    let recording = recorder.into_inner();
+
+    let reader = Reader::new(&recording[..]).unwrap();
+    let mut replay = Replay::from_reader(reader, Instant::now()).unwrap();
+
+    replay.send(&[5, 0]).unwrap();
+    assert_eq!(replay.recv().unwrap(), Some(vec![1, 2, 0]));
+    assert_eq!(replay.recv().unwrap(), Some(vec![3, 4, 0]));
+    assert_eq!(replay.recv().unwrap_err().kind(), ErrorKind::UnexpectedEof);
+}
@@ -0,0 +1,224 @@
+use core::time::Duration;
+
+use micromanager_tele::{
+    scheduler::{Budget, ChannelCounters, ChannelPolicy, Error, Scheduler},
+    telemetry::{ChannelId, Telemetry},
+    time::Timestamp,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Telemetry)]
+#[telemetry(channel = 1)]
+struct Imu;
+
+#[derive(Serialize, Deserialize, Telemetry)]
+#[telemetry(channel = 2)]
+struct Housekeeping;
+
+#[derive(Serialize, Deserialize, Telemetry)]
+#[telemetry(channel = 3)]
+struct Spectrum;
+
+const IMU: ChannelId = Imu::CHANNEL;
+const HOUSEKEEPING: ChannelId = Housekeeping::CHANNEL;
+
+fn ms(millis: u64) -> Timestamp {
+    Timestamp::from_micros(millis * 1000)
+}
+
+/// A frame of `len` bytes tagged with `tag`
+fn frame(tag: u8, len: usize) -> Vec<u8> {
+    vec![tag; len]
+}
+
+fn tag(frame: Option<&[u8]>) -> Option<(u8, usize)> {
+    frame.map(|frame| (frame[0], frame.len()))
+}
+
+#[test]
+fn responses_go_first() {
+    let mut scheduler = Scheduler::<64, 2, 4>::new()
+        .channel::<Imu>(ChannelPolicy {
+            priority: 10,
+            ..ChannelPolicy::default()
+        })
+        .channel::<Housekeeping>(ChannelPolicy::default());
+
+    scheduler.telemetry(IMU, &frame(1, 8), ms(0)).unwrap();
+    scheduler
+        .telemetry(HOUSEKEEPING, &frame(2, 8), ms(0))
+        .unwrap();
+    scheduler.response(&frame(0xa0, 4)).unwrap();
+    scheduler.response(&frame(0xa1, 4)).unwrap();
+
+    let sent: Vec<_> = core::iter::from_fn(|| tag(scheduler.poll(ms(0)))).collect();
+
+    assert_eq!(sent, [(0xa0, 4), (0xa1, 4), (1, 8), (2, 8)]);
+    assert!(scheduler.is_idle());
+}
+
+#[test]
+fn only_the_newest_packet_is_kept() {
+    let mut scheduler = Scheduler::<64, 1, 1>::new().channel::<Imu>(ChannelPolicy::default());
+
+    for n in 0..5 {
+        scheduler.telemetry(IMU, &frame(n, 8), ms(0)).unwrap();
+    }
+
+    assert_eq!(tag(scheduler.poll(ms(0))), Some((4, 8)));
+    assert_eq!(tag(scheduler.poll(ms(0))), None);
+    assert_eq!(
+        scheduler.counters(IMU),
+        Some(ChannelCounters {
+            sent: 1,
+            superseded: 4,
+            stale: 0,
+        })
+    );
+    assert_eq!(scheduler.dropped(), 4);
+}
+
+#[test]
+fn link_budget_holds_back_telemetry_for_responses() {
+    let mut scheduler = Scheduler::<64, 1, 2>::new()
+        .link_budget(Budget {
+            bytes_per_second: 1000,
+            burst: 20,
+        })
+        .channel::<Imu>(ChannelPolicy::default());
+
+    scheduler.telemetry(IMU, &frame(1, 16), ms(0)).unwrap();
+    assert_eq!(tag(scheduler.poll(ms(0))), Some((1, 16)));
+
+    // 4 bytes left in the burst, the response needs 10
+    scheduler.telemetry(IMU, &frame(2, 4), ms(0)).unwrap();
+    scheduler.response(&frame(0xa0, 10)).unwrap();
+    assert_eq!(tag(scheduler.poll(ms(0))), None);
+    assert_eq!(tag(scheduler.poll(ms(5))), None);
+    assert_eq!(tag(scheduler.poll(ms(6))), Some((0xa0, 10)));
+    assert_eq!(tag(scheduler.poll(ms(6))), None);
+    assert_eq!(tag(scheduler.poll(ms(10))), Some((2, 4)));
+}
+
+#[test]
+fn zero_budget_lets_nothing_through() {
+    let mut scheduler = Scheduler::<64, 2, 1>::new()
+        .channel::<Imu>(ChannelPolicy {
+            priority: 1,
+            budget: Some(Budget {
+                bytes_per_second: 1000,
+                burst: 0,
+            }),
+            max_age: None,
+        })
+        .channel::<Housekeeping>(ChannelPolicy::default());
+
+    scheduler.telemetry(IMU, &frame(1, 8), ms(0)).unwrap();
+    scheduler
+        .telemetry(HOUSEKEEPING, &frame(2, 8), ms(0))
+        .unwrap();
+
+    assert_eq!(tag(scheduler.poll(ms(0))), Some((2, 8)));
+    assert_eq!(tag(scheduler.poll(ms(1000))), None);
+    assert_eq!(scheduler.counters(IMU).unwrap().sent, 0);
+
+    let mut muted = Scheduler::<64, 1, 1>::new().link_budget(Budget {
+        bytes_per_second: 1000,
+        burst: 0,
+    });
+    muted.response(&frame(0xa0, 4)).unwrap();
+    assert_eq!(tag(muted.poll(ms(1000))), None);
+}
+
+#[test]
+fn channel_budgets_let_lower_priorities_through() {
+    let mut scheduler = Scheduler::<64, 2, 1>::new()
+        .channel::<Imu>(ChannelPolicy {
+            priority: 1,
+            budget: Some(Budget {
+                bytes_per_second: 100,
+                burst: 10,
+            }),
+            max_age: None,
+        })
+        .channel::<Housekeeping>(ChannelPolicy::default());
+
+    let mut sent = Vec::new();
+    for millis in (0..1000).step_by(10) {
+        scheduler.telemetry(IMU, &frame(1, 10), ms(millis)).unwrap();
+        scheduler
+            .telemetry(HOUSEKEEPING, &frame(2, 10), ms(millis))
+            .unwrap();
+
+        while let Some(frame) = tag(scheduler.poll(ms(millis))) {
+            sent.push(frame.0);
+        }
+    }
+
+    let imu = sent.iter().filter(|&&tag| tag == 1).count();
+    assert_eq!(imu, 10);
+    assert_eq!(sent.len(), 110);
+    // One packet sent every 100ms, and the last one still pending
+    assert_eq!(scheduler.counters(IMU).unwrap().superseded, 89);
+}
+
+#[test]
+fn equal_priorities_take_turns() {
+    let mut scheduler = Scheduler::<64, 2, 1>::new()
+        .link_budget(Budget {
+            bytes_per_second: 1000,
+            burst: 10,
+        })
+        .channel::<Imu>(ChannelPolicy::default())
+        .channel::<Housekeeping>(ChannelPolicy::default());
+
+    let mut sent = Vec::new();
+    for millis in (0..100).step_by(10) {
+        scheduler.telemetry(IMU, &frame(1, 10), ms(millis)).unwrap();
+        scheduler
+            .telemetry(HOUSEKEEPING, &frame(2, 10), ms(millis))
+            .unwrap();
+
+        sent.extend(tag(scheduler.poll(ms(millis))).map(|(tag, _)| tag));
+    }
+
+    assert_eq!(sent, [1, 2, 1, 2, 1, 2, 1, 2, 1, 2]);
+}
+
+#[test]
+fn stale_packets_are_dropped() {
+    let mut scheduler = Scheduler::<64, 1, 1>::new()
+        .link_budget(Budget {
+            bytes_per_second: 100,
+            burst: 10,
+        })
+        .channel::<Imu>(ChannelPolicy {
+            max_age: Some(Duration::from_millis(50)),
+            ..ChannelPolicy::default()
+        });
+
+    scheduler.telemetry(IMU, &frame(1, 10), ms(0)).unwrap();
+    assert_eq!(tag(scheduler.poll(ms(0))), Some((1, 10)));
+
+    // The link only has room again after 100ms
+    scheduler.telemetry(IMU, &frame(2, 10), ms(0)).unwrap();
+    assert_eq!(tag(scheduler.poll(ms(100))), None);
+    assert_eq!(scheduler.counters(IMU).unwrap().stale, 1);
+    assert!(scheduler.is_idle());
+}
+
+#[test]
+fn refused_frames() {
+    let mut scheduler = Scheduler::<8, 1, 1>::new().channel::<Imu>(ChannelPolicy::default());
+
+    assert_eq!(
+        scheduler.telemetry(Spectrum::CHANNEL, &[0; 4], ms(0)),
+        Err(Error::UnknownChannel(Spectrum::CHANNEL))
+    );
+    assert_eq!(
+        scheduler.telemetry(IMU, &[0; 9], ms(0)),
+        Err(Error::TooLong)
+    );
+    assert_eq!(scheduler.response(&[0; 4]), Ok(()));
+    assert_eq!(scheduler.response(&[0; 4]), Err(Error::Full));
+}
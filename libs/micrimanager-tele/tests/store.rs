@@ -0,0 +1,275 @@
+#![cfg(feature = "std")]
+
+use std::time::{Duration, Instant};
+
+use micromanager_tele::{
+    store::Store,
+    telemetry::{Telemetry, TelemetryHeader},
+    time::Timestamp,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Telemetry)]
+#[telemetry(channel = 1)]
+struct Altitude(f32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Telemetry)]
+#[telemetry(channel = 2)]
+struct Voltage(u16);
+
+const MS: Duration = Duration::from_millis(1);
+
+fn update<T: Telemetry>(store: &mut Store, sequence: u16, value: T, now: Instant) -> bool {
+    let timestamp = Timestamp::from_micros(sequence as u64 * 100_000);
+
+    update_at(store, sequence, timestamp, value, now)
+}
+
+fn update_at<T: Telemetry>(
+    store: &mut Store,
+    sequence: u16,
+    timestamp: Timestamp,
+    value: T,
+    now: Instant,
+) -> bool {
+    let header = TelemetryHeader {
+        channel: T::CHANNEL,
+        sequence,
+        timestamp,
+    };
+
+    store.update(&header, &postcard::to_allocvec(&value).unwrap(), now)
+}
+
+#[test]
+fn latest_value_is_kept() {
+    let start = Instant::now();
+    let mut store = Store::new();
+
+    assert_eq!(store.get::<Altitude>(start).unwrap(), None);
+
+    update(&mut store, 0, Altitude(10.0), start);
+    update(&mut store, 1, Altitude(12.5), start + MS * 100);
+
+    let reading = store.get::<Altitude>(start + MS * 150).unwrap().unwrap();
+    assert_eq!(reading.value, Altitude(12.5));
+    assert_eq!(reading.sequence, 1);
+    assert_eq!(reading.timestamp, Timestamp::from_micros(100_000));
+    assert_eq!(reading.freshness.received, start + MS * 100);
+    assert_eq!(reading.freshness.age, MS * 50);
+    assert_eq!(reading.freshness.interval, Some(MS * 100));
+    assert!(!reading.freshness.stale);
+}
+
+#[test]
+fn channels_go_stale() {
+    let start = Instant::now();
+    let mut store = Store::new();
+
+    for n in 0..10 {
+        update(&mut store, n, Altitude(0.0), start + MS * 100 * n as u32);
+    }
+
+    let last = start + MS * 900;
+    let freshness = |now| store.freshness(Altitude::CHANNEL, now).unwrap();
+
+    assert!(!freshness(last + MS * 300).stale);
+    assert!(freshness(last + MS * 301).stale);
+}
+
+#[test]
+fn lost_values_do_not_slow_the_estimate() {
+    let start = Instant::now();
+    let mut store = Store::new();
+
+    // Only every fourth value of a 10Hz channel arrives
+    for n in (0..40).step_by(4) {
+        update(&mut store, n, Voltage(n), start + MS * 100 * n as u32);
+    }
+
+    let freshness = store.freshness(Voltage::CHANNEL, start).unwrap();
+    assert_eq!(freshness.interval, Some(MS * 100));
+}
+
+#[test]
+fn configured_interval_overrides_estimate() {
+    let start = Instant::now();
+    let mut store = Store::new()
+        .expect::<Voltage>(Duration::from_secs(1))
+        .tolerance(2);
+
+    update(&mut store, 0, Voltage(3300), start);
+
+    let freshness = |now| store.freshness(Voltage::CHANNEL, now).unwrap();
+    assert_eq!(freshness(start).interval, Some(Duration::from_secs(1)));
+    assert!(!freshness(start + Duration::from_secs(2)).stale);
+    assert!(freshness(start + Duration::from_secs(3)).stale);
+
+    // A single value is not enough to estimate a rate from
+    update(&mut store, 0, Altitude(0.0), start);
+    let altitude = store.freshness(Altitude::CHANNEL, start + Duration::from_secs(60));
+    assert!(!altitude.unwrap().stale);
+}
+
+#[test]
+fn late_values_are_ignored() {
+    let start = Instant::now();
+    let mut store = Store::new();
+
+    assert!(update(&mut store, 5, Altitude(5.0), start));
+    assert!(!update(&mut store, 4, Altitude(4.0), start + MS));
+    assert!(!update(&mut store, 5, Altitude(5.0), start + MS));
+
+    let reading = store.get::<Altitude>(start).unwrap().unwrap();
+    assert_eq!(reading.value, Altitude(5.0));
+
+    // Far behind is a restart of the device
+    assert!(update(&mut store, 5000, Altitude(1.0), start + MS * 2));
+    assert!(update(&mut store, 0, Altitude(0.0), start + MS * 3));
+
+    let reading = store.get::<Altitude>(start).unwrap().unwrap();
+    assert_eq!(reading.sequence, 0);
+    assert_eq!(reading.freshness.interval, None);
+    assert_eq!(store.channels(start + MS * 3).count(), 1);
+}
+
+#[test]
+fn device_restarts_are_told_by_device_time() {
+    let start = Instant::now();
+    let mut store = Store::new();
+    let uptime = |sequence: u16| Timestamp::from_micros(3_600_000_000 + sequence as u64 * 100_000);
+
+    for sequence in 0..10 {
+        update_at(&mut store, sequence, uptime(sequence), Altitude(9.0), start);
+    }
+
+    // Restarted before sending as many packets as before
+    assert!(update(&mut store, 0, Altitude(0.0), start + MS * 100));
+    assert!(update(&mut store, 1, Altitude(1.0), start + MS * 200));
+
+    let reading = store.get::<Altitude>(start + MS * 200).unwrap().unwrap();
+    assert_eq!(reading.value, Altitude(1.0));
+    assert!(!reading.freshness.stale);
+
+    // Or once half way through the sequence numbers
+    update_at(
+        &mut store,
+        40_000,
+        uptime(40_000),
+        Altitude(9.0),
+        start + MS * 300,
+    );
+    assert!(update(&mut store, 0, Altitude(0.0), start + MS * 400));
+
+    let reading = store.get::<Altitude>(start + MS * 400).unwrap().unwrap();
+    assert_eq!(reading.value, Altitude(0.0));
+    assert_eq!(reading.freshness.interval, None);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Telemetry)]
+#[telemetry(channel = 3)]
+struct Current(f32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Telemetry)]
+#[telemetry(channel = 0x100)]
+struct Power(f32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Telemetry)]
+#[telemetry(channel = 0x101)]
+struct Overload(bool);
+
+fn power() -> Store {
+    Store::new()
+        .expect::<Voltage>(MS * 100)
+        .expect::<Current>(Duration::from_secs(1))
+        .derive(|(Voltage(millivolts), Current(amps))| Power(millivolts as f32 / 1000.0 * amps))
+        .derive(|(Power(watts),)| Overload(watts > 20.0))
+}
+
+#[test]
+fn derived_channels_follow_their_inputs() {
+    let start = Instant::now();
+    let mut store = power();
+
+    // Nothing to compute until every input has a value
+    update(&mut store, 0, Voltage(12000), start);
+    assert_eq!(store.get::<Power>(start).unwrap(), None);
+
+    update(&mut store, 0, Current(1.5), start);
+    update(&mut store, 1, Voltage(10000), start + MS * 100);
+
+    let reading = store.get::<Power>(start + MS * 100).unwrap().unwrap();
+    assert_eq!(reading.value, Power(15.0));
+    assert_eq!(reading.sequence, 1);
+    // The newest input was sampled 100ms into the session
+    assert_eq!(reading.timestamp, Timestamp::from_micros(100_000));
+
+    // Derived channels are inputs like any other
+    assert_eq!(
+        store.get::<Overload>(start).unwrap().unwrap().value,
+        Overload(false)
+    );
+    update(&mut store, 2, Current(2.5), start + MS * 200);
+    assert_eq!(
+        store.get::<Overload>(start).unwrap().unwrap().value,
+        Overload(true)
+    );
+
+    let channels = store.channels(start + MS * 200).map(|(channel, _)| channel);
+    assert_eq!(
+        channels.collect::<Vec<_>>(),
+        [
+            Voltage::CHANNEL,
+            Current::CHANNEL,
+            Power::CHANNEL,
+            Overload::CHANNEL
+        ]
+    );
+}
+
+#[test]
+fn derived_channels_inherit_staleness() {
+    let start = Instant::now();
+    let mut store = power();
+
+    update(&mut store, 0, Current(1.0), start);
+    for n in 0..10 {
+        update(&mut store, n, Voltage(12000), start + MS * 100 * n as u32);
+    }
+
+    // The current is the least fresh input, though the voltage keeps arriving
+    let freshness = store
+        .freshness(Overload::CHANNEL, start + MS * 900)
+        .unwrap();
+    assert_eq!(freshness.received, start);
+    assert_eq!(freshness.interval, Some(Duration::from_secs(1)));
+    assert!(!freshness.stale);
+
+    // And the voltage goes stale first
+    let freshness = store.freshness(Power::CHANNEL, start + MS * 1201).unwrap();
+    assert_eq!(freshness.received, start + MS * 900);
+    assert!(freshness.stale);
+}
+
+#[test]
+fn derived_channels_are_not_received() {
+    let start = Instant::now();
+    let mut store = power();
+
+    assert!(!update(&mut store, 0, Power(1.0), start));
+    assert_eq!(store.get::<Power>(start).unwrap(), None);
+}
+
+#[test]
+#[should_panic(expected = "channel #256 is already derived")]
+fn channels_are_derived_once() {
+    let _ = power().derive(|(Current(amps),)| Power(amps));
+}
+
+#[test]
+#[should_panic(expected = "channel #256 is derived after channel #257, which is derived from it")]
+fn inputs_are_derived_first() {
+    let _ = Store::new()
+        .derive(|(Power(watts),)| Overload(watts > 20.0))
+        .derive(|(Voltage(millivolts), Current(amps))| Power(millivolts as f32 / 1000.0 * amps));
+}
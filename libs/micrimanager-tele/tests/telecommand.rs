@@ -0,0 +1,206 @@
+#![cfg(feature = "std")]
+
+use std::time::Duration;
+
+use micromanager_tele::{
+    telecommand::{
+        CommandFrame, CommandId, CommandState, Commander, Event, NackReason, Outcome,
+        Reconciliation, ResponseHeader, Resync, ResyncResponse, RetryPolicy, Sequence, Status,
+        Telecommand,
+    },
+    time::Timestamp,
+    wire::{self, Header},
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Telecommand)]
+#[telecommand(id = 7, response = u32)]
+struct Reboot {
+    delay: u8,
+}
+
+fn decode_command(mut frame: Vec<u8>) -> (Header, Vec<u8>) {
+    let frame = wire::decode(&mut frame).unwrap();
+
+    (frame.header, frame.payload.to_vec())
+}
+
+fn at(millis: u64) -> Timestamp {
+    Timestamp::from_micros(millis * 1000)
+}
+
+#[test]
+fn derive_assigns_id_and_response() {
+    assert_eq!(Reboot::ID, CommandId::new(7));
+    assert_eq!(Reboot::NAME, "Reboot");
+
+    let response: <Reboot as Telecommand>::Response = 5;
+    assert_eq!(response, 5_u32);
+}
+
+#[test]
+fn responses_resolve_commands() {
+    let mut commander = Commander::new(RetryPolicy::default());
+
+    let (header, payload) = decode_command(
+        commander
+            .issue_command(&Reboot { delay: 9 }, Timestamp::ZERO)
+            .unwrap(),
+    );
+    let Header::Command(acked) = header else {
+        panic!("expected a command, got {:?}", header);
+    };
+    assert_eq!(acked.command, Reboot::ID);
+    assert_eq!(payload, [9]);
+
+    let refused = commander.issue(Reboot::ID, vec![1], Timestamp::ZERO).header;
+    assert!(refused.sequence.is_after(acked.sequence));
+    assert_eq!(commander.pending().count(), 2);
+
+    let ack = ResponseHeader {
+        sequence: acked.sequence,
+        status: Status::Ack,
+    };
+    assert_eq!(commander.receive(&ack), Some(Outcome::Acknowledged(acked)));
+    // The response to a retransmission arrives after the command was resolved
+    assert_eq!(commander.receive(&ack), None);
+
+    let nack = ResponseHeader {
+        sequence: refused.sequence,
+        status: Status::Nack(NackReason::Busy),
+    };
+    assert_eq!(
+        commander.receive(&nack),
+        Some(Outcome::Rejected(refused, NackReason::Busy))
+    );
+    assert!(commander.is_idle());
+}
+
+#[test]
+fn unanswered_commands_are_retransmitted_then_time_out() {
+    let mut commander = Commander::new(RetryPolicy {
+        timeout: Duration::from_millis(100),
+        retries: 2,
+    });
+    let frame = commander.issue(Reboot::ID, vec![1], at(0));
+
+    assert_eq!(commander.poll(at(99)), None);
+    assert_eq!(
+        commander.poll(at(100)),
+        Some(Event::Retransmit(frame.clone()))
+    );
+    assert_eq!(commander.poll(at(150)), None);
+    assert_eq!(
+        commander.poll(at(200)),
+        Some(Event::Retransmit(frame.clone()))
+    );
+
+    assert_eq!(
+        commander.poll(at(300)),
+        Some(Event::Resolved(Outcome::TimedOut(frame.header)))
+    );
+    assert_eq!(commander.poll(at(1000)), None);
+    assert!(commander.is_idle());
+}
+
+#[test]
+fn untracked_commands_use_up_a_sequence() {
+    let mut commander = Commander::new(RetryPolicy::default());
+
+    let untracked = commander.untracked();
+    let tracked = commander.issue(Reboot::ID, vec![1], Timestamp::ZERO).header;
+    assert_eq!(tracked.sequence, untracked.next());
+
+    let response = ResponseHeader {
+        sequence: untracked,
+        status: Status::Ack,
+    };
+    assert_eq!(commander.receive(&response), None);
+    assert_eq!(commander.pending().collect::<Vec<_>>(), [&tracked]);
+}
+
+#[test]
+fn sequences_wrap() {
+    let last = Sequence::new(u16::MAX);
+
+    assert_eq!(last.next(), Sequence::new(0));
+    assert!(last.next().is_after(last));
+    assert!(!last.is_after(last.next()));
+    assert!(!last.is_after(last));
+}
+
+#[test]
+fn resync_reconciles_pending_commands() {
+    let mut commander = Commander::new(RetryPolicy::default());
+    let now = Timestamp::ZERO;
+
+    let executed = commander.issue(CommandId::new(1), vec![1], now).header;
+    let refused = commander.issue(CommandId::new(1), vec![2], now).header;
+    let lost = commander.issue(CommandId::new(1), vec![3], now).header;
+    let forgotten = commander.issue(CommandId::new(1), vec![4], now).header;
+
+    let (header, payload) = decode_command(commander.resync(now).unwrap());
+    let resync = match header {
+        Header::Command(header) => header,
+        header => panic!("expected a command, got {:?}", header),
+    };
+    assert_eq!(resync.command, CommandId::RESYNC);
+
+    let request: Resync = postcard::from_bytes(&payload).unwrap();
+    assert_eq!(
+        request.sequences,
+        [
+            executed.sequence,
+            refused.sequence,
+            lost.sequence,
+            forgotten.sequence
+        ]
+    );
+
+    let outcome = commander.receive(&ResponseHeader {
+        sequence: resync.sequence,
+        status: Status::Ack,
+    });
+    assert_eq!(outcome, Some(Outcome::Acknowledged(resync)));
+
+    let failed = Status::Nack(NackReason::Failed(2));
+    let response = ResyncResponse {
+        last_executed: Some(refused.sequence),
+        commands: [
+            (executed.sequence, CommandState::Executed(Status::Ack)),
+            (refused.sequence, CommandState::Executed(failed)),
+            (lost.sequence, CommandState::NotReceived),
+            (forgotten.sequence, CommandState::Forgotten),
+            (Sequence::new(1000), CommandState::NotReceived),
+        ]
+        .into_iter()
+        .collect(),
+    };
+    commander.reconcile(&response, now);
+
+    let events: Vec<_> = std::iter::from_fn(|| commander.poll(now)).collect();
+    let completed = vec![
+        Outcome::Acknowledged(executed),
+        Outcome::Rejected(refused, NackReason::Failed(2)),
+    ];
+
+    assert_eq!(
+        events,
+        [
+            Event::Resolved(completed[0]),
+            Event::Resolved(completed[1]),
+            Event::Retransmit(CommandFrame {
+                header: lost,
+                payload: vec![3],
+            }),
+            Event::Resolved(Outcome::Unknown(forgotten)),
+            Event::Resynchronised(Reconciliation {
+                last_executed: Some(refused.sequence),
+                resent: vec![lost],
+                completed,
+                unknown: vec![forgotten],
+            }),
+        ]
+    );
+    assert_eq!(commander.pending().collect::<Vec<_>>(), [&lost]);
+}
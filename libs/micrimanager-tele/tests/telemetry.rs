@@ -0,0 +1,75 @@
+use core::time::Duration;
+
+use micromanager_tele::{
+    telemetry::{ChannelId, Packet, Telemetry},
+    time::Timestamp,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Telemetry)]
+#[telemetry(channel = 3)]
+struct Magnetometer {
+    x: f32,
+    y: f32,
+    z: f32,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Telemetry)]
+#[telemetry(channel = 0x10)]
+struct Temperature(i16);
+
+#[test]
+fn derive_assigns_channel_and_name() {
+    assert_eq!(Magnetometer::CHANNEL, ChannelId::new(3));
+    assert_eq!(Magnetometer::NAME, "Magnetometer");
+
+    assert_eq!(Temperature::CHANNEL, ChannelId::new(0x10));
+    assert_eq!(Temperature::NAME, "Temperature");
+    assert_eq!(ChannelId::new(0x10).to_string(), "#16");
+}
+
+#[test]
+fn packets_are_sent_over_the_channel_of_their_payload() {
+    let packet = Packet::new(
+        Timestamp::from_micros(5),
+        Magnetometer {
+            x: 1.0,
+            y: 0.0,
+            z: -1.0,
+        },
+    );
+
+    assert_eq!(packet.channel(), Magnetometer::CHANNEL);
+    assert_eq!(packet.timestamp, Timestamp::from_micros(5));
+}
+
+#[test]
+fn timestamps_saturate() {
+    let timestamp = Timestamp::from_micros(1_500_000);
+
+    assert_eq!(
+        timestamp + Duration::from_millis(1),
+        Timestamp::from_micros(1_501_000)
+    );
+    assert_eq!(timestamp - Duration::from_secs(2), Timestamp::ZERO);
+    assert_eq!(
+        Timestamp::from_micros(u64::MAX) + Duration::from_secs(1),
+        Timestamp::from_micros(u64::MAX)
+    );
+
+    assert_eq!(
+        timestamp.saturating_duration_since(Timestamp::from_micros(500_000)),
+        Duration::from_secs(1)
+    );
+    assert_eq!(
+        Timestamp::ZERO.saturating_duration_since(timestamp),
+        Duration::ZERO
+    );
+    assert_eq!(Timestamp::from(Duration::from_millis(2)).as_micros(), 2_000);
+}
+
+#[test]
+fn timestamps_display_as_seconds() {
+    assert_eq!(Timestamp::from_micros(1_500_000).to_string(), "T+1.500000s");
+    assert_eq!(Timestamp::from_micros(42).to_string(), "T+0.000042s");
+}
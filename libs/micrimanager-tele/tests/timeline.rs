@@ -0,0 +1,364 @@
+#![cfg(feature = "std")]
+
+use std::time::Duration;
+
+use micromanager_tele::{
+    telecommand::{
+        CommandHandler, CommandId, Commander, Dispatcher, Event, NackReason, RetryPolicy, Router,
+        Sequence, Status, Telecommand,
+    },
+    time::Timestamp,
+    timeline::{
+        Error, Fate, ItemId, ListScheduled, Outcome, SchedulePage, Timeline, Tracker, When,
+    },
+    wire::{self, Header},
+};
+use serde::{Deserialize, Serialize};
+
+const SECOND: Duration = Duration::from_secs(1);
+
+#[derive(Serialize, Deserialize, Telecommand)]
+#[telecommand(id = 1, response = ())]
+struct StartLogging;
+
+#[derive(Serialize, Deserialize, Telecommand)]
+#[telecommand(id = 2, response = ())]
+struct StopLogging;
+
+#[derive(Serialize, Deserialize, Telecommand)]
+#[telecommand(id = 3, response = ())]
+struct Annotate(Vec<u8>);
+
+#[derive(Default)]
+struct Logger {
+    logging: bool,
+}
+
+impl CommandHandler<StartLogging> for Logger {
+    type Error = NackReason;
+
+    fn handle(&mut self, StartLogging: StartLogging) -> Result<(), NackReason> {
+        if self.logging {
+            return Err(NackReason::Busy);
+        }
+
+        self.logging = true;
+        Ok(())
+    }
+}
+
+impl CommandHandler<StopLogging> for Logger {
+    type Error = core::convert::Infallible;
+
+    fn handle(&mut self, StopLogging: StopLogging) -> Result<(), Self::Error> {
+        self.logging = false;
+        Ok(())
+    }
+}
+
+struct Device {
+    dispatcher: Dispatcher<8, 64>,
+    timeline: Timeline<4>,
+    logger: Logger,
+    routes: Router<Logger, 2>,
+}
+
+impl Device {
+    fn new() -> Self {
+        Self {
+            dispatcher: Dispatcher::new(),
+            timeline: Timeline::new(),
+            logger: Logger::default(),
+            routes: Router::new().route::<StartLogging>().route::<StopLogging>(),
+        }
+    }
+
+    /// Answer a frame sent by the ground
+    fn answer(&mut self, mut frame: Vec<u8>, now: Timestamp) -> Vec<u8> {
+        let frame = wire::decode(&mut frame).unwrap();
+        let Header::Command(header) = frame.header else {
+            panic!("expected a command, got {:?}", frame.header);
+        };
+
+        let timeline = &mut self.timeline;
+        let reply =
+            self.dispatcher
+                .dispatch(header, frame.payload, now, |id, payload, response| {
+                    Timeline::router().handle(timeline, id, payload, response)
+                });
+
+        wire::to_vec_raw(&Header::Response(reply.header), reply.payload)
+    }
+
+    fn poll(&mut self, now: Timestamp) -> usize {
+        let (routes, logger) = (&self.routes, &mut self.logger);
+
+        self.timeline.poll(now, |id, payload, response| {
+            routes.handle(logger, id, payload, response)
+        })
+    }
+}
+
+/// Send a frame to the device and hand the response to the tracker
+fn exchange(device: &mut Device, tracker: &mut Tracker, frame: Vec<u8>, now: Timestamp) {
+    let mut response = device.answer(frame, now);
+    let frame = wire::decode(&mut response).unwrap();
+
+    assert!(tracker.receive(&frame));
+}
+
+fn at(seconds: u64) -> Timestamp {
+    Timestamp::from(SECOND * seconds as u32)
+}
+
+#[test]
+fn scheduled_commands_execute_when_due() {
+    let mut commander = Commander::new(RetryPolicy::default());
+    let mut tracker = Tracker::new();
+    let mut device = Device::new();
+
+    device.poll(at(10));
+
+    let (stop, frame) = tracker
+        .schedule(
+            &mut commander,
+            &StopLogging,
+            When::After(SECOND * 60),
+            at(10),
+        )
+        .unwrap();
+    exchange(&mut device, &mut tracker, frame, at(10));
+
+    let (start, frame) = tracker
+        .schedule(&mut commander, &StartLogging, When::At(at(15)), at(10))
+        .unwrap();
+    exchange(&mut device, &mut tracker, frame, at(10));
+
+    assert_eq!(tracker.get(start).unwrap().fate, Fate::Scheduled);
+    assert_eq!(device.timeline.next_due(), Some(at(15)));
+    assert_eq!(
+        device
+            .timeline
+            .items()
+            .map(|item| item.command)
+            .collect::<Vec<_>>(),
+        [StartLogging::ID, StopLogging::ID]
+    );
+
+    assert_eq!(device.poll(at(14)), 0);
+    assert!(!device.logger.logging);
+
+    assert_eq!(device.poll(at(15)), 1);
+    assert!(device.logger.logging);
+
+    assert_eq!(device.poll(at(70)), 1);
+    assert!(!device.logger.logging);
+    assert!(device.timeline.items().next().is_none());
+
+    while let Some(report) = device.timeline.next_report() {
+        assert_eq!(report.outcome, Outcome::Executed(Status::Ack));
+        tracker.report(&report);
+    }
+
+    assert_eq!(
+        tracker.get(start).unwrap().fate,
+        Fate::Executed(Status::Ack, at(15))
+    );
+    assert_eq!(
+        tracker.get(stop).unwrap().fate,
+        Fate::Executed(Status::Ack, at(70))
+    );
+    assert_eq!(tracker.get(stop).unwrap().scheduled.unwrap().due, at(70));
+
+    tracker.forget_final();
+    assert_eq!(tracker.iter().count(), 0);
+}
+
+#[test]
+fn refusals_of_handlers_are_reported() {
+    let mut commander = Commander::new(RetryPolicy::default());
+    let mut tracker = Tracker::new();
+    let mut device = Device::new();
+    device.logger.logging = true;
+
+    let (start, frame) = tracker
+        .schedule(&mut commander, &StartLogging, When::After(SECOND), at(0))
+        .unwrap();
+    exchange(&mut device, &mut tracker, frame, at(0));
+
+    device.poll(at(1));
+    tracker.report(&device.timeline.next_report().unwrap());
+
+    assert_eq!(
+        tracker.get(start).unwrap().fate,
+        Fate::Executed(Status::Nack(NackReason::Busy), at(1))
+    );
+}
+
+#[test]
+fn cancelled_commands_never_execute() {
+    let mut commander = Commander::new(RetryPolicy::default());
+    let mut tracker = Tracker::new();
+    let mut device = Device::new();
+
+    let (start, frame) = tracker
+        .schedule(&mut commander, &StartLogging, When::At(at(5)), at(0))
+        .unwrap();
+    exchange(&mut device, &mut tracker, frame, at(0));
+    let id = tracker.get(start).unwrap().scheduled.unwrap().id;
+
+    let frame = tracker.cancel(&mut commander, id, at(1)).unwrap();
+    exchange(&mut device, &mut tracker, frame, at(1));
+    assert_eq!(tracker.get(start).unwrap().fate, Fate::Cancelled);
+
+    assert_eq!(device.poll(at(10)), 0);
+    assert!(!device.logger.logging);
+
+    let report = device.timeline.next_report().unwrap();
+    assert_eq!(report.outcome, Outcome::Cancelled);
+    assert_eq!(report.finished, at(0));
+
+    // Cancelling again is refused, and changes nothing
+    let frame = tracker.cancel(&mut commander, id, at(11)).unwrap();
+    let mut response = device.answer(frame, at(11));
+    let response = wire::decode(&mut response).unwrap();
+    let Header::Response(header) = response.header else {
+        panic!("expected a response");
+    };
+    assert_eq!(header.status, Status::Nack(Error::UnknownItem.into()));
+    assert!(tracker.receive(&response));
+    assert_eq!(tracker.get(start).unwrap().fate, Fate::Cancelled);
+}
+
+#[test]
+fn device_refuses_what_it_can_not_schedule() {
+    let mut commander = Commander::new(RetryPolicy::default());
+    let mut tracker = Tracker::new();
+    let mut device = Device::new();
+    device.poll(at(10));
+
+    let (past, frame) = tracker
+        .schedule(&mut commander, &StartLogging, When::At(at(9)), at(10))
+        .unwrap();
+    exchange(&mut device, &mut tracker, frame, at(10));
+
+    let Fate::Refused(reason) = tracker.get(past).unwrap().fate else {
+        panic!("scheduling in the past was accepted");
+    };
+    assert_eq!(Error::from_reason(reason), Some(Error::Past));
+
+    for _ in 0..4 {
+        let (_, frame) = tracker
+            .schedule(&mut commander, &StopLogging, When::After(SECOND), at(10))
+            .unwrap();
+        exchange(&mut device, &mut tracker, frame, at(10));
+    }
+    let (full, frame) = tracker
+        .schedule(&mut commander, &StopLogging, When::After(SECOND), at(10))
+        .unwrap();
+    exchange(&mut device, &mut tracker, frame, at(10));
+    assert_eq!(
+        tracker.get(full).unwrap().fate,
+        Fate::Refused(Error::Full.into())
+    );
+
+    assert_eq!(
+        tracker
+            .schedule(
+                &mut commander,
+                &Annotate(vec![0; 40]),
+                When::After(SECOND),
+                at(10)
+            )
+            .unwrap_err(),
+        wire::Error::BufferTooSmall
+    );
+}
+
+#[test]
+fn lost_reports_are_recovered_by_query() {
+    let mut commander = Commander::new(RetryPolicy::default());
+    let mut tracker = Tracker::new();
+    let mut device = Device::new();
+
+    let (start, frame) = tracker
+        .schedule(&mut commander, &StartLogging, When::After(SECOND), at(0))
+        .unwrap();
+    exchange(&mut device, &mut tracker, frame, at(0));
+    let id = tracker.get(start).unwrap().scheduled.unwrap().id;
+
+    let frame = tracker.query(&mut commander, id, at(0)).unwrap();
+    exchange(&mut device, &mut tracker, frame, at(0));
+    assert_eq!(tracker.get(start).unwrap().fate, Fate::Scheduled);
+
+    device.poll(at(2));
+    // The report telemetry never reaches the ground
+    device.timeline.next_report().unwrap();
+
+    let frame = tracker.query(&mut commander, id, at(3)).unwrap();
+    exchange(&mut device, &mut tracker, frame, at(3));
+    assert_eq!(
+        tracker.get(start).unwrap().fate,
+        Fate::Executed(Status::Ack, at(2))
+    );
+
+    let frame = tracker
+        .query(&mut commander, ItemId::new(99), at(3))
+        .unwrap();
+    exchange(&mut device, &mut tracker, frame, at(3));
+}
+
+#[test]
+fn unanswered_requests_leave_the_fate_unknown() {
+    let mut commander = Commander::new(RetryPolicy {
+        timeout: SECOND,
+        retries: 0,
+    });
+    let mut tracker = Tracker::new();
+
+    let (start, _) = tracker
+        .schedule(&mut commander, &StartLogging, When::After(SECOND), at(0))
+        .unwrap();
+    assert_eq!(tracker.get(start).unwrap().fate, Fate::Requested);
+
+    while let Some(event) = commander.poll(at(5)) {
+        if let Event::Resolved(outcome) = event {
+            tracker.outcome(&outcome);
+        }
+    }
+
+    assert_eq!(tracker.get(start).unwrap().fate, Fate::Unknown);
+    assert_eq!(tracker.get(Sequence::new(7)), None);
+}
+
+#[test]
+fn schedule_is_listed_in_pages() {
+    let mut device = Device::new();
+    let mut commander = Commander::new(RetryPolicy::default());
+    let mut tracker = Tracker::new();
+
+    for seconds in [30, 10, 20] {
+        let (_, frame) = tracker
+            .schedule(&mut commander, &StopLogging, When::At(at(seconds)), at(0))
+            .unwrap();
+        exchange(&mut device, &mut tracker, frame, at(0));
+    }
+
+    let mut response = [0; 64];
+    let payload = postcard::to_allocvec(&ListScheduled { start: 1 }).unwrap();
+    let len = Timeline::router()
+        .handle(
+            &mut device.timeline,
+            CommandId::LIST_SCHEDULED,
+            &payload,
+            &mut response,
+        )
+        .unwrap();
+    let page: SchedulePage = postcard::from_bytes(&response[..len]).unwrap();
+
+    assert_eq!(page.total, 3);
+    assert_eq!(
+        page.items.iter().map(|item| item.due).collect::<Vec<_>>(),
+        [at(20), at(30)]
+    );
+}
@@ -0,0 +1,412 @@
+#![cfg(feature = "std")]
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use micromanager_tele::{
+    telecommand::{CommandHandler, Commander, Dispatcher, Event, RetryPolicy, Router},
+    time::Timestamp,
+    transfer::{
+        self, BlobId, BlobStorage, Download, Error, FinishUpload, Progress, StartDownload,
+        StartUpload, State, Transfers, Upload, UploadChunk,
+    },
+    wire::{self, Frame, Header},
+};
+
+const CALIBRATION: BlobId = BlobId::new(1);
+
+/// Blob storage of a device, in memory
+#[derive(Default)]
+struct Flash {
+    blobs: HashMap<BlobId, Vec<u8>>,
+    pending: HashMap<BlobId, Vec<u8>>,
+    /// Flip a bit of every chunk written, as failing memory would
+    corrupt: bool,
+}
+
+impl BlobStorage for Flash {
+    type Error = ();
+
+    fn len(&mut self, blob: BlobId) -> Option<u32> {
+        self.blobs.get(&blob).map(|data| data.len() as u32)
+    }
+
+    fn read(&mut self, blob: BlobId, offset: u32, buffer: &mut [u8]) -> Result<usize, ()> {
+        copy(self.blobs.get(&blob).ok_or(())?, offset, buffer)
+    }
+
+    fn read_pending(&mut self, blob: BlobId, offset: u32, buffer: &mut [u8]) -> Result<usize, ()> {
+        copy(self.pending.get(&blob).ok_or(())?, offset, buffer)
+    }
+
+    fn create(&mut self, blob: BlobId, len: u32) -> Result<(), ()> {
+        self.pending.insert(blob, vec![0; len as usize]);
+
+        Ok(())
+    }
+
+    fn write(&mut self, blob: BlobId, offset: u32, data: &[u8]) -> Result<(), ()> {
+        let pending = self.pending.get_mut(&blob).ok_or(())?;
+        let offset = offset as usize;
+        pending[offset..offset + data.len()].copy_from_slice(data);
+
+        if self.corrupt {
+            pending[offset] ^= 1;
+        }
+
+        Ok(())
+    }
+
+    fn commit(&mut self, blob: BlobId) -> Result<(), ()> {
+        let data = self.pending.remove(&blob).ok_or(())?;
+        self.blobs.insert(blob, data);
+
+        Ok(())
+    }
+}
+
+fn copy(data: &[u8], offset: u32, buffer: &mut [u8]) -> Result<usize, ()> {
+    let data = &data[offset as usize..];
+    let len = data.len().min(buffer.len());
+    buffer[..len].copy_from_slice(&data[..len]);
+
+    Ok(len)
+}
+
+/// Either direction of a transfer, to share the plumbing of the tests
+trait Transfer {
+    fn poll(&mut self, commander: &mut Commander<Vec<u8>>, now: Timestamp) -> Option<Vec<u8>>;
+    fn receive(&mut self, frame: &Frame<'_>) -> bool;
+    fn outcome(&mut self, event: &Event<Vec<u8>>);
+    fn state(&self) -> State;
+}
+
+macro_rules! transfer {
+    ($ty:ty) => {
+        impl Transfer for $ty {
+            fn poll(
+                &mut self,
+                commander: &mut Commander<Vec<u8>>,
+                now: Timestamp,
+            ) -> Option<Vec<u8>> {
+                <$ty>::poll(self, commander, now).unwrap()
+            }
+
+            fn receive(&mut self, frame: &Frame<'_>) -> bool {
+                <$ty>::receive(self, frame)
+            }
+
+            fn outcome(&mut self, event: &Event<Vec<u8>>) {
+                if let Event::Resolved(outcome) = event {
+                    <$ty>::outcome(self, outcome);
+                }
+            }
+
+            fn state(&self) -> State {
+                <$ty>::state(self)
+            }
+        }
+    };
+}
+
+transfer!(Upload);
+transfer!(Download);
+
+struct Device {
+    dispatcher: Dispatcher<8, 160>,
+    router: Router<Transfers<Flash>, 5>,
+    transfers: Transfers<Flash>,
+}
+
+impl Device {
+    fn new(flash: Flash) -> Self {
+        Self {
+            dispatcher: Dispatcher::new(),
+            router: Transfers::router(),
+            transfers: Transfers::new(flash),
+        }
+    }
+
+    /// Answer a frame sent by the ground
+    fn answer(&mut self, mut frame: Vec<u8>) -> Vec<u8> {
+        let frame = wire::decode(&mut frame).unwrap();
+        let Header::Command(header) = frame.header else {
+            panic!("expected a command, got {:?}", frame.header);
+        };
+
+        let (router, transfers) = (&self.router, &mut self.transfers);
+        let reply = self.dispatcher.dispatch(
+            header,
+            frame.payload,
+            Timestamp::ZERO,
+            |id, payload, response| router.handle(transfers, id, payload, response),
+        );
+
+        wire::to_vec_raw(&Header::Response(reply.header), reply.payload)
+    }
+
+    fn flash(&mut self) -> &mut Flash {
+        self.transfers.storage()
+    }
+}
+
+/// Exchange frames until the transfer stops, losing the responses for which
+/// `lose` returns true
+///
+/// Returns the number of frames sent by the ground.
+fn run(
+    commander: &mut Commander<Vec<u8>>,
+    device: &mut Device,
+    transfer: &mut impl Transfer,
+    mut lose: impl FnMut(usize) -> bool,
+) -> usize {
+    let mut now = Timestamp::ZERO;
+    let mut sent = 0;
+
+    while transfer.state() == State::Active {
+        let frames = transfer.poll(commander, now).into_iter().chain(
+            std::iter::from_fn(|| commander.poll(now)).filter_map(|event| {
+                transfer.outcome(&event);
+                match event {
+                    Event::Retransmit(frame) => Some(frame.to_wire()),
+                    _ => None,
+                }
+            }),
+        );
+        let frames = frames.collect::<Vec<_>>();
+
+        for frame in frames {
+            sent += 1;
+            let mut response = device.answer(frame);
+            if lose(sent) {
+                continue;
+            }
+
+            let frame = wire::decode(&mut response).unwrap();
+            if let Header::Response(header) = &frame.header {
+                commander.receive(header);
+            }
+            assert!(transfer.receive(&frame));
+        }
+
+        now = Timestamp::from_micros(now.as_micros() + 50_000);
+    }
+
+    sent
+}
+
+fn commander() -> Commander<Vec<u8>> {
+    Commander::new(RetryPolicy {
+        timeout: Duration::from_millis(100),
+        retries: 1,
+    })
+}
+
+fn blob(len: usize) -> Vec<u8> {
+    (0..len).map(|n| (n * 7) as u8).collect()
+}
+
+fn recorder() -> (Arc<Mutex<Vec<u32>>>, impl FnMut(Progress) + Send + 'static) {
+    let progress = Arc::new(Mutex::new(Vec::new()));
+    let callback = {
+        let progress = progress.clone();
+        move |update: Progress| progress.lock().unwrap().push(update.transferred)
+    };
+
+    (progress, callback)
+}
+
+#[test]
+fn upload_then_download() {
+    let mut commander = commander();
+    let mut device = Device::new(Flash::default());
+    let data = blob(300);
+
+    let (progress, callback) = recorder();
+    let mut upload = Upload::new(CALIBRATION, data.clone()).on_progress(callback);
+    let sent = run(&mut commander, &mut device, &mut upload, |_| false);
+
+    assert_eq!(upload.state(), State::Complete);
+    // Announcement, three chunks and verification
+    assert_eq!(sent, 5);
+    assert_eq!(*progress.lock().unwrap(), [0, 128, 256, 300]);
+    assert_eq!(upload.progress().fraction(), Some(1.0));
+    assert_eq!(device.flash().blobs[&CALIBRATION], data);
+
+    let (progress, callback) = recorder();
+    let mut download = Download::new(CALIBRATION).on_progress(callback);
+    assert_eq!(download.progress().total, None);
+    run(&mut commander, &mut device, &mut download, |_| false);
+
+    assert_eq!(download.state(), State::Complete);
+    assert_eq!(*progress.lock().unwrap(), [0, 128, 256, 300]);
+    assert_eq!(download.into_data(), data);
+}
+
+#[test]
+fn empty_blobs_transfer() {
+    let mut commander = commander();
+    let mut device = Device::new(Flash::default());
+
+    let mut upload = Upload::new(CALIBRATION, Vec::new());
+    run(&mut commander, &mut device, &mut upload, |_| false);
+    assert_eq!(upload.state(), State::Complete);
+
+    let mut download = Download::new(CALIBRATION);
+    run(&mut commander, &mut device, &mut download, |_| false);
+    assert_eq!(download.state(), State::Complete);
+    assert!(download.data().is_empty());
+}
+
+#[test]
+fn interrupted_upload_resumes() {
+    let mut commander = commander();
+    let mut device = Device::new(Flash::default());
+    let data = blob(600);
+    let mut upload = Upload::new(CALIBRATION, data.clone());
+
+    // The link fails after the second chunk arrived, eating its response
+    let sent = run(&mut commander, &mut device, &mut upload, |sent| sent >= 3);
+    assert_eq!(upload.state(), State::Interrupted);
+    assert_eq!(upload.progress().transferred, 128);
+    assert_eq!(device.transfers.upload(), Some((CALIBRATION, 256)));
+    // The chunk was retransmitted once, the device did not write it twice
+    assert_eq!(sent, 4);
+
+    upload.resume();
+    let sent = run(&mut commander, &mut device, &mut upload, |_| false);
+
+    assert_eq!(upload.state(), State::Complete);
+    // Announcement, the remaining three chunks and verification
+    assert_eq!(sent, 5);
+    assert_eq!(device.flash().blobs[&CALIBRATION], data);
+}
+
+#[test]
+fn upload_restarts_when_the_device_forgets_it() {
+    let mut commander = commander();
+    let mut device = Device::new(Flash::default());
+    let data = blob(300);
+    let mut upload = Upload::new(CALIBRATION, data.clone());
+
+    run(&mut commander, &mut device, &mut upload, |sent| sent >= 2);
+    assert_eq!(upload.state(), State::Interrupted);
+
+    // The device reset and lost the upload in progress
+    let flash = std::mem::take(device.flash());
+    let mut device = Device::new(flash);
+
+    upload.resume();
+    run(&mut commander, &mut device, &mut upload, |_| false);
+
+    assert_eq!(upload.state(), State::Complete);
+    assert_eq!(device.flash().blobs[&CALIBRATION], data);
+}
+
+#[test]
+fn corrupted_upload_is_not_committed() {
+    let mut commander = commander();
+    let mut device = Device::new(Flash {
+        corrupt: true,
+        ..Flash::default()
+    });
+    let mut upload = Upload::new(CALIBRATION, blob(200));
+
+    run(&mut commander, &mut device, &mut upload, |_| false);
+
+    let State::Failed(reason) = upload.state() else {
+        panic!("upload did not fail: {:?}", upload.state());
+    };
+    assert_eq!(Error::from_reason(reason), Some(Error::Checksum));
+    assert!(device.flash().blobs.is_empty());
+    assert_eq!(device.transfers.upload(), None);
+}
+
+#[test]
+fn uploads_replace_blobs_once_verified() {
+    let mut commander = commander();
+    let mut device = Device::new(Flash::default());
+    let old = blob(300);
+    let new: Vec<u8> = old.iter().map(|byte| !byte).collect();
+
+    let mut upload = Upload::new(CALIBRATION, old.clone());
+    run(&mut commander, &mut device, &mut upload, |_| false);
+
+    // Interrupted half way through replacing the blob
+    let mut upload = Upload::new(CALIBRATION, new.clone());
+    run(&mut commander, &mut device, &mut upload, |sent| sent >= 2);
+    assert_eq!(upload.state(), State::Interrupted);
+    assert_eq!(device.transfers.upload(), Some((CALIBRATION, 128)));
+
+    // Downloads still get the blob as it was
+    let mut download = Download::new(CALIBRATION);
+    run(&mut commander, &mut device, &mut download, |_| false);
+    assert_eq!(download.state(), State::Complete);
+    assert_eq!(download.into_data(), old);
+
+    upload.resume();
+    run(&mut commander, &mut device, &mut upload, |_| false);
+    assert_eq!(upload.state(), State::Complete);
+
+    let mut download = Download::new(CALIBRATION);
+    run(&mut commander, &mut device, &mut download, |_| false);
+    assert_eq!(download.into_data(), new);
+}
+
+#[test]
+fn unknown_blob_download_fails() {
+    let mut commander = commander();
+    let mut device = Device::new(Flash::default());
+    let mut download = Download::new(BlobId::new(7));
+
+    run(&mut commander, &mut device, &mut download, |_| false);
+
+    assert_eq!(download.state(), State::Failed(Error::UnknownBlob.into()));
+}
+
+#[test]
+fn device_refuses_chunks_out_of_order() {
+    let mut transfers = Transfers::new(Flash::default());
+    let data = blob(10);
+    let chunk = |offset: u32, data: &[u8]| UploadChunk {
+        blob: CALIBRATION,
+        offset,
+        data: data.try_into().unwrap(),
+    };
+
+    assert_eq!(
+        transfers.handle(chunk(0, &data[..5])),
+        Err(Error::UnknownBlob)
+    );
+
+    let start = StartUpload {
+        blob: CALIBRATION,
+        len: 10,
+        checksum: transfer::checksum(&data),
+    };
+    assert_eq!(transfers.handle(start), Ok(0));
+    assert_eq!(transfers.handle(chunk(0, &data[..5])), Ok(5));
+    assert_eq!(
+        transfers.handle(chunk(0, &data[..5])),
+        Err(Error::BadOffset)
+    );
+    assert_eq!(transfers.handle(chunk(5, &[0; 6])), Err(Error::BadOffset));
+    assert_eq!(
+        transfers.handle(FinishUpload { blob: CALIBRATION }),
+        Err(Error::BadOffset)
+    );
+
+    // Announcing the same blob again resumes it
+    assert_eq!(transfers.handle(start), Ok(5));
+    assert_eq!(transfers.handle(chunk(5, &data[5..])), Ok(10));
+    assert_eq!(transfers.handle(FinishUpload { blob: CALIBRATION }), Ok(()));
+
+    let info = transfers
+        .handle(StartDownload { blob: CALIBRATION })
+        .unwrap();
+    assert_eq!(info.len, 10);
+    assert_eq!(info.checksum, start.checksum);
+}
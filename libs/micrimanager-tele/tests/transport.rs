@@ -0,0 +1,125 @@
+#![cfg(feature = "std")]
+
+use std::{
+    io::Write,
+    net::{TcpListener, UdpSocket},
+    thread,
+    time::{Duration, Instant},
+};
+
+use micromanager_tele::{
+    telecommand::{Commander, Outcome, ResponseHeader, RetryPolicy, Status, Telecommand},
+    time::Timestamp,
+    transport::{loopback, Tcp, Transport, Udp},
+    wire::{self, Header},
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Telecommand)]
+#[telecommand(id = 1, response = u32)]
+struct Add(u32, u32);
+
+fn recv_blocking(transport: &mut impl Transport) -> Vec<u8> {
+    let deadline = Instant::now() + Duration::from_secs(5);
+
+    loop {
+        if let Some(frame) = transport.recv().unwrap() {
+            return frame;
+        }
+
+        assert!(Instant::now() < deadline, "no frame received");
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+#[test]
+fn loopback_command_round_trip() {
+    let (mut ground, mut device) = loopback::pair();
+    let mut commander = Commander::new(RetryPolicy::default());
+
+    let frame = commander
+        .issue_command(&Add(2, 3), Timestamp::ZERO)
+        .unwrap();
+    ground.send(&frame).unwrap();
+
+    let mut received = recv_blocking(&mut device);
+    let command = wire::decode(&mut received).unwrap();
+    let issued = match command.header {
+        Header::Command(header) => header,
+        header => panic!("expected a command, got {:?}", header),
+    };
+    let Add(a, b) = command.decode_payload().unwrap();
+
+    let response = Header::Response(ResponseHeader {
+        sequence: issued.sequence,
+        status: Status::Ack,
+    });
+    device
+        .send(&wire::to_vec(&response, &(a + b)).unwrap())
+        .unwrap();
+
+    let mut received = recv_blocking(&mut ground);
+    let response = wire::decode(&mut received).unwrap();
+    let header = match response.header {
+        Header::Response(header) => header,
+        header => panic!("expected a response, got {:?}", header),
+    };
+
+    assert_eq!(
+        commander.receive(&header),
+        Some(Outcome::Acknowledged(issued))
+    );
+    assert_eq!(response.decode_payload::<u32>().unwrap(), 5);
+    assert!(commander.is_idle());
+}
+
+#[test]
+fn loopback_reports_dropped_peer() {
+    let (mut ground, device) = loopback::pair();
+    drop(device);
+
+    assert!(ground.send(&[0x01, 0x00]).is_err());
+    assert!(ground.recv().is_err());
+}
+
+#[test]
+fn tcp_reassembles_split_frames() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+
+    let device = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+
+        // A flush, a frame split over two writes, then two frames in one write
+        stream.write_all(&[0x00, 0x02, 0x01]).unwrap();
+        stream.flush().unwrap();
+        thread::sleep(Duration::from_millis(20));
+        stream
+            .write_all(&[0x00, 0x02, 0x02, 0x00, 0x02, 0x03, 0x00])
+            .unwrap();
+    });
+
+    let mut ground = Tcp::connect(address).unwrap();
+
+    assert_eq!(recv_blocking(&mut ground), [0x02, 0x01, 0x00]);
+    assert_eq!(recv_blocking(&mut ground), [0x02, 0x02, 0x00]);
+    assert_eq!(recv_blocking(&mut ground), [0x02, 0x03, 0x00]);
+
+    device.join().unwrap();
+}
+
+#[test]
+fn udp_round_trip() {
+    let a = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let b = UdpSocket::bind("127.0.0.1:0").unwrap();
+    a.connect(b.local_addr().unwrap()).unwrap();
+    b.connect(a.local_addr().unwrap()).unwrap();
+
+    let mut a = Udp::from_socket(a).unwrap();
+    let mut b = Udp::from_socket(b).unwrap();
+
+    assert_eq!(a.recv().unwrap(), None);
+
+    a.send(&[0x02, 0x01, 0x00]).unwrap();
+    assert_eq!(recv_blocking(&mut b), [0x02, 0x01, 0x00]);
+}
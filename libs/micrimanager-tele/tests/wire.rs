@@ -0,0 +1,205 @@
+//! Golden byte vectors for the wire format
+//!
+//! If any of these fail, the wire format changed and devices built against an
+//! older version of this crate will no longer be understood.
+
+use micromanager_tele::{
+    telecommand::{CommandHeader, CommandId, NackReason, ResponseHeader, Sequence, Status},
+    telemetry::{ChannelId, Telemetry, TelemetryHeader},
+    time::Timestamp,
+    wire::{self, Decoder, Encoder, Error, Header},
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Telemetry)]
+#[telemetry(channel = 1)]
+struct Magnetometer {
+    x: f32,
+    y: f32,
+    z: f32,
+}
+
+const TELEMETRY: &[u8] = &[
+    0x03, 0x01, 0x01, 0x02, 0x02, 0x04, 0x40, 0x42, 0x0f, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x03,
+    0x80, 0x3f, 0x01, 0x01, 0x02, 0xbf, 0x01, 0x01, 0x01, 0x03, 0xdf, 0x3d, 0x00,
+];
+const COMMAND: &[u8] = &[
+    0x03, 0x02, 0x07, 0x07, 0x02, 0x01, 0xac, 0x02, 0x65, 0xfb, 0x00,
+];
+const ACK: &[u8] = &[0x03, 0x03, 0x07, 0x01, 0x04, 0x01, 0xd2, 0xbe, 0x00];
+const NACK: &[u8] = &[0x03, 0x03, 0x07, 0x05, 0x04, 0x09, 0x1e, 0xf3, 0x00];
+
+fn encode<T: Serialize + ?Sized>(header: &Header, payload: &T) -> Vec<u8> {
+    Encoder::<512>::new()
+        .encode(header, payload)
+        .unwrap()
+        .to_vec()
+}
+
+fn telemetry_header() -> Header {
+    Header::Telemetry(TelemetryHeader {
+        channel: Magnetometer::CHANNEL,
+        sequence: 2,
+        timestamp: Timestamp::from_micros(1_000_000),
+    })
+}
+
+fn command_header() -> Header {
+    Header::Command(CommandHeader {
+        sequence: Sequence::new(7),
+        command: CommandId::new(0x0102),
+    })
+}
+
+fn response_header(status: Status) -> Header {
+    Header::Response(ResponseHeader {
+        sequence: Sequence::new(7),
+        status,
+    })
+}
+
+fn magnetometer() -> Magnetometer {
+    Magnetometer {
+        x: 1.0,
+        y: -0.5,
+        z: 0.0,
+    }
+}
+
+#[test]
+fn encode_golden() {
+    assert_eq!(encode(&telemetry_header(), &magnetometer()), TELEMETRY);
+    assert_eq!(encode(&command_header(), &300u32), COMMAND);
+    assert_eq!(encode(&response_header(Status::Ack), &true), ACK);
+    assert_eq!(
+        encode(&response_header(Status::Nack(NackReason::Failed(9))), &()),
+        NACK
+    );
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn encode_to_vec() {
+    assert_eq!(
+        wire::to_vec(&telemetry_header(), &magnetometer()).unwrap(),
+        TELEMETRY
+    );
+}
+
+#[test]
+fn encode_into_slices() {
+    let mut scratch = [0; 64];
+    let mut out = [0; wire::max_encoded_len(12)];
+
+    let frame = wire::encode(&telemetry_header(), &magnetometer(), &mut scratch, &mut out);
+
+    assert_eq!(frame.unwrap(), TELEMETRY);
+}
+
+#[test]
+fn decode_golden() {
+    let mut buffer = TELEMETRY.to_vec();
+    let frame = wire::decode(&mut buffer).unwrap();
+    assert_eq!(frame.header, telemetry_header());
+    assert_eq!(
+        frame.decode_payload::<Magnetometer>().unwrap(),
+        magnetometer()
+    );
+
+    let mut buffer = COMMAND.to_vec();
+    let frame = wire::decode(&mut buffer).unwrap();
+    assert_eq!(frame.header, command_header());
+    assert_eq!(frame.decode_payload::<u32>().unwrap(), 300);
+
+    let mut buffer = ACK.to_vec();
+    let frame = wire::decode(&mut buffer).unwrap();
+    assert_eq!(frame.header, response_header(Status::Ack));
+    assert!(frame.decode_payload::<bool>().unwrap());
+
+    let mut buffer = NACK.to_vec();
+    let frame = wire::decode(&mut buffer).unwrap();
+    assert_eq!(
+        frame.header,
+        response_header(Status::Nack(NackReason::Failed(9)))
+    );
+}
+
+#[test]
+fn decode_without_delimiter() {
+    let mut buffer = COMMAND[..COMMAND.len() - 1].to_vec();
+
+    assert_eq!(wire::decode(&mut buffer).unwrap().header, command_header());
+}
+
+#[test]
+fn corrupt_frames_are_rejected() {
+    for index in 0..TELEMETRY.len() - 1 {
+        for bit in 0..8 {
+            let mut buffer = TELEMETRY.to_vec();
+            buffer[index] ^= 1 << bit;
+
+            assert!(
+                wire::decode(&mut buffer).is_err(),
+                "bit {} of byte {} flipped",
+                bit,
+                index
+            );
+        }
+    }
+}
+
+#[test]
+fn unknown_channel_round_trips() {
+    let header = Header::Telemetry(TelemetryHeader {
+        channel: ChannelId::new(u16::MAX),
+        sequence: u16::MAX,
+        timestamp: Timestamp::from_micros(u64::MAX),
+    });
+
+    let mut buffer = encode(&header, &[0u8; 300][..]);
+    let frame = wire::decode(&mut buffer).unwrap();
+
+    assert_eq!(frame.header, header);
+    assert_eq!(frame.decode_payload::<&[u8]>().unwrap(), &[0u8; 300][..]);
+}
+
+#[test]
+fn truncated_frames_are_rejected() {
+    let mut buffer = [0x01, wire::DELIMITER];
+
+    assert_eq!(wire::decode(&mut buffer), Err(Error::Truncated));
+}
+
+#[test]
+fn decode_stream() {
+    let mut decoder = Decoder::<64>::new();
+    let mut headers = Vec::new();
+
+    let stream = [
+        &[wire::DELIMITER][..],
+        TELEMETRY,
+        COMMAND,
+        &[0x42; 80],
+        &[wire::DELIMITER],
+        ACK,
+        NACK,
+    ]
+    .concat();
+
+    for byte in stream {
+        if let Some(frame) = decoder.push(byte) {
+            headers.push(frame.map(|frame| frame.header));
+        }
+    }
+
+    assert_eq!(
+        headers,
+        [
+            Ok(telemetry_header()),
+            Ok(command_header()),
+            Err(Error::BufferTooSmall),
+            Ok(response_header(Status::Ack)),
+            Ok(response_header(Status::Nack(NackReason::Failed(9)))),
+        ]
+    );
+}
@@ -0,0 +1,161 @@
+#![forbid(unsafe_code)]
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::{
+    parse::{Parse, ParseStream},
+    parse_macro_input,
+    punctuated::Punctuated,
+    DeriveInput, Error, Ident, LitInt, Result, Token, Type,
+};
+
+mod schema;
+
+/// Implement `micromanager_tele::telemetry::Telemetry` for a type
+///
+/// The channel the packet is sent over must be provided with the
+/// `#[telemetry(channel = <u16>)]` attribute.
+#[proc_macro_derive(Telemetry, attributes(telemetry))]
+pub fn derive_telemetry(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    expand_telemetry(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Implement `micromanager_tele::telecommand::Telecommand` for a type
+///
+/// The id of the command and the type of its response must be provided with
+/// the `#[telecommand(id = <u16>, response = <type>)]` attribute.
+#[proc_macro_derive(Telecommand, attributes(telecommand))]
+pub fn derive_telecommand(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    expand_telecommand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand_telemetry(input: DeriveInput) -> Result<proc_macro2::TokenStream> {
+    let mut channel = None;
+
+    for argument in arguments(&input, "telemetry")? {
+        match argument {
+            Argument::Int(key, value) if key == "channel" => set(&mut channel, key, value)?,
+            argument => return Err(argument.unknown()),
+        }
+    }
+
+    let channel = require(channel, "#[telemetry(channel = ...)]")?;
+    let schema = schema::describe(&input)?;
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::micromanager_tele::telemetry::Telemetry for #name #ty_generics #where_clause {
+            const CHANNEL: ::micromanager_tele::telemetry::ChannelId =
+                ::micromanager_tele::telemetry::ChannelId::new(#channel);
+            const NAME: &'static str = ::core::stringify!(#name);
+            const SCHEMA: u32 = ::micromanager_tele::handshake::schema_hash(#schema);
+        }
+    })
+}
+
+fn expand_telecommand(input: DeriveInput) -> Result<proc_macro2::TokenStream> {
+    let mut id = None;
+    let mut response = None;
+
+    for argument in arguments(&input, "telecommand")? {
+        match argument {
+            Argument::Int(key, value) if key == "id" => set(&mut id, key, value)?,
+            Argument::Type(key, value) if key == "response" => set(&mut response, key, *value)?,
+            argument => return Err(argument.unknown()),
+        }
+    }
+
+    let id = require(id, "#[telecommand(id = ...)]")?;
+    let response = require(response, "#[telecommand(response = ...)]")?;
+    let schema = format!(
+        "{}->{}",
+        schema::describe(&input)?,
+        schema::canonical(&quote!(#response))
+    );
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::micromanager_tele::telecommand::Telecommand for #name #ty_generics #where_clause {
+            const ID: ::micromanager_tele::telecommand::CommandId =
+                ::micromanager_tele::telecommand::CommandId::new(#id);
+            const NAME: &'static str = ::core::stringify!(#name);
+            const SCHEMA: u32 = ::micromanager_tele::handshake::schema_hash(#schema);
+
+            type Response = #response;
+        }
+    })
+}
+
+/// A single `key = value` pair of a derive attribute
+enum Argument {
+    Int(Ident, u16),
+    Type(Ident, Box<Type>),
+}
+
+impl Argument {
+    fn unknown(&self) -> Error {
+        let key = match self {
+            Argument::Int(key, _) | Argument::Type(key, _) => key,
+        };
+
+        Error::new_spanned(key, format!("unknown argument `{}`", key))
+    }
+}
+
+impl Parse for Argument {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let key: Ident = input.parse()?;
+        input.parse::<Token![=]>()?;
+
+        if key == "response" {
+            Ok(Argument::Type(key, Box::new(input.parse()?)))
+        } else {
+            Ok(Argument::Int(key, input.parse::<LitInt>()?.base10_parse()?))
+        }
+    }
+}
+
+fn arguments(input: &DeriveInput, attribute: &str) -> Result<Vec<Argument>> {
+    let mut arguments = Vec::new();
+
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident(attribute))
+    {
+        arguments
+            .extend(attr.parse_args_with(Punctuated::<Argument, Token![,]>::parse_terminated)?);
+    }
+
+    Ok(arguments)
+}
+
+fn set<T>(slot: &mut Option<T>, key: Ident, value: T) -> Result<()> {
+    if slot.replace(value).is_some() {
+        return Err(Error::new_spanned(&key, format!("duplicate `{}`", key)));
+    }
+
+    Ok(())
+}
+
+fn require<T>(value: Option<T>, attribute: &str) -> Result<T> {
+    value.ok_or_else(|| {
+        Error::new(
+            Span::call_site(),
+            format!("missing `{}` attribute", attribute),
+        )
+    })
+}
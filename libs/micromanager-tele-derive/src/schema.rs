@@ -0,0 +1,63 @@
+use proc_macro2::TokenStream;
+use quote::{quote, ToTokens};
+use syn::{Attribute, Data, DeriveInput, Error, Fields, Result};
+
+/// Describe the shape of a type, in a form that stays the same across
+/// compilers and that ignores changes which do not affect serialization
+pub fn describe(input: &DeriveInput) -> Result<String> {
+    let ident = &input.ident;
+    let generics = &input.generics;
+
+    let tokens = match &input.data {
+        Data::Struct(data) => {
+            let fields = fields(&data.fields);
+
+            quote!(struct #ident #generics #fields)
+        }
+        Data::Enum(data) => {
+            let variants = data.variants.iter().map(|variant| {
+                let attrs = attributes(&variant.attrs);
+                let ident = &variant.ident;
+                let fields = fields(&variant.fields);
+
+                quote!(#(#attrs)* #ident #fields,)
+            });
+
+            quote!(enum #ident #generics { #(#variants)* })
+        }
+        Data::Union(_) => return Err(Error::new_spanned(input, "unions are not supported")),
+    };
+
+    Ok(canonical(&tokens))
+}
+
+/// Token stream with all whitespace removed, as its spacing is not stable
+pub fn canonical(tokens: &impl ToTokens) -> String {
+    tokens
+        .to_token_stream()
+        .to_string()
+        .split_whitespace()
+        .collect()
+}
+
+fn fields(fields: &Fields) -> TokenStream {
+    let described = fields.iter().map(|field| {
+        let attrs = attributes(&field.attrs);
+        let ident = &field.ident;
+        let colon = &field.colon_token;
+        let ty = &field.ty;
+
+        quote!(#(#attrs)* #ident #colon #ty,)
+    });
+
+    match fields {
+        Fields::Named(_) => quote!({ #(#described)* }),
+        Fields::Unnamed(_) => quote!(( #(#described)* )),
+        Fields::Unit => quote!(),
+    }
+}
+
+/// Attributes that may change serialization, which excludes documentation
+fn attributes(attrs: &[Attribute]) -> impl Iterator<Item = &Attribute> {
+    attrs.iter().filter(|attr| !attr.path.is_ident("doc"))
+}